@@ -0,0 +1,131 @@
+use crate::ray::Ray;
+use crate::vector_3d::Vector3D;
+
+#[cfg(test)]
+use assert_approx_eq::assert_approx_eq;
+
+// Axis-aligned bounding box
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    min: Vector3D,
+    max: Vector3D,
+}
+
+impl Aabb {
+    pub fn new(min: Vector3D, max: Vector3D) -> Aabb {
+        return Aabb { min: min, max: max };
+    }
+
+    pub fn min(&self) -> Vector3D {
+        return self.min;
+    }
+
+    pub fn max(&self) -> Vector3D {
+        return self.max;
+    }
+
+    pub fn center(&self) -> Vector3D {
+        return (self.min + self.max) * 0.5;
+    }
+
+    // Vector from min to max, whose length is the size of the longest straight line in the box
+    pub fn diagonal(&self) -> Vector3D {
+        return self.max - self.min;
+    }
+
+    // Smallest box containing both boxes
+    pub fn surrounding(&self, other: &Aabb) -> Aabb {
+        return Aabb::new(
+            self.min.component_min(&other.min),
+            self.max.component_max(&other.max),
+        );
+    }
+
+    // Slab test, checks if the ray overlaps the box on every axis within [t_min, t_max]
+    pub fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> bool {
+        return self.hit_range(ray, t_min, t_max).is_some();
+    }
+
+    // Part of [t_min, t_max] where the ray is inside the box, None if it misses
+    pub fn hit_range(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<(f64, f64)> {
+        let mut t_min = t_min;
+        let mut t_max = t_max;
+
+        for axis in 0..3 {
+            let inverse_direction = 1.0 / ray.direction[axis];
+            let mut t0 = (self.min[axis] - ray.origin[axis]) * inverse_direction;
+            let mut t1 = (self.max[axis] - ray.origin[axis]) * inverse_direction;
+
+            if inverse_direction < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+
+            t_min = if t0 > t_min { t0 } else { t_min };
+            t_max = if t1 < t_max { t1 } else { t_max };
+
+            if t_max <= t_min {
+                return None;
+            }
+        }
+
+        return Some((t_min, t_max));
+    }
+}
+
+#[test]
+fn test_aabb_hit() {
+    let aabb = Aabb::new(
+        Vector3D::new(-1.0, -1.0, -1.0),
+        Vector3D::new(1.0, 1.0, 1.0),
+    );
+
+    let ray = Ray::new(Vector3D::new(0.0, 0.0, 5.0), Vector3D::new(0.0, 0.0, -1.0));
+    assert!(aabb.hit(&ray, 0.001, f64::MAX));
+
+    let ray = Ray::new(Vector3D::new(2.0, 0.0, 5.0), Vector3D::new(0.0, 0.0, -1.0));
+    assert!(!aabb.hit(&ray, 0.001, f64::MAX));
+
+    // Pointing away from the box
+    let ray = Ray::new(Vector3D::new(0.0, 0.0, 5.0), Vector3D::new(0.0, 0.0, 1.0));
+    assert!(!aabb.hit(&ray, 0.001, f64::MAX));
+}
+
+#[test]
+fn test_aabb_hit_range() {
+    let aabb = Aabb::new(
+        Vector3D::new(-1.0, -1.0, -1.0),
+        Vector3D::new(1.0, 1.0, 1.0),
+    );
+
+    let ray = Ray::new(Vector3D::new(0.0, 0.0, 5.0), Vector3D::new(0.0, 0.0, -1.0));
+    let (t_enter, t_exit) = aabb.hit_range(&ray, 0.001, f64::MAX).unwrap();
+    assert_approx_eq!(t_enter, 4.0);
+    assert_approx_eq!(t_exit, 6.0);
+
+    // Starting inside the box the range begins at t_min
+    let ray = Ray::new(Vector3D::new(0.0, 0.0, 0.0), Vector3D::new(0.0, 0.0, -1.0));
+    let (t_enter, t_exit) = aabb.hit_range(&ray, 0.001, f64::MAX).unwrap();
+    assert_approx_eq!(t_enter, 0.001);
+    assert_approx_eq!(t_exit, 1.0);
+}
+
+#[test]
+fn test_aabb_surrounding() {
+    let aabb_1 = Aabb::new(Vector3D::new(-1.0, 0.0, 0.0), Vector3D::new(0.0, 1.0, 1.0));
+    let aabb_2 = Aabb::new(Vector3D::new(0.0, -2.0, 0.5), Vector3D::new(3.0, 0.0, 0.75));
+    let union = aabb_1.surrounding(&aabb_2);
+
+    assert_approx_eq!(union.min().get_x(), -1.0);
+    assert_approx_eq!(union.min().get_y(), -2.0);
+    assert_approx_eq!(union.min().get_z(), 0.0);
+    assert_approx_eq!(union.max().get_x(), 3.0);
+    assert_approx_eq!(union.max().get_y(), 1.0);
+    assert_approx_eq!(union.max().get_z(), 1.0);
+}
+
+#[test]
+fn test_aabb_center_diagonal() {
+    let aabb = Aabb::new(Vector3D::new(-1.0, 0.0, 2.0), Vector3D::new(3.0, 1.0, 4.0));
+    assert_eq!(aabb.center(), Vector3D::new(1.0, 0.5, 3.0));
+    assert_eq!(aabb.diagonal(), Vector3D::new(4.0, 1.0, 2.0));
+}
@@ -1,71 +1,958 @@
-use palette::Srgb;
+use serde::{Deserialize, Serialize};
+
+use palette::{LinSrgb, Srgb};
 
 use rand::Rng;
 
 use crate::camera::Camera;
+use crate::color::{linear_to_srgb, luminance};
+use crate::filter::ReconstructionFilter;
+use crate::sampler::{Sample, SampleGenerator};
+use crate::tone_map::ToneMap;
 use crate::world::World;
 
+#[cfg(test)]
+use crate::aabb::Aabb;
+#[cfg(test)]
+use crate::background::Background;
+#[cfg(test)]
+use crate::camera::{CameraConfig, CameraSettings};
+#[cfg(test)]
+use crate::material::{DiffuseLight, Lambertian, Material, Metal};
+#[cfg(test)]
+use crate::object::{Object, ObjectHitRecord};
+#[cfg(test)]
+use crate::plane::Plane;
+#[cfg(test)]
+use crate::ray::Ray;
+#[cfg(test)]
+use crate::sphere::Sphere;
+#[cfg(test)]
+use crate::vector_3d::Vector3D;
+#[cfg(test)]
+use assert_approx_eq::assert_approx_eq;
+#[cfg(test)]
+use std::sync::Arc;
+#[cfg(test)]
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Number of samples taken between convergence checks in adaptive mode
+const ADAPTIVE_BATCH_SIZE: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
 pub enum AntiAliasingTechnique {
     SuperSampling, // Simple supersampling
     MonteCarlo,    // Advanced Monte Carlo sampling
     Spatial,       // Spatial anti-aliasing
     Temporal,      // Temporal anti-aliasing
+    Adaptive,      // Supersampling that stops once the pixel estimate converges
     None,
 }
 
+// Settings left out of a scene file take the same defaults as AntiAliasing::new
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct AntiAliasing {
     samples_per_pixel: usize,
     technique: AntiAliasingTechnique,
+    #[serde(default = "default_tone_map")]
+    tone_map: ToneMap,
+    #[serde(default = "default_adaptive_tolerance")]
+    adaptive_tolerance: f32, // Variance of the pixel estimate at which adaptive sampling stops
+    #[serde(default = "default_max_depth")]
+    max_depth: i32, // Maximum number of bounces traced per sample
+    #[serde(default = "default_stratified")]
+    stratified: bool, // Spread supersamples over a grid of cells within the pixel
+    #[serde(default = "default_filter")]
+    filter: ReconstructionFilter, // Weighs supersamples by their offset from the pixel center
+    #[serde(default)]
+    max_luminance: Option<f32>, // Brightest a single sample may be, None leaves samples as traced
+}
+
+fn default_tone_map() -> ToneMap {
+    return ToneMap::Clamp;
+}
+
+fn default_adaptive_tolerance() -> f32 {
+    return 1e-4;
+}
+
+fn default_max_depth() -> i32 {
+    return 50;
+}
+
+fn default_stratified() -> bool {
+    return true;
+}
+
+fn default_filter() -> ReconstructionFilter {
+    return ReconstructionFilter::Box;
+}
+
+impl Default for AntiAliasing {
+    // 16 stratified supersamples per pixel
+    fn default() -> Self {
+        AntiAliasing::new(16, AntiAliasingTechnique::SuperSampling)
+    }
+}
+
+// Fluent alternative to AntiAliasing::new plus setters, starting from the defaults
+// e.g. AntiAliasingBuilder::new().samples_per_pixel(64).max_depth(8).build()
+#[derive(Debug, Clone)]
+pub struct AntiAliasingBuilder {
+    anti_aliasing: AntiAliasing,
+}
+
+impl AntiAliasingBuilder {
+    pub fn new() -> AntiAliasingBuilder {
+        return AntiAliasingBuilder {
+            anti_aliasing: AntiAliasing::default(),
+        };
+    }
+
+    pub fn samples_per_pixel(mut self, samples_per_pixel: usize) -> AntiAliasingBuilder {
+        self.anti_aliasing.samples_per_pixel = samples_per_pixel;
+        return self;
+    }
+
+    pub fn technique(mut self, technique: AntiAliasingTechnique) -> AntiAliasingBuilder {
+        self.anti_aliasing.technique = technique;
+        return self;
+    }
+
+    pub fn tone_map(mut self, tone_map: ToneMap) -> AntiAliasingBuilder {
+        self.anti_aliasing.tone_map = tone_map;
+        return self;
+    }
+
+    pub fn adaptive_tolerance(mut self, tolerance: f32) -> AntiAliasingBuilder {
+        self.anti_aliasing.adaptive_tolerance = tolerance;
+        return self;
+    }
+
+    pub fn max_depth(mut self, max_depth: i32) -> AntiAliasingBuilder {
+        self.anti_aliasing.max_depth = max_depth;
+        return self;
+    }
+
+    pub fn stratified(mut self, stratified: bool) -> AntiAliasingBuilder {
+        self.anti_aliasing.stratified = stratified;
+        return self;
+    }
+
+    pub fn filter(mut self, filter: ReconstructionFilter) -> AntiAliasingBuilder {
+        self.anti_aliasing.filter = filter;
+        return self;
+    }
+
+    pub fn max_luminance(mut self, max_luminance: f32) -> AntiAliasingBuilder {
+        assert!(max_luminance > 0.0, "Maximum luminance must be positive");
+        self.anti_aliasing.max_luminance = Some(max_luminance);
+        return self;
+    }
+
+    pub fn build(self) -> AntiAliasing {
+        return self.anti_aliasing;
+    }
+}
+
+impl Default for AntiAliasingBuilder {
+    fn default() -> Self {
+        AntiAliasingBuilder::new()
+    }
 }
 
 impl AntiAliasing {
+    // Shortcut for the common case, see AntiAliasingBuilder for the other settings
     pub fn new(samples_per_pixel: usize, technique: AntiAliasingTechnique) -> Self {
         return AntiAliasing {
             samples_per_pixel: samples_per_pixel,
             technique: technique,
+            tone_map: default_tone_map(),
+            adaptive_tolerance: default_adaptive_tolerance(),
+            max_depth: default_max_depth(),
+            stratified: default_stratified(),
+            filter: default_filter(),
+            max_luminance: None,
+        };
+    }
+
+    // Copy of these settings with a different sample count, e.g. for a quick preview pass
+    pub fn with_samples(&self, samples_per_pixel: usize) -> AntiAliasing {
+        return AntiAliasing {
+            samples_per_pixel: samples_per_pixel,
+            ..self.clone()
         };
     }
 
+    pub fn samples_per_pixel(&self) -> usize {
+        return self.samples_per_pixel;
+    }
+
+    pub fn technique(&self) -> AntiAliasingTechnique {
+        return self.technique;
+    }
+
+    pub fn builder() -> AntiAliasingBuilder {
+        return AntiAliasingBuilder::new();
+    }
+
+    pub fn tone_map(&self) -> ToneMap {
+        return self.tone_map;
+    }
+
+    pub fn adaptive_tolerance(&self) -> f32 {
+        return self.adaptive_tolerance;
+    }
+
+    // Select how accumulated radiance is mapped into [0, 1] before sRGB encoding
+    pub fn set_tone_map(&mut self, tone_map: ToneMap) {
+        self.tone_map = tone_map;
+    }
+
+    // Stop adaptive sampling once the variance of the pixel estimate drops below this value
+    pub fn set_adaptive_tolerance(&mut self, tolerance: f32) {
+        self.adaptive_tolerance = tolerance;
+    }
+
+    // Limit how many times a ray may bounce, e.g. lower for diffuse scenes or higher for deep glass
+    pub fn set_max_depth(&mut self, max_depth: i32) {
+        self.max_depth = max_depth;
+    }
+
+    pub fn max_depth(&self) -> i32 {
+        return self.max_depth;
+    }
+
+    // Toggle stratified supersampling, where each sample is jittered within its own cell of a
+    // grid over the pixel instead of anywhere in the pixel, which reduces noise at edges.
+    // Points on the lens are stratified the same way, which also smooths depth of field
+    pub fn set_stratified(&mut self, stratified: bool) {
+        self.stratified = stratified;
+    }
+
+    pub fn stratified(&self) -> bool {
+        return self.stratified;
+    }
+
+    // Select how supersamples are weighed when averaged. Box is a plain average, Tent and
+    // Gaussian favor samples near the pixel center, which gives sharper edges for the same
+    // sample count. Adaptive sampling always averages plainly
+    pub fn set_filter(&mut self, filter: ReconstructionFilter) {
+        self.filter = filter;
+    }
+
+    pub fn filter(&self) -> ReconstructionFilter {
+        return self.filter;
+    }
+
+    // Scale down any sample brighter than max_luminance before it is averaged, which removes
+    // fireflies (lone, very bright pixels from rare light paths) at the cost of darkening
+    // genuinely bright highlights. None, the default, leaves every sample as traced
+    pub fn set_max_luminance(&mut self, max_luminance: Option<f32>) {
+        if let Some(max_luminance) = max_luminance {
+            assert!(max_luminance > 0.0, "Maximum luminance must be positive");
+        }
+        self.max_luminance = max_luminance;
+    }
+
+    pub fn max_luminance(&self) -> Option<f32> {
+        return self.max_luminance;
+    }
+
     pub fn anti_alias(&self, x: usize, y: usize, camera: &Camera, world: &World) -> Srgb {
-        let mut pixel_colors: Vec<f32> = vec![0.0; 3];
-        let mut color = Srgb::new(0.0, 0.0, 0.0);
+        return self.anti_alias_with_count(x, y, camera, world).0;
+    }
+
+    // Same as anti_alias, but also reports how many samples the pixel received
+    pub fn anti_alias_with_count(
+        &self,
+        x: usize,
+        y: usize,
+        camera: &Camera,
+        world: &World,
+    ) -> (Srgb, usize) {
+        let (radiance, sample_count) = self.radiance_with_count(x, y, camera, world);
+        return (self.encode(radiance, camera), sample_count);
+    }
+
+    // Linear radiance reaching pixel (x, y), before exposure and tone mapping
+    pub fn radiance(&self, x: usize, y: usize, camera: &Camera, world: &World) -> LinSrgb {
+        return self.radiance_with_count(x, y, camera, world).0;
+    }
+
+    fn radiance_with_count(
+        &self,
+        x: usize,
+        y: usize,
+        camera: &Camera,
+        world: &World,
+    ) -> (LinSrgb, usize) {
+        let radiance;
+        let mut sample_count = 1;
 
         match self.technique {
             AntiAliasingTechnique::SuperSampling => {
                 let mut rng = rand::thread_rng();
-                for _s in 0..self.samples_per_pixel {
-                    // Get random ray close to the original x and y within a [-0.5, 0.5] square
-                    let u = (x as f64 + rng.r#gen::<f64>()) / (camera.image_width as f64 - 1.0);
-                    let v = (camera.image_height as f64 - (y as f64 + rng.r#gen::<f64>()))
-                        / (camera.image_height as f64 - 1.0);
-
-                    let r = camera.get_ray(u, v);
-                    let c = camera.ray_color(&r, &world, 50);
-
-                    pixel_colors[0] += c.red;
-                    pixel_colors[1] += c.green;
-                    pixel_colors[2] += c.blue;
+
+                // Stratified pixel and lens samples come from a correlated multi-jittered
+                // pattern. It is seeded per call, so repeated renders still give independent
+                // estimates of the pixel
+                let samples: Vec<Sample> = if self.stratified {
+                    SampleGenerator::new(self.samples_per_pixel, rng.r#gen())
+                        .samples()
+                        .to_vec()
+                } else {
+                    (0..self.samples_per_pixel)
+                        .map(|_| Sample {
+                            pixel: (rng.r#gen(), rng.r#gen()),
+                            lens: (rng.r#gen(), rng.r#gen()),
+                        })
+                        .collect()
+                };
+
+                // Each color next to where in the pixel it was sampled, for the filter
+                let mut colors = Vec::with_capacity(samples.len());
+
+                // With the simd feature the samples are traced four at a time, so their first
+                // hits can be found together, and only the remainder is traced one by one
+                #[cfg(feature = "simd")]
+                let samples = {
+                    let mut batches = samples.chunks_exact(4);
+                    for batch in &mut batches {
+                        let batch = [batch[0], batch[1], batch[2], batch[3]];
+                        let batch_colors = self.sample_at4(x, y, &batch, camera, world);
+                        for (sample, c) in batch.iter().zip(batch_colors) {
+                            colors.push((sample.pixel, c));
+                        }
+                    }
+                    batches.remainder()
+                };
+
+                for sample in samples.iter() {
+                    let c = self.sample_at(x, y, sample.pixel, Some(sample.lens), camera, world);
+                    colors.push((sample.pixel, c));
                 }
 
                 // Average the color integration
-                let scale = 1.0 / self.samples_per_pixel as f32;
-                color = Srgb::new(
-                    (scale * pixel_colors[0]).sqrt(),
-                    (scale * pixel_colors[1]).sqrt(),
-                    (scale * pixel_colors[2]).sqrt(),
-                );
+                radiance = self.filter.average(&colors);
+                sample_count = self.samples_per_pixel;
+            }
+
+            AntiAliasingTechnique::Adaptive => {
+                let mut rng = rand::thread_rng();
+
+                // Running mean and sum of squared differences per channel (Welford's algorithm)
+                let mut mean: Vec<f32> = vec![0.0; 3];
+                let mut m2: Vec<f32> = vec![0.0; 3];
+                let mut n = 0;
+
+                while n < self.samples_per_pixel {
+                    let batch_end = (n + ADAPTIVE_BATCH_SIZE).min(self.samples_per_pixel);
+                    while n < batch_end {
+                        let c = self.jittered_sample(x, y, camera, world, &mut rng);
+                        n += 1;
+
+                        for (channel, value) in [c.red, c.green, c.blue].iter().enumerate() {
+                            let delta = value - mean[channel];
+                            mean[channel] += delta / n as f32;
+                            m2[channel] += delta * (value - mean[channel]);
+                        }
+                    }
+
+                    // Variance of the mean estimate is the sample variance divided by n
+                    let converged = n > 1
+                        && m2
+                            .iter()
+                            .all(|m2| (m2 / (n - 1) as f32) / n as f32 <= self.adaptive_tolerance);
+                    if converged {
+                        break;
+                    }
+                }
+
+                radiance = LinSrgb::new(mean[0], mean[1], mean[2]);
+                sample_count = n;
+            }
+
+            AntiAliasingTechnique::None => {
+                radiance = self.center_sample(x, y, camera, world);
             }
 
-            // Default case (Including the None option)
-            _ => {
-                let u = (x as f64) / (camera.image_width as f64 - 1.0);
-                let v = ((camera.image_height as f64) - (y as f64))
-                    / (camera.image_height as f64 - 1.0);
-                let r = camera.get_ray(u, v);
-                color = camera.ray_color(&r, &world, 50);
+            AntiAliasingTechnique::MonteCarlo
+            | AntiAliasingTechnique::Spatial
+            | AntiAliasingTechnique::Temporal => {
+                debug_assert!(
+                    false,
+                    "{:?} anti-aliasing is not implemented yet",
+                    self.technique
+                );
+
+                // Release builds fall back to a single sample through the pixel center
+                radiance = self.center_sample(x, y, camera, world);
             }
         }
 
+        return (radiance, sample_count);
+    }
+
+    // Trace a ray through a random point inside the pixel
+    fn jittered_sample<R: Rng>(
+        &self,
+        x: usize,
+        y: usize,
+        camera: &Camera,
+        world: &World,
+        rng: &mut R,
+    ) -> LinSrgb {
+        // Get random ray close to the original x and y within a [-0.5, 0.5] square
+        let offset = (rng.r#gen::<f64>(), rng.r#gen::<f64>());
+        return self.sample_at(x, y, offset, None, camera, world);
+    }
+
+    // Trace a single ray through the center of the pixel
+    fn center_sample(&self, x: usize, y: usize, camera: &Camera, world: &World) -> LinSrgb {
+        return self.sample_at(x, y, (0.5, 0.5), None, camera, world);
+    }
+
+    // Trace a ray through the point at offset in [0, 1) within the pixel, starting from the
+    // lens point picked by lens_sample (a random one for None)
+    fn sample_at(
+        &self,
+        x: usize,
+        y: usize,
+        offset: (f64, f64),
+        lens_sample: Option<(f64, f64)>,
+        camera: &Camera,
+        world: &World,
+    ) -> LinSrgb {
+        let (u, v) = pixel_uv(x, y, offset.0, offset.1, camera);
+        let color = camera.sample_color(u, v, lens_sample, world, self.max_depth);
+        return self.clamp_luminance(color);
+    }
+
+    // Same as sample_at for four samples within the pixel at once
+    #[cfg(feature = "simd")]
+    fn sample_at4(
+        &self,
+        x: usize,
+        y: usize,
+        samples: &[Sample; 4],
+        camera: &Camera,
+        world: &World,
+    ) -> [LinSrgb; 4] {
+        // Chromatic aberration traces every channel on its own
+        if camera.chromatic_aberration() > 0.0 {
+            return samples.map(|sample| {
+                self.sample_at(x, y, sample.pixel, Some(sample.lens), camera, world)
+            });
+        }
+
+        let rays = samples.map(|sample| {
+            let (u, v) = pixel_uv(x, y, sample.pixel.0, sample.pixel.1, camera);
+            camera.get_ray_with_lens(u, v, Some(sample.lens))
+        });
+        return camera
+            .ray_color4(&rays, world, self.max_depth)
+            .map(|color| self.clamp_luminance(color));
+    }
+
+    // Scale a sample down to max_luminance, keeping its hue
+    fn clamp_luminance(&self, color: LinSrgb) -> LinSrgb {
+        if let Some(max_luminance) = self.max_luminance {
+            let sample_luminance = luminance(color);
+            if sample_luminance > max_luminance {
+                return color * (max_luminance / sample_luminance);
+            }
+        }
         return color;
     }
+
+    // Scale the averaged linear radiance by the camera exposure, tone map it,
+    // then encode it with the sRGB transfer curve
+    fn encode(&self, color: LinSrgb, camera: &Camera) -> Srgb {
+        let mapped = self.tone_map.apply(color * camera.exposure());
+        return Srgb::new(
+            linear_to_srgb(mapped.red),
+            linear_to_srgb(mapped.green),
+            linear_to_srgb(mapped.blue),
+        );
+    }
+}
+
+// Viewport coordinates of the point at (offset_x, offset_y) in [0, 1) within pixel (x, y)
+fn pixel_uv(x: usize, y: usize, offset_x: f64, offset_y: f64, camera: &Camera) -> (f64, f64) {
+    return camera.pixel_uv(x as f64 + offset_x, y as f64 + offset_y);
+}
+
+#[test]
+fn test_adaptive_sampling() {
+    let camera_config = CameraConfig {
+        aspect: 1.0,
+        image_width: 16,
+        vertical_fov: 90.0,
+        horizontal_fov: None,
+        background: Background::default(),
+        vector_up: Vector3D::new(0.0, 1.0, 0.0),
+        look_from: Vector3D::new(0.0, 0.0, 0.0),
+        look_at: Vector3D::new(0.0, 0.0, -1.0),
+        settings: CameraSettings::default(),
+    };
+    let camera = Camera::from(camera_config);
+
+    // The top of the image only sees the smooth sky, while the middle row straddles the
+    // horizon between the bright sky and a black floor
+    let mut world = World::new();
+    world.add(Plane::new(
+        Vector3D::new(0.0, -0.5, 0.0),
+        Vector3D::new(0.0, 1.0, 0.0),
+        Material::Lambertian(Lambertian::new(Srgb::new(0.0, 0.0, 0.0))),
+    ));
+
+    let max_samples = 256;
+    let anti_aliasing = AntiAliasing::new(max_samples, AntiAliasingTechnique::Adaptive);
+
+    let (_, flat_samples) = anti_aliasing.anti_alias_with_count(8, 0, &camera, &world);
+
+    // A single horizon pixel can still stop after its first batch if every sample happens
+    // to land on the same side, so take the largest count across the row
+    let noisy_samples = (0..16)
+        .map(|x| anti_aliasing.anti_alias_with_count(x, 8, &camera, &world).1)
+        .max()
+        .unwrap();
+
+    assert!(
+        flat_samples <= max_samples / 4,
+        "Flat pixel used {} samples",
+        flat_samples
+    );
+    assert!(
+        noisy_samples > flat_samples,
+        "Noisy pixel used {} samples, flat pixel used {}",
+        noisy_samples,
+        flat_samples
+    );
+}
+
+#[test]
+fn test_with_samples() {
+    let anti_aliasing = AntiAliasing::new(100, AntiAliasingTechnique::SuperSampling);
+    let preview = anti_aliasing.with_samples(4);
+
+    assert_eq!(preview.samples_per_pixel(), 4);
+    assert_eq!(preview.technique(), AntiAliasingTechnique::SuperSampling);
+    assert_eq!(anti_aliasing.samples_per_pixel(), 100);
+
+    let camera_config = CameraConfig {
+        aspect: 1.0,
+        image_width: 4,
+        vertical_fov: 90.0,
+        horizontal_fov: None,
+        background: Background::default(),
+        vector_up: Vector3D::new(0.0, 1.0, 0.0),
+        look_from: Vector3D::new(0.0, 0.0, 0.0),
+        look_at: Vector3D::new(0.0, 0.0, -1.0),
+        settings: CameraSettings::default(),
+    };
+    let camera = Camera::from(camera_config);
+    let world = World::new();
+
+    let (_, sample_count) = preview.anti_alias_with_count(0, 0, &camera, &world);
+    assert_eq!(sample_count, 4);
+}
+
+// Counts how many rays were tested against it, and is hit wherever the object it wraps is,
+// or never without one
+#[cfg(test)]
+#[derive(Clone)]
+pub(crate) struct CountingObject {
+    pub(crate) count: Arc<AtomicUsize>,
+    pub(crate) object: Option<Box<dyn Object>>,
+}
+
+#[cfg(test)]
+impl Object for CountingObject {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<ObjectHitRecord> {
+        self.count.fetch_add(1, Ordering::SeqCst);
+        return self.object.as_ref()?.hit(ray, t_min, t_max);
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        return self.object.as_ref()?.bounding_box();
+    }
+}
+
+#[test]
+fn test_none_single_sample() {
+    let camera_config = CameraConfig {
+        aspect: 4.0 / 3.0,
+        image_width: 4,
+        vertical_fov: 90.0,
+        horizontal_fov: None,
+        background: Background::default(),
+        vector_up: Vector3D::new(0.0, 1.0, 0.0),
+        look_from: Vector3D::new(0.0, 0.0, 0.0),
+        look_at: Vector3D::new(0.0, 0.0, -1.0),
+        settings: CameraSettings::default(),
+    };
+    let camera = Camera::from(camera_config);
+
+    let count = Arc::new(AtomicUsize::new(0));
+    let mut world = World::new();
+    world.add(CountingObject {
+        count: count.clone(),
+        object: None,
+    });
+
+    let anti_aliasing = AntiAliasing::new(16, AntiAliasingTechnique::None);
+    camera.render(&world, &anti_aliasing);
+
+    assert_eq!(
+        count.load(Ordering::SeqCst),
+        camera.image_width * camera.image_height
+    );
+
+    // Without jitter, sampling the same pixel twice gives the same color
+    let first = anti_aliasing.anti_alias(1, 1, &camera, &world);
+    let second = anti_aliasing.anti_alias(1, 1, &camera, &world);
+    assert_eq!(first, second);
+}
+
+#[test]
+fn test_max_depth() {
+    let camera_config = CameraConfig {
+        aspect: 1.0,
+        image_width: 16,
+        vertical_fov: 90.0,
+        horizontal_fov: None,
+        background: Background::default(),
+        vector_up: Vector3D::new(0.0, 1.0, 0.0),
+        look_from: Vector3D::new(0.0, 0.0, 0.0),
+        look_at: Vector3D::new(0.0, 0.0, -1.0),
+        settings: CameraSettings::default(),
+    };
+    let camera = Camera::from(camera_config);
+
+    // A perfect mirror floor only shows the sky if the reflected ray is traced
+    let mut world = World::new();
+    world.add(Plane::new(
+        Vector3D::new(0.0, -0.5, 0.0),
+        Vector3D::new(0.0, 1.0, 0.0),
+        Material::Metal(Metal::new(Srgb::new(1.0, 1.0, 1.0), 0.0)),
+    ));
+
+    let mut anti_aliasing = AntiAliasing::new(1, AntiAliasingTechnique::None);
+    let deep = anti_aliasing.anti_alias(8, 15, &camera, &world);
+
+    anti_aliasing.set_max_depth(1);
+    let shallow = anti_aliasing.anti_alias(8, 15, &camera, &world);
+
+    assert_eq!(anti_aliasing.max_depth(), 1);
+    assert_eq!(shallow, Srgb::new(0.0, 0.0, 0.0));
+    assert!(deep.red > 0.0 && deep.green > 0.0 && deep.blue > 0.0);
+}
+
+#[test]
+fn test_stratified_supersampling() {
+    let camera_config = CameraConfig {
+        aspect: 1.0,
+        image_width: 16,
+        vertical_fov: 90.0,
+        horizontal_fov: None,
+        background: Background::SolidColor(Srgb::new(1.0, 1.0, 1.0)),
+        vector_up: Vector3D::new(0.0, 1.0, 0.0),
+        look_from: Vector3D::new(0.0, 0.0, 0.0),
+        look_at: Vector3D::new(0.0, 0.0, -1.0),
+        settings: CameraSettings::default(),
+    };
+    let camera = Camera::from(camera_config);
+
+    // Flat white sky above a flat black floor, with the horizon running through pixel row 8
+    let mut world = World::new();
+    world.add(Plane::new(
+        Vector3D::new(0.0, -0.5, 0.0),
+        Vector3D::new(0.0, 1.0, 0.0),
+        Material::Lambertian(Lambertian::new(Srgb::new(0.0, 0.0, 0.0))),
+    ));
+
+    // Variance of the pixel estimate over repeated renders
+    let measure_variance = |anti_aliasing: &AntiAliasing| {
+        let trials = 200;
+        let values: Vec<f32> = (0..trials)
+            .map(|_| anti_aliasing.anti_alias(8, 8, &camera, &world).red)
+            .collect();
+        let mean = values.iter().sum::<f32>() / trials as f32;
+        return values.iter().map(|v| (v - mean) * (v - mean)).sum::<f32>() / trials as f32;
+    };
+
+    let mut anti_aliasing = AntiAliasing::new(16, AntiAliasingTechnique::SuperSampling);
+    assert!(anti_aliasing.stratified());
+    let stratified_variance = measure_variance(&anti_aliasing);
+
+    anti_aliasing.set_stratified(false);
+    let random_variance = measure_variance(&anti_aliasing);
+
+    assert!(
+        stratified_variance * 2.0 < random_variance,
+        "Stratified variance {} is not below random variance {}",
+        stratified_variance,
+        random_variance
+    );
+}
+
+#[test]
+fn test_linear_accumulation() {
+    let anti_aliasing = AntiAliasing::new(2, AntiAliasingTechnique::SuperSampling);
+    let camera = Camera::from(CameraConfig::default());
+
+    // Two half brightness contributions add up to full brightness in linear space
+    let half: LinSrgb = LinSrgb::new(0.5, 0.5, 0.5);
+    let total = half + half;
+    assert_eq!(total, LinSrgb::new(1.0, 1.0, 1.0));
+
+    // Averaging happens before encoding, so a bright and a dark sample encode to the
+    // encoded mean rather than the mean of the encoded samples
+    let dark: LinSrgb = LinSrgb::new(0.0, 0.0, 0.0);
+    let average = anti_aliasing.encode((total + dark) / 2.0, &camera);
+    assert_approx_eq!(average.red, linear_to_srgb(0.5));
+    let encoded_average =
+        (anti_aliasing.encode(total, &camera).red + anti_aliasing.encode(dark, &camera).red) / 2.0;
+    assert!((average.red - encoded_average).abs() > 0.1);
+}
+
+#[test]
+fn test_anti_aliasing_serde() {
+    let mut anti_aliasing = AntiAliasing::new(16, AntiAliasingTechnique::MonteCarlo);
+    anti_aliasing.set_tone_map(ToneMap::AcesFilmic);
+
+    let json = serde_json::to_string(&anti_aliasing).unwrap();
+    let loaded: AntiAliasing = serde_json::from_str(&json).unwrap();
+    assert_eq!(loaded, anti_aliasing);
+
+    // Only the sample count and technique are required
+    let json = r#"{ "samples_per_pixel": 16, "technique": "MonteCarlo" }"#;
+    let loaded: AntiAliasing = serde_json::from_str(json).unwrap();
+    assert_eq!(
+        loaded,
+        AntiAliasing::new(16, AntiAliasingTechnique::MonteCarlo)
+    );
+}
+
+#[test]
+fn test_anti_aliasing_default() {
+    let anti_aliasing = AntiAliasing::default();
+    assert_eq!(anti_aliasing.samples_per_pixel(), 16);
+    assert_eq!(
+        anti_aliasing.technique(),
+        AntiAliasingTechnique::SuperSampling
+    );
+
+    // The defaults render a valid image
+    let camera = Camera::from(CameraConfig {
+        image_width: 8,
+        ..Default::default()
+    });
+    let pixels = camera.render(&World::new(), &anti_aliasing);
+    assert_eq!(pixels.len(), camera.image_width * camera.image_height * 3);
+}
+
+#[test]
+fn test_exposure() {
+    let mut camera = Camera::from(CameraConfig {
+        image_width: 4,
+        background: Background::SolidColor(Srgb::new(0.25, 0.25, 0.25)),
+        ..Default::default()
+    });
+    let world = World::new();
+    let anti_aliasing = AntiAliasing::new(1, AntiAliasingTechnique::None);
+    assert_eq!(camera.exposure(), 1.0);
+
+    // Doubling the exposure doubles the linear value before it is encoded
+    assert_approx_eq!(
+        anti_aliasing.anti_alias(1, 1, &camera, &world).red,
+        linear_to_srgb(0.25)
+    );
+    camera.set_exposure(2.0);
+    assert_approx_eq!(
+        anti_aliasing.anti_alias(1, 1, &camera, &world).red,
+        linear_to_srgb(0.5)
+    );
+
+    // Overexposed values are still limited by the tone map
+    camera.set_exposure(8.0);
+    assert_approx_eq!(anti_aliasing.anti_alias(1, 1, &camera, &world).red, 1.0);
+}
+
+#[test]
+fn test_anti_aliasing_builder() {
+    let anti_aliasing = AntiAliasing::builder()
+        .samples_per_pixel(64)
+        .technique(AntiAliasingTechnique::Adaptive)
+        .adaptive_tolerance(1e-3)
+        .max_depth(8)
+        .tone_map(ToneMap::Reinhard)
+        .stratified(false)
+        .filter(ReconstructionFilter::Gaussian)
+        .build();
+
+    assert_eq!(anti_aliasing.samples_per_pixel(), 64);
+    assert_eq!(anti_aliasing.technique(), AntiAliasingTechnique::Adaptive);
+    assert_approx_eq!(anti_aliasing.adaptive_tolerance(), 1e-3);
+    assert_eq!(anti_aliasing.max_depth(), 8);
+    assert_eq!(anti_aliasing.tone_map(), ToneMap::Reinhard);
+    assert!(!anti_aliasing.stratified());
+    assert_eq!(anti_aliasing.filter(), ReconstructionFilter::Gaussian);
+
+    // Untouched settings keep their defaults
+    assert_eq!(AntiAliasingBuilder::new().build(), AntiAliasing::default());
+    let anti_aliasing = AntiAliasingBuilder::new().max_depth(4).build();
+    assert_eq!(anti_aliasing.tone_map(), ToneMap::Clamp);
+    assert_eq!(anti_aliasing.samples_per_pixel(), 16);
+    assert_eq!(anti_aliasing.filter(), ReconstructionFilter::Box);
+}
+
+// Mirror floor reflecting a small, very bright light that is itself out of view
+#[cfg(test)]
+fn bright_reflection_scene(mirror: Metal) -> (Camera, World) {
+    let camera = Camera::from(CameraConfig {
+        aspect: 1.0,
+        image_width: 16,
+        vertical_fov: 40.0,
+        horizontal_fov: None,
+        background: Background::SolidColor(Srgb::new(0.0, 0.0, 0.0)),
+        vector_up: Vector3D::new(0.0, 1.0, 0.0),
+        look_from: Vector3D::new(0.0, 1.0, 0.0),
+        look_at: Vector3D::new(0.0, 0.0, -2.0),
+        settings: CameraSettings::default(),
+    });
+
+    let mut world = World::new();
+    world.add(Plane::new(
+        Vector3D::new(0.0, 0.0, 0.0),
+        Vector3D::new(0.0, 1.0, 0.0),
+        Material::Metal(mirror),
+    ));
+    world.add(Sphere::new(
+        Vector3D::new(0.0, 2.0, -4.0),
+        0.3,
+        Material::DiffuseLight(DiffuseLight::new(Srgb::new(100.0, 100.0, 100.0))),
+    ));
+
+    return (camera, world);
+}
+
+#[cfg(test)]
+fn pixel_luminances(anti_aliasing: &AntiAliasing, camera: &Camera, world: &World) -> Vec<f32> {
+    let mut luminances = Vec::new();
+    for y in 0..camera.image_height {
+        for x in 0..camera.image_width {
+            luminances.push(luminance(anti_aliasing.radiance(x, y, camera, world)));
+        }
+    }
+    return luminances;
+}
+
+#[test]
+fn test_max_luminance() {
+    let (camera, world) = bright_reflection_scene(Metal::new(Srgb::new(0.9, 0.9, 0.9), 0.0));
+    let mut anti_aliasing = AntiAliasing::new(4, AntiAliasingTechnique::SuperSampling);
+    assert_eq!(anti_aliasing.max_luminance(), None);
+
+    let extreme_pixels = |anti_aliasing: &AntiAliasing| {
+        return pixel_luminances(anti_aliasing, &camera, &world)
+            .iter()
+            .filter(|&&l| l > 5.0)
+            .count();
+    };
+
+    // The reflection of the light is far brighter than anything else
+    let unclamped = extreme_pixels(&anti_aliasing);
+    assert!(unclamped > 0);
+
+    anti_aliasing.set_max_luminance(Some(4.0));
+    let clamped = extreme_pixels(&anti_aliasing);
+    assert!(
+        clamped < unclamped,
+        "{} extreme pixels clamped, {} unclamped",
+        clamped,
+        unclamped
+    );
+    assert_eq!(clamped, 0);
+
+    // Samples below the limit are untouched
+    let dim = LinSrgb::new(0.5, 1.0, 2.0);
+    assert_eq!(anti_aliasing.clamp_luminance(dim), dim);
+    let bright = anti_aliasing.clamp_luminance(LinSrgb::new(50.0, 100.0, 200.0));
+    assert_approx_eq!(luminance(bright), 4.0, 1e-4);
+    assert_approx_eq!(bright.blue / bright.red, 4.0, 1e-4);
+}
+
+#[test]
+#[should_panic(expected = "Maximum luminance must be positive")]
+fn test_max_luminance_must_be_positive() {
+    let mut anti_aliasing = AntiAliasing::new(4, AntiAliasingTechnique::SuperSampling);
+    anti_aliasing.set_max_luminance(Some(f32::NAN));
+}
+
+#[test]
+fn test_min_roughness() {
+    let (mut camera, world) = bright_reflection_scene(Metal::new(Srgb::new(0.9, 0.9, 0.9), 0.0));
+    let anti_aliasing = AntiAliasing::new(64, AntiAliasingTechnique::SuperSampling);
+    assert_eq!(camera.min_roughness(), 0.0);
+
+    let brightest = |camera: &Camera| {
+        return pixel_luminances(&anti_aliasing, camera, &world)
+            .into_iter()
+            .fold(0.0, f32::max);
+    };
+
+    // A perfect mirror shows the light at full strength in every sample of some pixels,
+    // roughening it spreads the reflection out over many dimmer pixels
+    let sharp = brightest(&camera);
+    camera.set_min_roughness(0.5);
+    let rough = brightest(&camera);
+    assert!(
+        rough < sharp * 0.5,
+        "Brightest pixel {} with a minimum roughness, {} without",
+        rough,
+        sharp
+    );
+
+    // Brushed metal that is only rough across the brushing is still a mirror along it,
+    // however rough it is on average, and the floor applies to each direction on its own
+    let brushed = Metal::anisotropic(
+        Srgb::new(0.9, 0.9, 0.9),
+        Vector3D::new(1.0, 0.0, 0.0),
+        0.0,
+        1.0,
+    );
+    let plane = Plane::new(
+        Vector3D::new(0.0, 0.0, 0.0),
+        Vector3D::new(0.0, 1.0, 0.0),
+        Material::Metal(brushed.clone()),
+    );
+    let ray = Ray::new(Vector3D::new(0.0, 1.0, 0.0), Vector3D::new(0.0, -1.0, 0.0));
+    let hit_record = plane.hit(&ray, 0.001, f64::MAX).unwrap();
+    let spread_along_brushing = |min_roughness: f64| {
+        return (0..100)
+            .filter_map(|_| brushed.scatter_with_min_roughness(&ray, &hit_record, min_roughness))
+            .map(|(scattered, _)| scattered.direction.get_x().abs())
+            .fold(0.0, f64::max);
+    };
+    assert_eq!(spread_along_brushing(0.0), 0.0);
+    assert!(spread_along_brushing(0.5) > 0.1);
+
+    // Only metals smoother than the floor are affected: the fuzz never strays further from
+    // the mirror direction than the roughness, so the largest stray shows which one was used
+    let mirror = Vector3D::new(0.0, 1.0, 0.0);
+    let largest_stray = |metal: &Metal, min_roughness: f64| {
+        return (0..200)
+            .filter_map(|_| metal.scatter_with_min_roughness(&ray, &hit_record, min_roughness))
+            .map(|(scattered, _)| (scattered.direction - mirror).length())
+            .fold(0.0, f64::max);
+    };
+    let rough = Metal::new(Srgb::new(0.9, 0.9, 0.9), 0.5);
+    assert!(largest_stray(&rough, 0.3) > 0.3);
+    let smooth = Metal::new(Srgb::new(0.9, 0.9, 0.9), 0.1);
+    assert!(largest_stray(&smooth, 0.0) <= 0.1 + 1e-9);
+    assert!(largest_stray(&smooth, 0.3) > 0.1);
+    assert!(largest_stray(&smooth, 0.3) <= 0.3 + 1e-9);
 }
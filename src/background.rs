@@ -0,0 +1,98 @@
+use serde::{Deserialize, Serialize};
+
+use palette::Srgb;
+
+use crate::material::SrgbAsArray;
+use crate::ray::Ray;
+use crate::sphere::Sphere;
+use crate::texture::{ImageTexture, Texture};
+
+#[cfg(test)]
+use crate::vector_3d::Vector3D;
+#[cfg(test)]
+use assert_approx_eq::assert_approx_eq;
+
+// What a ray sees when it escapes the scene without hitting anything
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub enum Background {
+    SolidColor(#[serde(with = "SrgbAsArray")] Srgb),
+    // Vertical blend from bottom (looking straight down) to top (looking straight up)
+    Gradient {
+        #[serde(with = "SrgbAsArray")]
+        top: Srgb,
+        #[serde(with = "SrgbAsArray")]
+        bottom: Srgb,
+    },
+    // Equirectangular environment map surrounding the whole scene
+    Environment(ImageTexture),
+}
+
+impl Default for Background {
+    // The classic blue-white sky
+    fn default() -> Self {
+        Background::Gradient {
+            top: Srgb::new(0.5, 0.7, 1.0),
+            bottom: Srgb::new(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+impl Background {
+    pub fn color(&self, ray: &Ray) -> Srgb {
+        match self {
+            Background::SolidColor(color) => *color,
+            Background::Gradient { top, bottom } => {
+                let t: f32 = 0.5 * (ray.direction.unit_vector().get_y() as f32 + 1.0);
+                Srgb::new(
+                    (1.0 - t) * bottom.red + t * top.red,
+                    (1.0 - t) * bottom.green + t * top.green,
+                    (1.0 - t) * bottom.blue + t * top.blue,
+                )
+            }
+            Background::Environment(texture) => {
+                // Map the direction onto the unit sphere the same way sphere textures are mapped
+                let direction = ray.direction.unit_vector();
+                let (u, v) = Sphere::get_sphere_uv(&direction);
+                texture.value(u, v, &direction)
+            }
+        }
+    }
+}
+
+#[test]
+fn test_background_gradient() {
+    let background = Background::default();
+
+    let up = Ray::new(Vector3D::new(0.0, 0.0, 0.0), Vector3D::new(0.0, 1.0, 0.0));
+    let color = background.color(&up);
+    assert_approx_eq!(color.red, 0.5);
+    assert_approx_eq!(color.green, 0.7);
+    assert_approx_eq!(color.blue, 1.0);
+
+    let down = Ray::new(Vector3D::new(0.0, 0.0, 0.0), Vector3D::new(0.0, -1.0, 0.0));
+    let color = background.color(&down);
+    assert_approx_eq!(color.red, 1.0);
+    assert_approx_eq!(color.green, 1.0);
+    assert_approx_eq!(color.blue, 1.0);
+}
+
+#[test]
+fn test_background_environment() {
+    // 4x2 black environment map with a single red texel
+    let mut data = vec![0u8; 4 * 2 * 3];
+    let red_index = (4 + 2) * 3; // Row 1, column 2
+    data[red_index] = 255;
+
+    let background = Background::Environment(ImageTexture::new(4, 2, data));
+
+    // +X maps to u = 0.5, v = 0.5, which is column 2 of the bottom row
+    let ray = Ray::new(Vector3D::new(0.0, 0.0, 0.0), Vector3D::new(1.0, 0.0, 0.0));
+    let color = background.color(&ray);
+    assert_approx_eq!(color.red, 1.0);
+    assert_approx_eq!(color.green, 0.0);
+    assert_approx_eq!(color.blue, 0.0);
+
+    let ray = Ray::new(Vector3D::new(0.0, 0.0, 0.0), Vector3D::new(-1.0, 0.0, 0.0));
+    let color = background.color(&ray);
+    assert_approx_eq!(color.red, 0.0);
+}
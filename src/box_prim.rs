@@ -0,0 +1,121 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::material::Material;
+use crate::object::{Object, ObjectHitRecord};
+use crate::ray::Ray;
+use crate::rectangle::{Axis, AxisAlignedRect};
+use crate::vector_3d::Vector3D;
+
+#[cfg(test)]
+use crate::material::Lambertian;
+#[cfg(test)]
+use assert_approx_eq::assert_approx_eq;
+#[cfg(test)]
+use palette::Srgb;
+
+// Axis-aligned box built from six rectangles with outward facing normals
+#[derive(Clone)]
+pub struct BoxPrim {
+    min: Vector3D,
+    max: Vector3D,
+    sides: Vec<AxisAlignedRect>,
+}
+
+impl BoxPrim {
+    pub fn new(min: Vector3D, max: Vector3D, material: impl Into<Arc<Material>>) -> BoxPrim {
+        // All six sides share one material
+        let material = material.into();
+        let (x0, y0, z0) = (min.get_x(), min.get_y(), min.get_z());
+        let (x1, y1, z1) = (max.get_x(), max.get_y(), max.get_z());
+
+        let sides = vec![
+            // Front and back
+            AxisAlignedRect::new(Axis::Z, x0, x1, y0, y1, z1, material.clone()),
+            AxisAlignedRect::new(Axis::Z, x0, x1, y0, y1, z0, material.clone()).flipped(),
+            // Top and bottom
+            AxisAlignedRect::new(Axis::Y, x0, x1, z0, z1, y1, material.clone()),
+            AxisAlignedRect::new(Axis::Y, x0, x1, z0, z1, y0, material.clone()).flipped(),
+            // Right and left
+            AxisAlignedRect::new(Axis::X, y0, y1, z0, z1, x1, material.clone()),
+            AxisAlignedRect::new(Axis::X, y0, y1, z0, z1, x0, material).flipped(),
+        ];
+
+        return BoxPrim {
+            min: min,
+            max: max,
+            sides: sides,
+        };
+    }
+
+    pub fn min(&self) -> Vector3D {
+        return self.min;
+    }
+
+    pub fn max(&self) -> Vector3D {
+        return self.max;
+    }
+}
+
+impl Object for BoxPrim {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<ObjectHitRecord> {
+        let mut closest_so_far = t_max;
+        let mut hit_record = None;
+        for side in &self.sides {
+            if let Some(hit) = side.hit(ray, t_min, closest_so_far) {
+                closest_so_far = hit.t;
+                hit_record = Some(hit);
+            }
+        }
+        return hit_record;
+    }
+
+    fn material(&self) -> Option<&Material> {
+        // Every side shares the box's material
+        return self.sides[0].material();
+    }
+
+    fn set_material(&mut self, material: Arc<Material>) -> bool {
+        for side in self.sides.iter_mut() {
+            side.set_material(material.clone());
+        }
+        return true;
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        return Some(Aabb::new(self.min, self.max));
+    }
+}
+
+#[test]
+fn test_box_prim_front_face() {
+    let unit_box = BoxPrim::new(
+        Vector3D::new(-0.5, -0.5, -0.5),
+        Vector3D::new(0.5, 0.5, 0.5),
+        Material::Lambertian(Lambertian::new(Srgb::new(0.5, 0.5, 0.5))),
+    );
+
+    let ray = Ray::new(Vector3D::new(0.0, 0.0, 5.0), Vector3D::new(0.0, 0.0, -1.0));
+    let hit = unit_box
+        .hit(&ray, 0.001, f64::MAX)
+        .expect("Ray should hit the box");
+
+    assert_approx_eq!(hit.t, 4.5);
+    assert_approx_eq!(hit.point.get_x(), 0.0);
+    assert_approx_eq!(hit.point.get_y(), 0.0);
+    assert_approx_eq!(hit.point.get_z(), 0.5);
+    assert_approx_eq!(hit.normal.get_x(), 0.0);
+    assert_approx_eq!(hit.normal.get_y(), 0.0);
+    assert_approx_eq!(hit.normal.get_z(), 1.0);
+    assert!(hit.front_face);
+
+    // From below, the bottom face should report an outward -y normal
+    let ray = Ray::new(Vector3D::new(0.0, -5.0, 0.0), Vector3D::new(0.0, 1.0, 0.0));
+    let hit = unit_box
+        .hit(&ray, 0.001, f64::MAX)
+        .expect("Ray should hit the box");
+
+    assert_approx_eq!(hit.point.get_y(), -0.5);
+    assert_approx_eq!(hit.normal.get_y(), -1.0);
+    assert!(hit.front_face);
+}
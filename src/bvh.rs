@@ -0,0 +1,207 @@
+use std::cmp::Ordering;
+
+use crate::aabb::Aabb;
+use crate::error::RayTracerError;
+use crate::object::{Object, ObjectHitRecord};
+use crate::ray::Ray;
+
+#[cfg(test)]
+use crate::material::{Lambertian, Material};
+#[cfg(test)]
+use crate::plane::Plane;
+#[cfg(test)]
+use crate::vector_3d::Vector3D;
+#[cfg(test)]
+use crate::world::World;
+#[cfg(test)]
+use palette::Srgb;
+#[cfg(test)]
+use rand::{Rng, SeedableRng};
+
+// Subtrees with fewer objects than this are built on the current thread, since splitting
+// small builds across threads costs more than it saves
+const PARALLEL_BUILD_THRESHOLD: usize = 1024;
+
+// Object paired with its bounding box, which the build looks at many times
+type BoundedObject = (Aabb, Box<dyn Object>);
+
+// Bounding volume hierarchy, a binary tree of boxes so a ray only tests the objects
+// whose boxes it passes through
+#[derive(Clone)]
+pub struct BvhNode {
+    left: Box<dyn Object>,
+    right: Option<Box<dyn Object>>, // None for a leaf holding a single object
+    bounds: Aabb,
+}
+
+impl BvhNode {
+    // Build the tree on the current thread. Every object needs a bounding box, so unbounded
+    // objects (e.g. planes) have to stay outside the tree
+    pub fn new(objects: Vec<Box<dyn Object>>) -> Result<BvhNode, RayTracerError> {
+        return Ok(BvhNode::build(BvhNode::with_bounds(objects)?, false));
+    }
+
+    // Same tree as new, with the two halves of large splits built concurrently
+    pub fn new_parallel(objects: Vec<Box<dyn Object>>) -> Result<BvhNode, RayTracerError> {
+        return Ok(BvhNode::build(BvhNode::with_bounds(objects)?, true));
+    }
+
+    fn with_bounds(objects: Vec<Box<dyn Object>>) -> Result<Vec<BoundedObject>, RayTracerError> {
+        if objects.is_empty() {
+            return Err(RayTracerError::InvalidInput(
+                "A BVH needs at least one object".to_string(),
+            ));
+        }
+
+        let mut bounded = Vec::with_capacity(objects.len());
+        for (index, object) in objects.into_iter().enumerate() {
+            match object.bounding_box() {
+                Some(bounds) => bounded.push((bounds, object)),
+                None => {
+                    return Err(RayTracerError::InvalidInput(format!(
+                        "Object {} has no bounding box and cannot be put in a BVH",
+                        index
+                    )));
+                }
+            }
+        }
+        return Ok(bounded);
+    }
+
+    fn build(mut objects: Vec<BoundedObject>, parallel: bool) -> BvhNode {
+        if objects.len() == 1 {
+            let (bounds, object) = objects.pop().unwrap();
+            return BvhNode {
+                left: object,
+                right: None,
+                bounds: bounds,
+            };
+        }
+
+        // Split at the median along the axis where the box centers are most spread out
+        let mut min = objects[0].0.center();
+        let mut max = min;
+        for (bounds, _) in objects.iter() {
+            min = min.component_min(&bounds.center());
+            max = max.component_max(&bounds.center());
+        }
+        let spread = max - min;
+        let axis = (0..3)
+            .max_by(|&a, &b| spread[a].partial_cmp(&spread[b]).unwrap_or(Ordering::Equal))
+            .unwrap();
+
+        // Stable sort, so serial and parallel builds always produce the same tree
+        objects.sort_by(|a, b| a.0.center()[axis].total_cmp(&b.0.center()[axis]));
+        let right_objects = objects.split_off(objects.len() / 2);
+        let left_objects = objects;
+
+        let (left, right) =
+            if parallel && left_objects.len() + right_objects.len() >= PARALLEL_BUILD_THRESHOLD {
+                rayon::join(
+                    || BvhNode::build(left_objects, true),
+                    || BvhNode::build(right_objects, true),
+                )
+            } else {
+                (
+                    BvhNode::build(left_objects, parallel),
+                    BvhNode::build(right_objects, parallel),
+                )
+            };
+
+        return BvhNode {
+            bounds: left.bounds.surrounding(&right.bounds),
+            left: BvhNode::collapse(left),
+            right: Some(BvhNode::collapse(right)),
+        };
+    }
+
+    // Store a leaf's object directly instead of behind another node
+    fn collapse(node: BvhNode) -> Box<dyn Object> {
+        match node.right {
+            None => node.left,
+            Some(_) => Box::new(node),
+        }
+    }
+}
+
+impl Object for BvhNode {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<ObjectHitRecord> {
+        if !self.bounds.hit(ray, t_min, t_max) {
+            return None;
+        }
+
+        let left_hit = self.left.hit(ray, t_min, t_max);
+        let right = match &self.right {
+            Some(right) => right,
+            None => return left_hit,
+        };
+
+        // Only hits closer than the left one matter on the right
+        let closest_so_far = left_hit.as_ref().map_or(t_max, |hit| hit.t);
+        return right.hit(ray, t_min, closest_so_far).or(left_hit);
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        return Some(self.bounds);
+    }
+}
+
+// Objects of World::random_spheres, which the BVH is checked against
+#[cfg(test)]
+fn random_spheres(count: usize, seed: u64) -> Vec<Box<dyn Object>> {
+    return World::random_spheres(count, seed)
+        .iter()
+        .map(|object| object.clone_box())
+        .collect();
+}
+
+#[test]
+fn test_bvh_parallel_matches_serial() {
+    let serial = BvhNode::new(random_spheres(5000, 7)).unwrap();
+    let parallel = BvhNode::new_parallel(random_spheres(5000, 7)).unwrap();
+    let brute_force = random_spheres(5000, 7);
+
+    let mut rng = rand::rngs::StdRng::seed_from_u64(11);
+    let mut hits = 0;
+    for _ in 0..2000 {
+        let origin = Vector3D::new(
+            rng.gen_range(-25.0..25.0),
+            rng.gen_range(-25.0..25.0),
+            rng.gen_range(-25.0..25.0),
+        );
+        let target = Vector3D::new(
+            rng.gen_range(-11.0..11.0),
+            rng.gen_range(0.0..0.4),
+            rng.gen_range(-11.0..11.0),
+        );
+        let ray = Ray::new(origin, target - origin);
+
+        let serial_hit = serial.hit(&ray, 0.001, f64::MAX).map(|hit| hit.t);
+        let parallel_hit = parallel.hit(&ray, 0.001, f64::MAX).map(|hit| hit.t);
+        assert_eq!(serial_hit, parallel_hit);
+
+        // Both agree with testing every sphere
+        let expected = brute_force
+            .iter()
+            .filter_map(|sphere| sphere.hit(&ray, 0.001, f64::MAX))
+            .map(|hit| hit.t)
+            .min_by(|a, b| a.total_cmp(b));
+        assert_eq!(serial_hit, expected);
+        hits += serial_hit.is_some() as usize;
+    }
+    assert!(hits > 0);
+}
+
+#[test]
+fn test_bvh_rejects_invalid_input() {
+    assert!(BvhNode::new(Vec::new()).is_err());
+    assert!(BvhNode::new_parallel(Vec::new()).is_err());
+
+    let mut objects = random_spheres(3, 1);
+    objects.push(Box::new(Plane::new(
+        Vector3D::new(0.0, 0.0, 0.0),
+        Vector3D::new(0.0, 1.0, 0.0),
+        Material::Lambertian(Lambertian::new(Srgb::new(0.5, 0.5, 0.5))),
+    )));
+    assert!(BvhNode::new(objects).is_err());
+}
@@ -1,22 +1,193 @@
+use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, PI};
 use std::fs::File;
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
 
 use serde::{Deserialize, Serialize};
 
 use palette::Pixel;
-use palette::Srgb;
+use palette::{LinSrgb, Srgb};
+
+use rand::Rng;
 
 use image::ColorType;
+use image::Rgb;
+use image::RgbImage;
+use image::hdr::HDREncoder;
+use image::jpeg::JPEGEncoder;
 use image::png::PNGEncoder;
 
 use crate::anti_aliasing::AntiAliasing;
-use crate::material::Scatterable;
-use crate::ray::Ray;
+use crate::background::Background;
+use crate::error::RayTracerError;
+use crate::fog::Fog;
+use crate::light::PdfLight;
+use crate::material::{Material, Scatterable};
+use crate::object::ObjectHitRecord;
+use crate::ray::{Ray, SURFACE_EPSILON, T_MIN};
 use crate::vector_3d::Vector3D;
 use crate::world::World;
 
+#[cfg(test)]
+use crate::anti_aliasing::{AntiAliasingTechnique, CountingObject};
+#[cfg(test)]
+use crate::light::PointLight;
+#[cfg(test)]
+use crate::material::{DiffuseLight, Lambertian, Metal, Phong};
+#[cfg(test)]
+use crate::plane::Plane;
+#[cfg(test)]
+use crate::rectangle::{Axis, AxisAlignedRect};
+#[cfg(test)]
+use crate::sphere::Sphere;
 #[cfg(test)]
 use assert_approx_eq::assert_approx_eq;
 
+// Edge length in pixels of the tiles handed out to render_parallel threads
+const PARALLEL_TILE_SIZE: usize = 16;
+
+// Bounces every path gets before Russian roulette may end it
+const ROULETTE_MIN_BOUNCES: u32 = 3;
+
+// Lower bound on the odds of a path surviving Russian roulette, which limits how much the
+// survivors of very dark paths are boosted and so the noise they add
+const ROULETTE_MIN_SURVIVAL: f32 = 0.05;
+
+// Quality write_image_auto uses for .jpg and .jpeg files
+const DEFAULT_JPEG_QUALITY: u8 = 90;
+
+// What ray_color computes for each camera ray
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShadeMode {
+    Shaded,  // Full path traced color
+    Normals, // Surface normal of the first hit, mapped from [-1, 1] to [0, 1] RGB
+    Depth,   // Distance to the first hit as grayscale, white when close and fading to black
+}
+
+// Outline of the lens opening, which out of focus highlights take the shape of
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, Serialize)]
+pub enum BokehShape {
+    #[default]
+    Circle,
+    Polygon {
+        sides: usize,
+    }, // Regular polygon inscribed in the aperture, like a bladed iris
+}
+
+impl BokehShape {
+    // Uniformly distributed point inside the shape, scaled to fit the unit circle
+    pub fn sample_lens(&self) -> (f64, f64) {
+        let mut rng = rand::thread_rng();
+        loop {
+            let x = rng.gen_range(-1.0..1.0);
+            let y = rng.gen_range(-1.0..1.0);
+            if self.contains(x, y) {
+                return (x, y);
+            }
+        }
+    }
+
+    // Map a point of the unit square onto the shape, evenly and without rejection, so
+    // stratified samples stay stratified on the lens (e.g. from a SampleGenerator)
+    pub fn map_sample(&self, sample_x: f64, sample_y: f64) -> (f64, f64) {
+        match self {
+            BokehShape::Circle => {
+                // Concentric mapping, squares of the unit square become rings of the disk
+                let a = 2.0 * sample_x - 1.0;
+                let b = 2.0 * sample_y - 1.0;
+                if a == 0.0 && b == 0.0 {
+                    return (0.0, 0.0);
+                }
+
+                let (radius, angle) = if a.abs() > b.abs() {
+                    (a, FRAC_PI_4 * (b / a))
+                } else {
+                    (b, FRAC_PI_2 - FRAC_PI_4 * (a / b))
+                };
+                return (radius * angle.cos(), radius * angle.sin());
+            }
+            BokehShape::Polygon { sides } => {
+                // sample_x picks one of the triangles fanning out from the center and the rest
+                // of it, with sample_y, picks an evenly distributed point in that triangle
+                let sector = 2.0 * PI / *sides as f64;
+                let scaled = sample_x * *sides as f64;
+                let triangle = scaled.floor();
+                let along_edge = scaled - triangle;
+
+                let start = FRAC_PI_2 + triangle * sector;
+                let end = start + sector;
+                let from_center = sample_y.sqrt();
+                return (
+                    from_center * ((1.0 - along_edge) * start.cos() + along_edge * end.cos()),
+                    from_center * ((1.0 - along_edge) * start.sin() + along_edge * end.sin()),
+                );
+            }
+        }
+    }
+
+    fn contains(&self, x: f64, y: f64) -> bool {
+        let radius_squared = x * x + y * y;
+        match self {
+            BokehShape::Circle => return radius_squared < 1.0,
+            BokehShape::Polygon { sides } => {
+                // Angle from the middle of the closest edge, with the first corner at the top
+                let sector = 2.0 * PI / *sides as f64;
+                let angle = (y.atan2(x) - FRAC_PI_2).rem_euclid(sector) - sector / 2.0;
+
+                // Inside if the distance along that edge's normal is below the apothem
+                return radius_squared.sqrt() * angle.cos() < (sector / 2.0).cos();
+            }
+        }
+    }
+}
+
+// Counts of the work done by a render, from Camera::with_stats
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RenderStats {
+    // Samples taken through the camera, one each even when chromatic aberration traces
+    // each color channel along its own path
+    pub camera_rays: u64,
+    pub paths: u64, // Paths traced from the camera, three per sample with chromatic aberration
+    pub rays: u64,  // Every ray tested against the scene, camera rays included
+    pub bounces: u64, // Scattered rays followed from a surface or volume
+    pub shadow_rays: u64, // Occlusion tests towards point lights
+}
+
+impl RenderStats {
+    // Average number of rays per traced path, at least 1 when anything was rendered
+    pub fn average_path_length(&self) -> f64 {
+        if self.paths == 0 {
+            return 0.0;
+        }
+        return self.rays as f64 / self.paths as f64;
+    }
+}
+
+// Shared by the render threads while statistics are collected
+#[derive(Debug, Default)]
+struct RenderCounters {
+    camera_rays: AtomicU64,
+    paths: AtomicU64,
+    rays: AtomicU64,
+    bounces: AtomicU64,
+    shadow_rays: AtomicU64,
+}
+
+impl RenderCounters {
+    fn snapshot(&self) -> RenderStats {
+        return RenderStats {
+            camera_rays: self.camera_rays.load(Ordering::Relaxed),
+            paths: self.paths.load(Ordering::Relaxed),
+            rays: self.rays.load(Ordering::Relaxed),
+            bounces: self.bounces.load(Ordering::Relaxed),
+            shadow_rays: self.shadow_rays.load(Ordering::Relaxed),
+        };
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(from = "CameraConfig")]
 pub struct Camera {
@@ -38,16 +209,131 @@ pub struct Camera {
     vector_up: Vector3D,
     look_from: Vector3D,
     look_at: Vector3D,
+    background: Background,
+    #[serde(skip_serializing)]
+    shade_mode: ShadeMode,
+    exposure: f32, // Multiplier on the radiance reaching the sensor, applied before tone mapping
+    fog: Option<Fog>,
+    aperture: f64, // Lens diameter, 0 for a pinhole camera with everything in focus
+    focus_distance: f64, // Distance from the camera to the plane in perfect focus
+    bokeh_shape: BokehShape,
+    chromatic_aberration: f64, // Fraction of the focus distance red and blue are focused away from green
+    russian_roulette: bool, // End low contribution paths early instead of always tracing to max depth
+    light_sampling: bool, // Aim some diffuse bounces straight at the lights (next event estimation)
+    ambient_on_miss: bool, // Whether rays escaping after a bounce pick up the background
+    min_roughness: f64,   // Floor on the roughness of metals, 0 leaves mirrors sharp
+    #[serde(skip_serializing)]
+    stats: Option<Arc<RenderCounters>>, // Only counted inside with_stats
+    #[serde(skip_serializing)]
+    right: Vector3D, // Unit vectors spanning the lens
+    #[serde(skip_serializing)]
+    up: Vector3D,
+}
+
+// Lens and rendering options of a camera, stored alongside the view in CameraConfig
+// Any field left out when deserializing takes its value from CameraSettings::default
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CameraSettings {
+    pub exposure: f32,
+    pub fog: Option<Fog>,
+    pub aperture: f64,
+    pub focus_distance: f64,
+    pub bokeh_shape: BokehShape,
+    pub chromatic_aberration: f64,
+    pub russian_roulette: bool,
+    pub light_sampling: bool,
+    pub ambient_on_miss: bool,
+    pub min_roughness: f64,
+}
+
+impl Default for CameraSettings {
+    // Pinhole camera at exposure 1 without fog, with roulette, light sampling and ambient on
+    fn default() -> Self {
+        CameraSettings {
+            exposure: 1.0,
+            fog: None,
+            aperture: 0.0,
+            focus_distance: 1.0,
+            bokeh_shape: BokehShape::Circle,
+            chromatic_aberration: 0.0,
+            russian_roulette: true,
+            light_sampling: true,
+            ambient_on_miss: true,
+            min_roughness: 0.0,
+        }
+    }
 }
 
-#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct CameraConfig {
     pub aspect: f64,
     pub image_width: usize,
     pub vertical_fov: f64, // Vertical FOV in degrees
+    // Horizontal FOV in degrees, takes precedence over vertical_fov when set
+    #[serde(default)]
+    pub horizontal_fov: Option<f64>,
+    #[serde(default)]
+    pub background: Background,
     pub vector_up: Vector3D,
     pub look_from: Vector3D,
     pub look_at: Vector3D,
+    // Read from the same level as the view fields, so a Camera round trips through serde
+    #[serde(default, flatten)]
+    pub settings: CameraSettings,
+}
+
+impl Default for CameraConfig {
+    // 16:9 at 400 pixels wide with a 90 degree vertical FOV, looking down -Z from the origin
+    fn default() -> Self {
+        CameraConfig {
+            aspect: 16.0 / 9.0,
+            image_width: 400,
+            vertical_fov: 90.0,
+            horizontal_fov: None,
+            background: Background::default(),
+            vector_up: Vector3D::new(0.0, 1.0, 0.0),
+            look_from: Vector3D::new(0.0, 0.0, 0.0),
+            look_at: Vector3D::new(0.0, 0.0, -1.0),
+            settings: CameraSettings::default(),
+        }
+    }
+}
+
+impl CameraConfig {
+    // Config for an image of exactly width x height pixels, deriving the aspect ratio
+    // from the dimensions so the two cannot disagree
+    pub fn from_dimensions(
+        width: usize,
+        height: usize,
+        vertical_fov: f64,
+        vector_up: Vector3D,
+        look_from: Vector3D,
+        look_at: Vector3D,
+    ) -> CameraConfig {
+        return CameraConfig {
+            aspect: width as f64 / height as f64,
+            image_width: width,
+            vertical_fov: vertical_fov,
+            horizontal_fov: None,
+            background: Background::default(),
+            vector_up: vector_up,
+            look_from: look_from,
+            look_at: look_at,
+            settings: CameraSettings::default(),
+        };
+    }
+
+    // Effective vertical FOV in degrees, converting from the horizontal FOV if one is set
+    pub fn vertical_fov(&self) -> f64 {
+        match self.horizontal_fov {
+            Some(horizontal_fov) => {
+                let half_width = (horizontal_fov.to_radians() / 2.0).tan();
+                (2.0 * (half_width / self.aspect).atan()).to_degrees()
+            }
+            None => self.vertical_fov,
+        }
+    }
 }
 
 impl From<CameraConfig> for Camera {
@@ -58,163 +344,2470 @@ impl From<CameraConfig> for Camera {
 
 impl Camera {
     pub fn new(config: CameraConfig) -> Camera {
-        let theta = config.vertical_fov.to_radians();
+        let vertical_fov = config.vertical_fov();
+        let image_height = ((config.image_width as f64) / config.aspect).round() as usize;
+
+        let mut camera = Camera {
+            origin: config.look_from,
+            lower_left_corner: Vector3D::new(0.0, 0.0, 0.0),
+            focal_length: 0.0,
+            horizontal: Vector3D::new(0.0, 0.0, 0.0),
+            vertical: Vector3D::new(0.0, 0.0, 0.0),
+            look_from: config.look_from,
+            look_at: config.look_at,
+            vector_up: config.vector_up,
+            background: config.background,
+            shade_mode: ShadeMode::Shaded,
+            exposure: config.settings.exposure,
+            fog: config.settings.fog,
+            aperture: 0.0,
+            focus_distance: 1.0,
+            bokeh_shape: BokehShape::Circle,
+            chromatic_aberration: 0.0,
+            russian_roulette: config.settings.russian_roulette,
+            light_sampling: config.settings.light_sampling,
+            ambient_on_miss: config.settings.ambient_on_miss,
+            min_roughness: 0.0,
+            stats: None,
+            right: Vector3D::new(1.0, 0.0, 0.0),
+            up: Vector3D::new(0.0, 1.0, 0.0),
+            vertical_fov: vertical_fov,
+            aspect: config.aspect,
+            image_width: config.image_width,
+            image_height: image_height,
+        };
+
+        // Settings with limits go through their setters, which also lays out the view
+        let settings = config.settings;
+        camera.set_depth_of_field(settings.aperture, settings.focus_distance);
+        camera.set_bokeh_shape(settings.bokeh_shape);
+        camera.set_chromatic_aberration(settings.chromatic_aberration);
+        camera.set_min_roughness(settings.min_roughness);
+
+        return camera;
+    }
+
+    // Point the camera somewhere else, keeping the lens and image settings
+    pub fn set_look(&mut self, look_from: Vector3D, look_at: Vector3D, vector_up: Vector3D) {
+        self.look_from = look_from;
+        self.look_at = look_at;
+        self.vector_up = vector_up;
+        self.update_view();
+    }
+
+    // Recompute the viewport from the look and field-of-view settings
+    fn update_view(&mut self) {
+        let theta = self.vertical_fov.to_radians();
         let half_height = (theta / 2.0).tan();
-        let half_width = config.aspect * half_height;
+        let half_width = self.aspect * half_height;
 
         // Forward direction vector
-        let w = (config.look_from - config.look_at).unit_vector();
+        let w = (self.look_from - self.look_at).unit_vector();
 
         // Right direction vector (Cross product of forwards x updwards)
-        let u = config.vector_up.cross(&w).unit_vector();
+        let u = self.vector_up.cross(&w).unit_vector();
 
         // Up direction vector
         let v = w.cross(&u);
 
-        let origin = config.look_from;
+        self.origin = self.look_from;
+        self.right = u;
+        self.up = v;
+
+        // The viewport sits on the focus plane, so rays from anywhere on the lens meet there
+        let focus_distance = self.focus_distance;
 
         // Left corner is origin - forward direction - (right direction * half width) - (up direction * half height)
-        let lower_left_corner = origin - (u * half_width) - (v * half_height) - w;
+        self.lower_left_corner = self.origin
+            - (u * half_width * focus_distance)
+            - (v * half_height * focus_distance)
+            - w * focus_distance;
 
         // Full width * right direction
-        let horizontal = u * 2.0 * half_width;
+        self.horizontal = u * 2.0 * half_width * focus_distance;
 
         // Full height * up direction
-        let vertical = v * 2.0 * half_height;
+        self.vertical = v * 2.0 * half_height * focus_distance;
 
-        let image_height = ((config.image_width as f64) / config.aspect).round() as usize;
+        self.focal_length = (self.look_from - self.look_at).length();
+    }
 
-        return Camera {
-            origin,
-            lower_left_corner,
-            focal_length: (config.look_from - config.look_at).length(),
-            horizontal,
-            vertical,
-            look_from: config.look_from,
-            look_at: config.look_at,
-            vector_up: config.vector_up,
-            vertical_fov: config.vertical_fov,
-            aspect: config.aspect,
-            image_width: config.image_width,
-            image_height: image_height,
+    pub fn get_ray(&self, u: f64, v: f64) -> Ray {
+        return self.get_ray_with_lens(u, v, None);
+    }
+
+    // Viewport coordinates of a point in the image, in pixels from the top left corner
+    // e.g. (10.5, 3.5) is the center of pixel (10, 3)
+    pub(crate) fn pixel_uv(&self, x: f64, y: f64) -> (f64, f64) {
+        let u = x / (self.image_width as f64 - 1.0);
+        let v = (self.image_height as f64 - y) / (self.image_height as f64 - 1.0);
+        return (u, v);
+    }
+
+    // Ray from the center of the lens through a point in the image, in pixels from the top
+    // left corner like pixel_uv. Useful for picking the object under the cursor
+    pub fn pixel_to_ray(&self, x: f64, y: f64) -> Ray {
+        let (u, v) = self.pixel_uv(x, y);
+        let target = self.lower_left_corner + (self.horizontal * u) + (self.vertical * v);
+        return Ray::new(self.origin, target - self.origin);
+    }
+
+    // Where in the image a world point appears, in pixels from the top left corner, the
+    // inverse of pixel_to_ray. Points outside the field of view give coordinates outside
+    // the image, and points level with or behind the camera give None
+    pub fn project(&self, point: &Vector3D) -> Option<(f64, f64)> {
+        let direction = point - &self.origin;
+
+        // Follow the line of sight until it crosses the viewport
+        let normal = self.horizontal.cross(&self.vertical);
+        let along_normal = direction.dot(&normal);
+        if along_normal.abs() < 1e-12 {
+            return None;
+        }
+        let scale = (self.lower_left_corner - self.origin).dot(&normal) / along_normal;
+        if scale <= 0.0 {
+            return None;
+        }
+
+        let on_viewport = self.origin + direction * scale - self.lower_left_corner;
+        let u = on_viewport.dot(&self.horizontal) / self.horizontal.length_squared();
+        let v = on_viewport.dot(&self.vertical) / self.vertical.length_squared();
+
+        let x = u * (self.image_width as f64 - 1.0);
+        let y = self.image_height as f64 - v * (self.image_height as f64 - 1.0);
+        return Some((x, y));
+    }
+
+    // Like get_ray, with lens_sample in [0, 1) x [0, 1) picking the point on the lens instead
+    // of a random one. None picks a random point, and a pinhole camera ignores it
+    pub fn get_ray_with_lens(&self, u: f64, v: f64, lens_sample: Option<(f64, f64)>) -> Ray {
+        let target = self.lower_left_corner + (self.horizontal * u) + (self.vertical * v);
+        if self.aperture <= 0.0 {
+            return Ray::new(self.origin, target - self.origin);
+        }
+
+        // Start from a point on the lens, aimed at the same point on the focus plane
+        let origin = self.lens_point(lens_sample);
+        return Ray::new(origin, target - origin);
+    }
+
+    fn lens_point(&self, lens_sample: Option<(f64, f64)>) -> Vector3D {
+        let (lens_x, lens_y) = match lens_sample {
+            Some((sample_x, sample_y)) => self.bokeh_shape.map_sample(sample_x, sample_y),
+            None => self.bokeh_shape.sample_lens(),
         };
+        let lens_radius = self.aperture / 2.0;
+        return self.origin
+            + self.right * (lens_x * lens_radius)
+            + self.up * (lens_y * lens_radius);
     }
 
-    pub fn get_ray(&self, u: f64, v: f64) -> Ray {
-        return Ray::new(
-            self.origin,
-            self.lower_left_corner + (self.horizontal * u) + (self.vertical * v) - self.origin,
+    // Radiance seen through viewport point (u, v). With chromatic aberration the channels are
+    // focused at different distances, so each is traced separately from the same lens point.
+    // lens_sample picks the lens point like in get_ray_with_lens
+    pub fn sample_color(
+        &self,
+        u: f64,
+        v: f64,
+        lens_sample: Option<(f64, f64)>,
+        world: &World,
+        depth: i32,
+    ) -> LinSrgb {
+        self.count(|stats| &stats.camera_rays, 1);
+        if self.chromatic_aberration <= 0.0 || self.aperture <= 0.0 {
+            return self.ray_color(&self.get_ray_with_lens(u, v, lens_sample), world, depth);
+        }
+
+        // Scaling the direction to the focus plane moves the point of focus along the same line
+        // of sight. Like a simple glass lens, red is focused furthest away and blue closest
+        let target = self.lower_left_corner + (self.horizontal * u) + (self.vertical * v);
+        let origin = self.lens_point(lens_sample);
+        let [red, green, blue] = [
+            1.0 + self.chromatic_aberration,
+            1.0,
+            1.0 - self.chromatic_aberration,
+        ]
+        .map(|scale| {
+            let channel_target = self.origin + (target - self.origin) * scale;
+            self.ray_color(&Ray::new(origin, channel_target - origin), world, depth)
+        });
+
+        return LinSrgb::new(red.red, green.green, blue.blue);
+    }
+
+    pub fn chromatic_aberration(&self) -> f64 {
+        return self.chromatic_aberration;
+    }
+
+    // Colored fringes along out of focus edges, only visible with an aperture. 0 turns it off,
+    // otherwise red and blue are focused this fraction of the focus distance behind and in front
+    pub fn set_chromatic_aberration(&mut self, chromatic_aberration: f64) {
+        assert!(
+            (0.0..1.0).contains(&chromatic_aberration),
+            "Chromatic aberration must be in [0, 1)"
+        );
+        self.chromatic_aberration = chromatic_aberration;
+    }
+
+    pub fn aperture(&self) -> f64 {
+        return self.aperture;
+    }
+
+    pub fn focus_distance(&self) -> f64 {
+        return self.focus_distance;
+    }
+
+    // Blur everything away from focus_distance, more so with a wider aperture
+    // An aperture of 0 keeps the default pinhole camera
+    pub fn set_depth_of_field(&mut self, aperture: f64, focus_distance: f64) {
+        assert!(focus_distance > 0.0, "Focus distance must be positive");
+        self.aperture = aperture;
+        self.focus_distance = focus_distance;
+        self.update_view();
+    }
+
+    pub fn bokeh_shape(&self) -> BokehShape {
+        return self.bokeh_shape;
+    }
+
+    pub fn set_bokeh_shape(&mut self, bokeh_shape: BokehShape) {
+        if let BokehShape::Polygon { sides } = bokeh_shape {
+            assert!(sides >= 3, "A bokeh polygon needs at least 3 sides");
+        }
+        self.bokeh_shape = bokeh_shape;
+    }
+
+    pub fn background(&self) -> &Background {
+        return &self.background;
+    }
+
+    pub fn set_background(&mut self, background: Background) {
+        self.background = background;
+    }
+
+    // Shortcut for a vertical sky gradient, the default is (0.5, 0.7, 1.0) on top and white below
+    pub fn set_sky_gradient(&mut self, sky_top: Srgb, sky_bottom: Srgb) {
+        self.background = Background::Gradient {
+            top: sky_top,
+            bottom: sky_bottom,
+        };
+    }
+
+    pub fn exposure(&self) -> f32 {
+        return self.exposure;
+    }
+
+    // Brighten (above 1.0) or darken (below 1.0) the image before tone mapping
+    pub fn set_exposure(&mut self, exposure: f32) {
+        self.exposure = exposure;
+    }
+
+    pub fn fog(&self) -> Option<Fog> {
+        return self.fog;
+    }
+
+    // Fade hits towards the fog color with distance, None turns the fog off
+    pub fn set_fog(&mut self, fog: Option<Fog>) {
+        self.fog = fog;
+    }
+
+    pub fn russian_roulette(&self) -> bool {
+        return self.russian_roulette;
+    }
+
+    // Russian roulette is on by default, turning it off traces every path to the full depth
+    pub fn set_russian_roulette(&mut self, russian_roulette: bool) {
+        self.russian_roulette = russian_roulette;
+    }
+
+    pub fn light_sampling(&self) -> bool {
+        return self.light_sampling;
+    }
+
+    // Light sampling is on by default, turning it off leaves lights to be found by chance
+    pub fn set_light_sampling(&mut self, light_sampling: bool) {
+        self.light_sampling = light_sampling;
+    }
+
+    pub fn ambient_on_miss(&self) -> bool {
+        return self.ambient_on_miss;
+    }
+
+    // The background lights the scene by default. Turning this off keeps it visible directly
+    // but stops it from lighting surfaces, e.g. for closed rooms that should never see the sky
+    pub fn set_ambient_on_miss(&mut self, ambient_on_miss: bool) {
+        self.ambient_on_miss = ambient_on_miss;
+    }
+
+    pub fn min_roughness(&self) -> f64 {
+        return self.min_roughness;
+    }
+
+    // Render every metal at least this rough, trading perfectly sharp reflections for fewer
+    // fireflies from small bright lights seen in them. 0 by default, which changes nothing
+    pub fn set_min_roughness(&mut self, min_roughness: f64) {
+        assert!(
+            min_roughness >= 0.0,
+            "Minimum roughness must not be negative"
         );
+        self.min_roughness = min_roughness;
+    }
+
+    pub fn shade_mode(&self) -> ShadeMode {
+        return self.shade_mode;
+    }
+
+    pub fn set_shade_mode(&mut self, shade_mode: ShadeMode) {
+        self.shade_mode = shade_mode;
+    }
+
+    // Run render with a copy of the camera that counts rays as it goes, e.g.
+    // camera.with_stats(|camera| camera.render(&world, &anti_aliasing)). Counting is
+    // left out of every other render, so it costs nothing unless asked for
+    pub fn with_stats<T>(&self, render: impl FnOnce(&Camera) -> T) -> (T, RenderStats) {
+        let counters = Arc::new(RenderCounters::default());
+        let mut camera = self.clone();
+        camera.stats = Some(counters.clone());
+
+        let result = render(&camera);
+        return (result, counters.snapshot());
+    }
+
+    fn count(&self, counter: fn(&RenderCounters) -> &AtomicU64, amount: u64) {
+        if let Some(stats) = &self.stats {
+            counter(stats).fetch_add(amount, Ordering::Relaxed);
+        }
+    }
+
+    // Linear radiance carried back along the ray, only encoded to Srgb once per pixel
+    pub fn ray_color(&self, ray: &Ray, world: &World, depth: i32) -> LinSrgb {
+        match self.shade_mode {
+            ShadeMode::Shaded => {
+                return self.shaded_color(ray, world, depth, 0, LinSrgb::new(1.0, 1.0, 1.0));
+            }
+            ShadeMode::Normals | ShadeMode::Depth => return self.debug_color(ray, world),
+        }
+    }
+
+    // Same as ray_color for four rays, finding the first hits of all four together
+    pub fn ray_color4(&self, rays: &[Ray; 4], world: &World, depth: i32) -> [LinSrgb; 4] {
+        self.count(|stats| &stats.camera_rays, 4);
+        if self.shade_mode != ShadeMode::Shaded || depth <= 0 {
+            return [0, 1, 2, 3].map(|lane| self.ray_color(&rays[lane], world, depth));
+        }
+
+        self.count(|stats| &stats.paths, 4);
+        self.count(|stats| &stats.rays, 4);
+        let hits = world.hit4(rays, T_MIN, f64::MAX);
+        let mut lane = 0;
+        return hits.map(|hit| {
+            let throughput = LinSrgb::new(1.0, 1.0, 1.0);
+            let color = self.shade_hit(&rays[lane], hit, world, depth, 0, throughput);
+            lane += 1;
+            color
+        });
     }
 
-    pub fn ray_color(&self, ray: &Ray, world: &World, depth: i32) -> Srgb {
+    // bounces is how many surfaces the path has already scattered off, and throughput the
+    // product of their albedos, which Russian roulette uses to pick which paths to end early
+    fn shaded_color(
+        &self,
+        ray: &Ray,
+        world: &World,
+        depth: i32,
+        bounces: u32,
+        throughput: LinSrgb,
+    ) -> LinSrgb {
         if depth <= 0 {
-            return Srgb::new(0.0, 0.0, 0.0);
+            return LinSrgb::new(0.0, 0.0, 0.0);
+        }
+
+        // Only rays that are actually traced count, not ones cut off by the depth limit
+        self.count(|stats| &stats.rays, 1);
+        if bounces > 0 {
+            self.count(|stats| &stats.bounces, 1);
+        } else {
+            self.count(|stats| &stats.paths, 1);
+        }
+        let hit = world.hit(ray, T_MIN, f64::MAX);
+        return self.shade_hit(ray, hit, world, depth, bounces, throughput);
+    }
+
+    // Scatter off the material that was hit, roughening metals up to min_roughness
+    fn scatter(&self, ray: &Ray, hit_record: &ObjectHitRecord) -> Option<(Ray, Srgb)> {
+        if let Material::Metal(metal) = hit_record.material.as_ref() {
+            return metal.scatter_with_min_roughness(ray, hit_record, self.min_roughness);
         }
+        return hit_record.material.scatter(ray, hit_record);
+    }
 
-        let hit = world.hit(ray, 0.001, std::f64::MAX);
+    // Radiance along a ray given its first hit, tracing the rest of the path from there
+    fn shade_hit(
+        &self,
+        ray: &Ray,
+        hit: Option<ObjectHitRecord>,
+        world: &World,
+        depth: i32,
+        bounces: u32,
+        throughput: LinSrgb,
+    ) -> LinSrgb {
         match hit {
             Some(hit_record) => {
-                let scattered = hit_record.material.scatter(ray, &hit_record);
+                let emitted = linear(hit_record.material.emitted())
+                    + self.point_lighting(ray, &hit_record, world);
+                let scattered = self.scatter(ray, &hit_record);
 
-                match scattered {
+                let color = match scattered {
                     Some((scattered_ray, albedo)) => {
-                        let target_color = self.ray_color(&scattered_ray, world, depth - 1);
-                        return Srgb::new(
-                            albedo.red * target_color.red,
-                            albedo.green * target_color.green,
-                            albedo.blue * target_color.blue,
+                        let (scattered_ray, pdf_ratio) =
+                            self.sample_lights(&hit_record, scattered_ray, world);
+                        if pdf_ratio <= 0.0 {
+                            return self.apply_fog(emitted, ray, &hit_record);
+                        }
+
+                        let mut weight = linear(albedo) * pdf_ratio;
+                        let mut throughput = throughput * weight;
+
+                        // Russian roulette: past the first few bounces, end dim paths at random
+                        // and boost the survivors by the same odds, so the estimate stays unbiased
+                        if self.russian_roulette && bounces >= ROULETTE_MIN_BOUNCES {
+                            let survival = throughput
+                                .red
+                                .max(throughput.green)
+                                .max(throughput.blue)
+                                .clamp(ROULETTE_MIN_SURVIVAL, 1.0);
+                            if rand::thread_rng().r#gen::<f32>() >= survival {
+                                return self.apply_fog(emitted, ray, &hit_record);
+                            }
+                            weight /= survival;
+                            throughput /= survival;
+                        }
+
+                        let target_color = self.shaded_color(
+                            &scattered_ray,
+                            world,
+                            depth - 1,
+                            bounces + 1,
+                            throughput,
                         );
+                        emitted + weight * target_color
                     }
-                    None => {
-                        return Srgb::new(0.0, 0.0, 0.0);
-                    }
-                }
+                    None => emitted,
+                };
+
+                return self.apply_fog(color, ray, &hit_record);
             }
             None => {
-                let t: f32 = 0.5 * (ray.direction.unit_vector().get_y() as f32 + 1.0);
-                return Srgb::new(
-                    (1.0 - t) * 1.0 + t * 0.5,
-                    (1.0 - t) * 1.0 + t * 0.7,
-                    (1.0 - t) * 1.0 + t * 1.0,
-                );
+                if bounces > 0 && !self.ambient_on_miss {
+                    return LinSrgb::new(0.0, 0.0, 0.0);
+                }
+                return linear(self.background.color(ray));
             }
         }
     }
 
-    pub fn render(&self, world: &World, anti_aliasing: &AntiAliasing) -> Vec<u8> {
-        let mut pixels = vec![0; self.image_width * self.image_height * 3];
+    // Next event estimation for Lambertian surfaces: half of the bounces are aimed at a light
+    // instead of following the BRDF. Either way the ray is weighted by the BRDF's density over
+    // the average of both densities (multiple importance sampling with the balance heuristic),
+    // which is returned as a factor on the albedo
+    fn sample_lights(
+        &self,
+        hit_record: &ObjectHitRecord,
+        scattered_ray: Ray,
+        world: &World,
+    ) -> (Ray, f32) {
+        let lambertian = match hit_record.material.as_ref() {
+            Material::Lambertian(lambertian) if self.light_sampling => lambertian,
+            _ => return (scattered_ray, 1.0),
+        };
+        if world.emissive_objects().is_empty() {
+            return (scattered_ray, 1.0);
+        }
 
-        for y in 0..self.image_height {
-            for x in 0..self.image_width {
-                let color = anti_aliasing.anti_alias(x, y, self, world);
+        let pdf_light = PdfLight::new(scattered_ray.origin, world);
+        if pdf_light.is_empty() {
+            return (scattered_ray, 1.0);
+        }
 
-                let i = y * self.image_width + x;
-                let pixel: [u8; 3] = color.into_format().into_raw();
-                pixels[i * 3] = pixel[0];
-                pixels[i * 3 + 1] = pixel[1];
-                pixels[i * 3 + 2] = pixel[2];
-            }
+        let scattered_ray = if rand::thread_rng().r#gen::<bool>() {
+            Ray::new(scattered_ray.origin, pdf_light.generate())
+        } else {
+            scattered_ray
+        };
+
+        let brdf_pdf = lambertian.scattering_pdf(hit_record, &scattered_ray.direction);
+        let pdf = 0.5 * brdf_pdf + 0.5 * pdf_light.value(&scattered_ray.direction);
+        if pdf <= 0.0 {
+            return (scattered_ray, 0.0);
         }
 
-        return pixels;
+        return (scattered_ray, (brdf_pdf / pdf) as f32);
     }
 
-    pub fn write_image(
-        &self,
-        filename: &str,
-        pixels: &[u8],
-        width: usize,
-        height: usize,
-    ) -> Result<(), std::io::Error> {
-        let output = File::create(filename)?;
-        let encoder = PNGEncoder::new(output);
-        encoder.encode(pixels, width as u32, height as u32, ColorType::RGB(8))?;
-        Ok(())
+    // Fog between the ray origin and the hit, so every bounce is attenuated
+    fn apply_fog(&self, color: LinSrgb, ray: &Ray, hit_record: &ObjectHitRecord) -> LinSrgb {
+        match &self.fog {
+            Some(fog) => return fog.apply(color, hit_record.t * ray.direction.length()),
+            None => return color,
+        }
     }
-}
 
-#[test]
-fn test_camera() {
-    let camera_config = CameraConfig {
-        aspect: 800.0 / 600.0,
-        image_width: 800,
-        vertical_fov: 90.0,
-        vector_up: Vector3D::new(0.0, 1.0, 0.0),
-        look_from: Vector3D::new(0.0, 0.0, 0.0),
-        look_at: Vector3D::new(0.0, 0.0, -1.0),
-    };
+    // Light reaching the hit straight from the world's point lights, which only Phong
+    // surfaces respond to since rays can never hit a point light by chance
+    fn point_lighting(&self, ray: &Ray, hit_record: &ObjectHitRecord, world: &World) -> LinSrgb {
+        let mut total = LinSrgb::new(0.0, 0.0, 0.0);
+        let phong = match hit_record.material.as_ref() {
+            Material::Phong(phong) => phong,
+            _ => return total,
+        };
 
-    let camera = Camera::from(camera_config);
+        let to_viewer = -ray.direction.unit_vector();
+        for light in world.lights() {
+            let to_light = light.position() - hit_record.point;
+            let distance = to_light.length();
+            let to_light = to_light / distance;
 
-    assert_eq!(camera.origin.get_x(), 0.0);
-    assert_eq!(camera.origin.get_y(), 0.0);
-    assert_eq!(camera.origin.get_z(), 0.0);
+            // Skip lights hidden behind other objects
+            let shadow_ray = Ray::new(hit_record.point, to_light)
+                .offset_origin(&hit_record.normal, SURFACE_EPSILON);
+            self.count(|stats| &stats.shadow_rays, 1);
+            if world.hit_any(&shadow_ray, 0.0, distance) {
+                continue;
+            }
 
-    assert_approx_eq!(camera.lower_left_corner.get_x(), -(1.0 + (1.0 / 3.0)));
-    assert_approx_eq!(camera.lower_left_corner.get_y(), -1.0);
-    assert_approx_eq!(camera.lower_left_corner.get_z(), -1.0);
-}
+            let reflectance = phong.reflectance(&hit_record.normal, &to_viewer, &to_light);
+            total += linear(reflectance) * linear(light.intensity()) / (distance * distance) as f32;
+        }
 
-#[test]
-fn test_camera_get_ray() {
-    let camera_config = CameraConfig {
-        aspect: 800.0 / 600.0,
-        image_width: 800,
-        vertical_fov: 90.0,
-        vector_up: Vector3D::new(0.0, 1.0, 0.0),
-        look_from: Vector3D::new(-4.0, 4.0, 1.0),
-        look_at: Vector3D::new(0.0, 0.0, -1.0),
-    };
+        return total;
+    }
 
-    let camera = Camera::from(camera_config);
-    let ray = camera.get_ray(0.5, 0.5);
+    // Color of the first hit for the Normals and Depth modes, misses are black
+    fn debug_color(&self, ray: &Ray, world: &World) -> LinSrgb {
+        self.count(|stats| &stats.rays, 1);
+        let hit_record = match world.hit(ray, T_MIN, f64::MAX) {
+            Some(hit_record) => hit_record,
+            None => return LinSrgb::new(0.0, 0.0, 0.0),
+        };
+
+        if self.shade_mode == ShadeMode::Normals {
+            let color = (hit_record.normal + Vector3D::new(1.0, 1.0, 1.0)) * 0.5;
+            return LinSrgb::new(
+                color.get_x() as f32,
+                color.get_y() as f32,
+                color.get_z() as f32,
+            );
+        }
+
+        // Camera rays are as long as the focus distance, so t alone is not a distance
+        let distance = hit_record.t * ray.direction.length();
+        let gray = (1.0 / (1.0 + distance)) as f32;
+        return LinSrgb::new(gray, gray, gray);
+    }
+
+    pub fn render(&self, world: &World, anti_aliasing: &AntiAliasing) -> Vec<u8> {
+        return self.render_with_progress(world, anti_aliasing, |_, _| {});
+    }
+
+    // Render straight into an image buffer, which knows its own dimensions and can be saved,
+    // resized or composited with the image crate
+    pub fn render_image(&self, world: &World, anti_aliasing: &AntiAliasing) -> RgbImage {
+        let pixels = self.render(world, anti_aliasing);
+        return RgbImage::from_raw(self.image_width as u32, self.image_height as u32, pixels)
+            .expect("Rendered pixels match the image dimensions");
+    }
+
+    // Render the image, calling progress(rows_done, total_rows) after every completed row
+    pub fn render_with_progress(
+        &self,
+        world: &World,
+        anti_aliasing: &AntiAliasing,
+        progress: impl FnMut(usize, usize),
+    ) -> Vec<u8> {
+        let mut pixels = vec![0; self.image_width * self.image_height * 3];
+        self.render_rows(world, anti_aliasing, &mut pixels, progress);
+        return pixels;
+    }
+
+    // Render into a caller owned RGB buffer, e.g. to reuse one allocation across animation frames
+    // The buffer must hold exactly image_width * image_height * 3 bytes
+    pub fn render_into(
+        &self,
+        world: &World,
+        anti_aliasing: &AntiAliasing,
+        pixels: &mut [u8],
+    ) -> Result<(), RayTracerError> {
+        let expected_length = self.image_width * self.image_height * 3;
+        if pixels.len() != expected_length {
+            return Err(RayTracerError::InvalidInput(format!(
+                "Pixel buffer holds {} bytes but the image needs {}",
+                pixels.len(),
+                expected_length
+            )));
+        }
+
+        self.render_rows(world, anti_aliasing, pixels, |_, _| {});
+        Ok(())
+    }
+
+    fn render_rows(
+        &self,
+        world: &World,
+        anti_aliasing: &AntiAliasing,
+        pixels: &mut [u8],
+        mut progress: impl FnMut(usize, usize),
+    ) {
+        for y in 0..self.image_height {
+            for x in 0..self.image_width {
+                let color = anti_aliasing.anti_alias(x, y, self, world);
+                write_pixel(pixels, y * self.image_width + x, color);
+            }
+
+            progress(y + 1, self.image_height);
+        }
+    }
+
+    // Render the image in square tiles of tile_size pixels, each rendered independently
+    // Tiles on the right and bottom edges are clipped to the image bounds
+    pub fn render_tiles(
+        &self,
+        world: &World,
+        anti_aliasing: &AntiAliasing,
+        tile_size: usize,
+    ) -> Vec<u8> {
+        let mut pixels = vec![0; self.image_width * self.image_height * 3];
+
+        for tile in self.tiles(tile_size) {
+            let tile_pixels = self.render_tile(world, anti_aliasing, &tile);
+            self.copy_tile(&mut pixels, &tile, &tile_pixels);
+        }
+
+        return pixels;
+    }
+
+    // Render only the pixels from (x0, y0) inclusive to (x1, y1) exclusive, e.g. to debug one area
+    // or split a frame across machines. The buffer holds just the region, row major from its top left
+    pub fn render_region(
+        &self,
+        world: &World,
+        anti_aliasing: &AntiAliasing,
+        x0: usize,
+        y0: usize,
+        x1: usize,
+        y1: usize,
+    ) -> Result<Vec<u8>, RayTracerError> {
+        if x0 >= x1 || y0 >= y1 || x1 > self.image_width || y1 > self.image_height {
+            return Err(RayTracerError::InvalidInput(format!(
+                "Region ({}, {}) to ({}, {}) is empty or outside the {}x{} image",
+                x0, y0, x1, y1, self.image_width, self.image_height
+            )));
+        }
+
+        let region = Tile {
+            x0: x0,
+            y0: y0,
+            x1: x1,
+            y1: y1,
+        };
+        return Ok(self.render_tile(world, anti_aliasing, &region));
+    }
+
+    // Render at 16 bits per channel, which avoids banding in smooth gradients
+    pub fn render_16bit(&self, world: &World, anti_aliasing: &AntiAliasing) -> Vec<u16> {
+        let mut pixels = vec![0; self.image_width * self.image_height * 3];
+
+        for y in 0..self.image_height {
+            for x in 0..self.image_width {
+                let color = anti_aliasing.anti_alias(x, y, self, world);
+                write_pixel_16bit(&mut pixels, y * self.image_width + x, color);
+            }
+        }
+
+        return pixels;
+    }
+
+    // Linear radiance per pixel as RGB floats, before exposure and tone mapping, in the same
+    // order as render. Nothing is clamped, so this keeps the full range for HDR output
+    pub fn render_radiance(&self, world: &World, anti_aliasing: &AntiAliasing) -> Vec<f32> {
+        let mut radiance = Vec::with_capacity(self.image_width * self.image_height * 3);
+
+        for y in 0..self.image_height {
+            for x in 0..self.image_width {
+                let color = anti_aliasing.radiance(x, y, self, world);
+                radiance.extend_from_slice(&[color.red, color.green, color.blue]);
+            }
+        }
+
+        return radiance;
+    }
+
+    // Grayscale RGB image of how many samples each pixel took, from black for none to white
+    // for anti_aliasing's full samples_per_pixel, e.g. to see where adaptive sampling spends
+    // its effort while tuning the tolerance. Same layout as render, so it can be written out
+    // with write_image
+    pub fn render_sample_heatmap(&self, world: &World, anti_aliasing: &AntiAliasing) -> Vec<u8> {
+        let mut pixels = vec![0; self.image_width * self.image_height * 3];
+        let max_samples = anti_aliasing.samples_per_pixel().max(1) as f32;
+
+        for y in 0..self.image_height {
+            for x in 0..self.image_width {
+                let (_, sample_count) = anti_aliasing.anti_alias_with_count(x, y, self, world);
+                let level = (sample_count as f32 / max_samples).min(1.0);
+                write_pixel(
+                    &mut pixels,
+                    y * self.image_width + x,
+                    Srgb::new(level, level, level),
+                );
+            }
+        }
+
+        return pixels;
+    }
+
+    // Distance along one center ray per pixel to the first hit, f64::INFINITY where nothing is hit
+    // Pixels are in the same order as render, so this can be composited with the color image
+    pub fn render_depth(&self, world: &World) -> Vec<f64> {
+        let mut depths = Vec::with_capacity(self.image_width * self.image_height);
+
+        for y in 0..self.image_height {
+            for x in 0..self.image_width {
+                // Same ray as the center sample of the color render
+                let (u, v) = self.pixel_uv(x as f64 + 0.5, y as f64 + 0.5);
+                let ray = self.get_ray(u, v);
+
+                match world.hit(&ray, T_MIN, f64::MAX) {
+                    Some(hit_record) => depths.push(hit_record.t * ray.direction.length()),
+                    None => depths.push(f64::INFINITY),
+                }
+            }
+        }
+
+        return depths;
+    }
+
+    // Render on num_threads threads that share a queue of tiles, each thread taking the next
+    // unrendered tile as soon as it finishes one so expensive regions do not hold up the rest
+    pub fn render_parallel(
+        &self,
+        world: &World,
+        anti_aliasing: &AntiAliasing,
+        num_threads: usize,
+    ) -> Vec<u8> {
+        let tiles = self.tiles(PARALLEL_TILE_SIZE);
+        let next_tile = AtomicUsize::new(0);
+        let rendered = Mutex::new(Vec::with_capacity(tiles.len()));
+
+        thread::scope(|scope| {
+            for _ in 0..num_threads.max(1) {
+                scope.spawn(|| {
+                    loop {
+                        let index = next_tile.fetch_add(1, Ordering::Relaxed);
+                        if index >= tiles.len() {
+                            break;
+                        }
+
+                        let tile_pixels = self.render_tile(world, anti_aliasing, &tiles[index]);
+                        rendered.lock().unwrap().push((index, tile_pixels));
+                    }
+                });
+            }
+        });
+
+        let mut pixels = vec![0; self.image_width * self.image_height * 3];
+        for (index, tile_pixels) in rendered.into_inner().unwrap() {
+            self.copy_tile(&mut pixels, &tiles[index], &tile_pixels);
+        }
+
+        return pixels;
+    }
+
+    // Split the image into tiles, row by row from the top left
+    fn tiles(&self, tile_size: usize) -> Vec<Tile> {
+        assert!(tile_size > 0, "Tile size must be at least one pixel");
+
+        let mut tiles = Vec::new();
+        for y0 in (0..self.image_height).step_by(tile_size) {
+            for x0 in (0..self.image_width).step_by(tile_size) {
+                tiles.push(Tile {
+                    x0: x0,
+                    y0: y0,
+                    x1: (x0 + tile_size).min(self.image_width),
+                    y1: (y0 + tile_size).min(self.image_height),
+                });
+            }
+        }
+        return tiles;
+    }
+
+    // Render a single tile into its own tightly packed RGB buffer
+    fn render_tile(&self, world: &World, anti_aliasing: &AntiAliasing, tile: &Tile) -> Vec<u8> {
+        let tile_width = tile.x1 - tile.x0;
+        let mut pixels = vec![0; tile_width * (tile.y1 - tile.y0) * 3];
+
+        for y in tile.y0..tile.y1 {
+            for x in tile.x0..tile.x1 {
+                let color = anti_aliasing.anti_alias(x, y, self, world);
+                write_pixel(
+                    &mut pixels,
+                    (y - tile.y0) * tile_width + (x - tile.x0),
+                    color,
+                );
+            }
+        }
+
+        return pixels;
+    }
+
+    // Copy a rendered tile into its place in the full image
+    fn copy_tile(&self, pixels: &mut [u8], tile: &Tile, tile_pixels: &[u8]) {
+        let row_length = (tile.x1 - tile.x0) * 3;
+        for (row, y) in (tile.y0..tile.y1).enumerate() {
+            let start = (y * self.image_width + tile.x0) * 3;
+            pixels[start..start + row_length]
+                .copy_from_slice(&tile_pixels[row * row_length..(row + 1) * row_length]);
+        }
+    }
+
+    pub fn write_image(
+        &self,
+        filename: &str,
+        pixels: &[u8],
+        width: usize,
+        height: usize,
+    ) -> Result<(), RayTracerError> {
+        let encoded = self.encode_png(pixels, width, height)?;
+        let mut output = File::create(filename)?;
+        output.write_all(&encoded)?;
+        Ok(())
+    }
+
+    // Encode the pixels as a PNG in memory, without touching the filesystem
+    pub fn encode_png(
+        &self,
+        pixels: &[u8],
+        width: usize,
+        height: usize,
+    ) -> Result<Vec<u8>, RayTracerError> {
+        let mut output = Cursor::new(Vec::new());
+        let encoder = PNGEncoder::new(&mut output);
+        encoder.encode(pixels, width as u32, height as u32, ColorType::RGB(8))?;
+        Ok(output.into_inner())
+    }
+
+    pub fn write_image_16bit(
+        &self,
+        filename: &str,
+        pixels: &[u16],
+        width: usize,
+        height: usize,
+    ) -> Result<(), RayTracerError> {
+        let encoded = self.encode_png_16bit(pixels, width, height)?;
+        let mut output = File::create(filename)?;
+        output.write_all(&encoded)?;
+        Ok(())
+    }
+
+    // Encode 16 bit pixels as a PNG in memory, PNG stores the samples big endian
+    pub fn encode_png_16bit(
+        &self,
+        pixels: &[u16],
+        width: usize,
+        height: usize,
+    ) -> Result<Vec<u8>, RayTracerError> {
+        let bytes: Vec<u8> = pixels
+            .iter()
+            .flat_map(|channel| channel.to_be_bytes())
+            .collect();
+
+        let mut output = Cursor::new(Vec::new());
+        let encoder = PNGEncoder::new(&mut output);
+        encoder.encode(&bytes, width as u32, height as u32, ColorType::RGB(16))?;
+        Ok(output.into_inner())
+    }
+
+    // Lossy JPEG, quality runs from 1 (smallest file) to 100 (best quality)
+    pub fn write_jpeg(
+        &self,
+        filename: &str,
+        pixels: &[u8],
+        width: usize,
+        height: usize,
+        quality: u8,
+    ) -> Result<(), RayTracerError> {
+        let encoded = self.encode_jpeg(pixels, width, height, quality)?;
+        let mut output = File::create(filename)?;
+        output.write_all(&encoded)?;
+        Ok(())
+    }
+
+    pub fn encode_jpeg(
+        &self,
+        pixels: &[u8],
+        width: usize,
+        height: usize,
+        quality: u8,
+    ) -> Result<Vec<u8>, RayTracerError> {
+        if !(1..=100).contains(&quality) {
+            return Err(RayTracerError::InvalidInput(format!(
+                "JPEG quality {} is outside [1, 100]",
+                quality
+            )));
+        }
+        check_rgb_buffer(pixels.len(), width, height)?;
+
+        let mut output = Vec::new();
+        let mut encoder = JPEGEncoder::new_with_quality(&mut output, quality);
+        encoder.encode(pixels, width as u32, height as u32, ColorType::RGB(8))?;
+        Ok(output)
+    }
+
+    pub fn write_bmp(
+        &self,
+        filename: &str,
+        pixels: &[u8],
+        width: usize,
+        height: usize,
+    ) -> Result<(), RayTracerError> {
+        let encoded = self.encode_bmp(pixels, width, height)?;
+        let mut output = File::create(filename)?;
+        output.write_all(&encoded)?;
+        Ok(())
+    }
+
+    // Uncompressed 24 bit BMP, written by hand since the image crate can only decode BMPs
+    pub fn encode_bmp(
+        &self,
+        pixels: &[u8],
+        width: usize,
+        height: usize,
+    ) -> Result<Vec<u8>, RayTracerError> {
+        check_rgb_buffer(pixels.len(), width, height)?;
+
+        // Rows are stored bottom up as BGR, each padded to a multiple of 4 bytes
+        let row_length = (width * 3).div_ceil(4) * 4;
+        let image_size = row_length * height;
+        let header_size = 14 + 40;
+
+        let mut output = Vec::with_capacity(header_size + image_size);
+
+        // File header
+        output.extend_from_slice(b"BM");
+        output.extend_from_slice(&((header_size + image_size) as u32).to_le_bytes());
+        output.extend_from_slice(&[0; 4]);
+        output.extend_from_slice(&(header_size as u32).to_le_bytes());
+
+        // BITMAPINFOHEADER
+        output.extend_from_slice(&40u32.to_le_bytes());
+        output.extend_from_slice(&(width as i32).to_le_bytes());
+        output.extend_from_slice(&(height as i32).to_le_bytes());
+        output.extend_from_slice(&1u16.to_le_bytes()); // Color planes
+        output.extend_from_slice(&24u16.to_le_bytes()); // Bits per pixel
+        output.extend_from_slice(&0u32.to_le_bytes()); // No compression
+        output.extend_from_slice(&(image_size as u32).to_le_bytes());
+        output.extend_from_slice(&2835i32.to_le_bytes()); // 72 DPI horizontally
+        output.extend_from_slice(&2835i32.to_le_bytes()); // and vertically
+        output.extend_from_slice(&0u32.to_le_bytes()); // No palette
+        output.extend_from_slice(&0u32.to_le_bytes());
+
+        for row in pixels.chunks(width * 3).rev() {
+            for pixel in row.chunks(3) {
+                output.extend_from_slice(&[pixel[2], pixel[1], pixel[0]]);
+            }
+            output.resize(output.len() + row_length - width * 3, 0);
+        }
+
+        return Ok(output);
+    }
+
+    // Radiance RGBE (.hdr) file from linear RGB floats, e.g. from render_radiance
+    pub fn write_hdr(
+        &self,
+        filename: &str,
+        radiance: &[f32],
+        width: usize,
+        height: usize,
+    ) -> Result<(), RayTracerError> {
+        let encoded = self.encode_hdr(radiance, width, height)?;
+        let mut output = File::create(filename)?;
+        output.write_all(&encoded)?;
+        Ok(())
+    }
+
+    // Each pixel keeps an 8 bit mantissa per channel with a shared exponent, so values far
+    // above 1.0 survive but channels much dimmer than the brightest one in a pixel lose precision
+    pub fn encode_hdr(
+        &self,
+        radiance: &[f32],
+        width: usize,
+        height: usize,
+    ) -> Result<Vec<u8>, RayTracerError> {
+        if radiance.len() != width * height * 3 {
+            return Err(RayTracerError::InvalidInput(format!(
+                "Radiance buffer holds {} floats but a {}x{} image needs {}",
+                radiance.len(),
+                width,
+                height,
+                width * height * 3
+            )));
+        }
+
+        let pixels: Vec<Rgb<f32>> = radiance
+            .chunks(3)
+            .map(|pixel| Rgb {
+                data: [pixel[0], pixel[1], pixel[2]],
+            })
+            .collect();
+
+        let mut output = Vec::new();
+        HDREncoder::new(&mut output).encode(&pixels, width, height)?;
+        Ok(output)
+    }
+
+    pub fn write_ppm(
+        &self,
+        filename: &str,
+        pixels: &[u8],
+        width: usize,
+        height: usize,
+    ) -> Result<(), RayTracerError> {
+        check_rgb_buffer(pixels.len(), width, height)?;
+        let mut output = File::create(filename)?;
+
+        // Binary P6 header followed by the raw RGB bytes
+        write!(output, "P6\n{} {}\n255\n", width, height)?;
+        output.write_all(pixels)?;
+        Ok(())
+    }
+
+    // Pick the output format from the file extension
+    pub fn write_image_auto(
+        &self,
+        filename: &str,
+        pixels: &[u8],
+        width: usize,
+        height: usize,
+    ) -> Result<(), RayTracerError> {
+        let extension = Path::new(filename)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase());
+
+        match extension.as_deref() {
+            Some("png") => self.write_image(filename, pixels, width, height),
+            Some("ppm") => self.write_ppm(filename, pixels, width, height),
+            Some("jpg") | Some("jpeg") => {
+                self.write_jpeg(filename, pixels, width, height, DEFAULT_JPEG_QUALITY)
+            }
+            Some("bmp") => self.write_bmp(filename, pixels, width, height),
+            _ => Err(RayTracerError::InvalidInput(format!(
+                "Unsupported image format for {}",
+                filename
+            ))),
+        }
+    }
+}
+
+// Render one PNG per camera into out_dir, named prefix_0000.png, prefix_0001.png, ...
+// so a turntable or flythrough is just an iterator of cameras. Returns the written paths
+pub fn render_animation(
+    camera_frames: impl Iterator<Item = Camera>,
+    world: &World,
+    anti_aliasing: &AntiAliasing,
+    out_dir: impl AsRef<Path>,
+    prefix: &str,
+) -> Result<Vec<PathBuf>, RayTracerError> {
+    let mut paths = Vec::new();
+
+    for (frame, camera) in camera_frames.enumerate() {
+        let pixels = camera.render(world, anti_aliasing);
+        let encoded = camera.encode_png(&pixels, camera.image_width, camera.image_height)?;
+
+        let path = out_dir
+            .as_ref()
+            .join(format!("{}_{:04}.png", prefix, frame));
+        let mut output = File::create(&path)?;
+        output.write_all(&encoded)?;
+        paths.push(path);
+    }
+
+    return Ok(paths);
+}
+
+// Rectangular block of pixels, from (x0, y0) inclusive to (x1, y1) exclusive
+struct Tile {
+    x0: usize,
+    y0: usize,
+    x1: usize,
+    y1: usize,
+}
+
+// Albedos and background colors are given as linear reflectance and radiance,
+// so their components are used as is
+fn linear(color: Srgb) -> LinSrgb {
+    return LinSrgb::from_components(color.into_components());
+}
+
+// Error unless a tightly packed RGB buffer of length bytes holds a non-empty width x height image
+fn check_rgb_buffer(length: usize, width: usize, height: usize) -> Result<(), RayTracerError> {
+    if width == 0 || length != width * height * 3 {
+        return Err(RayTracerError::InvalidInput(format!(
+            "Pixel buffer holds {} bytes but a {}x{} image needs {}",
+            length,
+            width,
+            height,
+            width * height * 3
+        )));
+    }
+    return Ok(());
+}
+
+// Store an encoded color at pixel index i of a tightly packed RGB buffer
+fn write_pixel(pixels: &mut [u8], i: usize, color: Srgb) {
+    let pixel: [u8; 3] = color.into_format().into_raw();
+    pixels[i * 3] = pixel[0];
+    pixels[i * 3 + 1] = pixel[1];
+    pixels[i * 3 + 2] = pixel[2];
+}
+
+// Same as write_pixel, for a 16 bit per channel buffer
+fn write_pixel_16bit(pixels: &mut [u16], i: usize, color: Srgb) {
+    let pixel: [u16; 3] = color.into_format().into_raw();
+    pixels[i * 3] = pixel[0];
+    pixels[i * 3 + 1] = pixel[1];
+    pixels[i * 3 + 2] = pixel[2];
+}
+
+#[test]
+fn test_camera() {
+    let camera_config = CameraConfig {
+        aspect: 800.0 / 600.0,
+        image_width: 800,
+        vertical_fov: 90.0,
+        horizontal_fov: None,
+        background: Background::default(),
+        vector_up: Vector3D::new(0.0, 1.0, 0.0),
+        look_from: Vector3D::new(0.0, 0.0, 0.0),
+        look_at: Vector3D::new(0.0, 0.0, -1.0),
+        settings: CameraSettings::default(),
+    };
+
+    let camera = Camera::from(camera_config);
+
+    assert_eq!(camera.origin.get_x(), 0.0);
+    assert_eq!(camera.origin.get_y(), 0.0);
+    assert_eq!(camera.origin.get_z(), 0.0);
+
+    assert_approx_eq!(camera.lower_left_corner.get_x(), -(1.0 + (1.0 / 3.0)));
+    assert_approx_eq!(camera.lower_left_corner.get_y(), -1.0);
+    assert_approx_eq!(camera.lower_left_corner.get_z(), -1.0);
+}
+
+#[test]
+fn test_camera_get_ray() {
+    let camera_config = CameraConfig {
+        aspect: 800.0 / 600.0,
+        image_width: 800,
+        vertical_fov: 90.0,
+        horizontal_fov: None,
+        background: Background::default(),
+        vector_up: Vector3D::new(0.0, 1.0, 0.0),
+        look_from: Vector3D::new(-4.0, 4.0, 1.0),
+        look_at: Vector3D::new(0.0, 0.0, -1.0),
+        settings: CameraSettings::default(),
+    };
+
+    let camera = Camera::from(camera_config);
+    let ray = camera.get_ray(0.5, 0.5);
 
     assert_eq!(ray.origin.get_x(), -4.0);
     assert_eq!(ray.origin.get_y(), 4.0);
     assert_eq!(ray.origin.get_z(), 1.0);
 
-    assert_approx_eq!(ray.direction.get_x(), (2.0 / 3.0));
-    assert_approx_eq!(ray.direction.get_y(), -(2.0 / 3.0));
-    assert_approx_eq!(ray.direction.get_z(), -(1.0 / 3.0));
+    assert_approx_eq!(ray.direction.get_x(), (2.0 / 3.0));
+    assert_approx_eq!(ray.direction.get_y(), -(2.0 / 3.0));
+    assert_approx_eq!(ray.direction.get_z(), -(1.0 / 3.0));
+}
+
+#[test]
+fn test_camera_write_ppm() {
+    let camera_config = CameraConfig {
+        aspect: 1.0,
+        image_width: 2,
+        vertical_fov: 90.0,
+        horizontal_fov: None,
+        background: Background::default(),
+        vector_up: Vector3D::new(0.0, 1.0, 0.0),
+        look_from: Vector3D::new(0.0, 0.0, 0.0),
+        look_at: Vector3D::new(0.0, 0.0, -1.0),
+        settings: CameraSettings::default(),
+    };
+
+    let camera = Camera::from(camera_config);
+    let pixels: [u8; 12] = [255, 0, 0, 0, 255, 0, 0, 0, 255, 255, 255, 255];
+
+    let path = std::env::temp_dir().join("ray_tracer_test_camera_write_ppm.ppm");
+    let filename = path.to_str().unwrap();
+    camera
+        .write_image_auto(filename, &pixels, 2, 2)
+        .expect("Failed to write image");
+
+    let bytes = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let header = b"P6\n2 2\n255\n";
+    assert!(bytes.starts_with(header));
+    assert_eq!(bytes.len(), header.len() + pixels.len());
+    assert_eq!(&bytes[header.len()..], &pixels);
+
+    // A buffer that does not fit the dimensions is rejected before anything is written
+    let error = camera.write_ppm(filename, &pixels[..9], 2, 2).unwrap_err();
+    assert!(matches!(error, RayTracerError::InvalidInput(_)));
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_camera_encode_png() {
+    let camera_config = CameraConfig {
+        aspect: 1.0,
+        image_width: 2,
+        vertical_fov: 90.0,
+        horizontal_fov: None,
+        background: Background::default(),
+        vector_up: Vector3D::new(0.0, 1.0, 0.0),
+        look_from: Vector3D::new(0.0, 0.0, 0.0),
+        look_at: Vector3D::new(0.0, 0.0, -1.0),
+        settings: CameraSettings::default(),
+    };
+
+    let camera = Camera::from(camera_config);
+    let pixels = [128u8; 12];
+
+    let encoded = camera
+        .encode_png(&pixels, 2, 2)
+        .expect("Failed to encode image");
+
+    assert!(encoded.starts_with(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]));
+}
+
+#[test]
+fn test_camera_render_with_progress() {
+    let camera_config = CameraConfig {
+        aspect: 2.0,
+        image_width: 8,
+        vertical_fov: 90.0,
+        horizontal_fov: None,
+        background: Background::default(),
+        vector_up: Vector3D::new(0.0, 1.0, 0.0),
+        look_from: Vector3D::new(0.0, 0.0, 0.0),
+        look_at: Vector3D::new(0.0, 0.0, -1.0),
+        settings: CameraSettings::default(),
+    };
+
+    let camera = Camera::from(camera_config);
+    let world = World::new();
+    let anti_aliasing = AntiAliasing::new(1, AntiAliasingTechnique::None);
+
+    let mut calls = Vec::new();
+    let pixels = camera.render_with_progress(&world, &anti_aliasing, |done, total| {
+        calls.push((done, total));
+    });
+
+    assert_eq!(pixels.len(), camera.image_width * camera.image_height * 3);
+    assert_eq!(calls.len(), camera.image_height);
+    for (i, (done, total)) in calls.iter().enumerate() {
+        assert_eq!(*done, i + 1);
+        assert_eq!(*total, camera.image_height);
+    }
+}
+
+#[test]
+fn test_camera_horizontal_fov() {
+    let vertical_config = CameraConfig {
+        aspect: 1.0,
+        image_width: 100,
+        vertical_fov: 90.0,
+        horizontal_fov: None,
+        background: Background::default(),
+        vector_up: Vector3D::new(0.0, 1.0, 0.0),
+        look_from: Vector3D::new(0.0, 0.0, 0.0),
+        look_at: Vector3D::new(0.0, 0.0, -1.0),
+        settings: CameraSettings::default(),
+    };
+    let horizontal_config = CameraConfig {
+        vertical_fov: 0.0,
+        horizontal_fov: Some(90.0),
+        ..vertical_config.clone()
+    };
+
+    let vertical_camera = Camera::from(vertical_config);
+    let horizontal_camera = Camera::from(horizontal_config.clone());
+
+    assert_approx_eq!(horizontal_config.vertical_fov(), 90.0);
+    assert_eq!(
+        horizontal_camera.lower_left_corner,
+        vertical_camera.lower_left_corner
+    );
+    assert_eq!(horizontal_camera.horizontal, vertical_camera.horizontal);
+    assert_eq!(horizontal_camera.vertical, vertical_camera.vertical);
+
+    // At a wide aspect ratio the vertical FOV must be narrower than the horizontal one
+    let wide_config = CameraConfig {
+        aspect: 2.0,
+        ..horizontal_config
+    };
+    let wide_camera = Camera::from(wide_config);
+    assert_approx_eq!(wide_camera.horizontal.length(), 2.0);
+    assert_approx_eq!(wide_camera.vertical.length(), 1.0);
+}
+
+#[test]
+fn test_camera_solid_background() {
+    let camera_config = CameraConfig {
+        aspect: 1.0,
+        image_width: 4,
+        vertical_fov: 90.0,
+        horizontal_fov: None,
+        background: Background::SolidColor(Srgb::new(0.0, 0.0, 0.0)),
+        vector_up: Vector3D::new(0.0, 1.0, 0.0),
+        look_from: Vector3D::new(0.0, 0.0, 0.0),
+        look_at: Vector3D::new(0.0, 0.0, -1.0),
+        settings: CameraSettings::default(),
+    };
+
+    let camera = Camera::from(camera_config);
+    let world = World::new();
+    let ray = camera.get_ray(0.5, 0.5);
+
+    assert_eq!(
+        camera.ray_color(&ray, &world, 50),
+        LinSrgb::new(0.0, 0.0, 0.0)
+    );
+}
+
+#[test]
+fn test_camera_render_tiles() {
+    let camera_config = CameraConfig {
+        aspect: 10.0 / 7.0,
+        image_width: 10,
+        vertical_fov: 90.0,
+        horizontal_fov: None,
+        background: Background::default(),
+        vector_up: Vector3D::new(0.0, 1.0, 0.0),
+        look_from: Vector3D::new(0.0, 0.0, 0.0),
+        look_at: Vector3D::new(0.0, 0.0, -1.0),
+        settings: CameraSettings::default(),
+    };
+    let camera = Camera::from(camera_config);
+
+    // A smooth mirror and single center samples keep the render deterministic
+    let mut world = World::new();
+    world.add(Sphere::new(
+        Vector3D::new(0.0, 0.0, -1.0),
+        0.5,
+        Material::Metal(Metal::new(Srgb::new(0.8, 0.6, 0.2), 0.0)),
+    ));
+    let anti_aliasing = AntiAliasing::new(1, AntiAliasingTechnique::None);
+
+    let scanline = camera.render(&world, &anti_aliasing);
+
+    // 4 does not divide 10x7, so the last column and row of tiles are clipped
+    for tile_size in [1, 3, 4, 16] {
+        let tiled = camera.render_tiles(&world, &anti_aliasing, tile_size);
+        assert_eq!(
+            tiled, scanline,
+            "Tile size {} differs from scanline",
+            tile_size
+        );
+    }
+}
+
+#[test]
+fn test_camera_shade_mode() {
+    let camera_config = CameraConfig {
+        aspect: 1.0,
+        image_width: 4,
+        vertical_fov: 90.0,
+        horizontal_fov: None,
+        background: Background::default(),
+        vector_up: Vector3D::new(0.0, 1.0, 0.0),
+        look_from: Vector3D::new(0.0, 0.0, 0.0),
+        look_at: Vector3D::new(0.0, 0.0, -1.0),
+        settings: CameraSettings::default(),
+    };
+    let mut camera = Camera::from(camera_config);
+    assert_eq!(camera.shade_mode(), ShadeMode::Shaded);
+
+    let mut world = World::new();
+    world.add(Sphere::new(
+        Vector3D::new(0.0, 0.0, -1.0),
+        0.5,
+        Material::Metal(Metal::new(Srgb::new(0.8, 0.6, 0.2), 0.0)),
+    ));
+
+    // The center ray hits the front of the sphere at z = -0.5 with normal (0, 0, 1)
+    let ray = camera.get_ray(0.5, 0.5);
+
+    camera.set_shade_mode(ShadeMode::Normals);
+    let color = camera.ray_color(&ray, &world, 50);
+    assert_approx_eq!(color.red, 0.5);
+    assert_approx_eq!(color.green, 0.5);
+    assert_approx_eq!(color.blue, 1.0);
+
+    camera.set_shade_mode(ShadeMode::Depth);
+    let color = camera.ray_color(&ray, &world, 50);
+    assert_approx_eq!(color.red, 1.0 / 1.5);
+    assert_eq!(color.red, color.green);
+    assert_eq!(color.red, color.blue);
+
+    // Misses are black in the debug modes regardless of the background
+    let ray = camera.get_ray(0.0, 0.0);
+    assert_eq!(
+        camera.ray_color(&ray, &world, 50),
+        LinSrgb::new(0.0, 0.0, 0.0)
+    );
+}
+
+#[test]
+fn test_camera_render_depth() {
+    let camera_config = CameraConfig {
+        aspect: 1.0,
+        image_width: 9,
+        vertical_fov: 90.0,
+        horizontal_fov: None,
+        background: Background::default(),
+        vector_up: Vector3D::new(0.0, 1.0, 0.0),
+        look_from: Vector3D::new(0.0, 0.0, 0.0),
+        look_at: Vector3D::new(0.0, 0.0, -1.0),
+        settings: CameraSettings::default(),
+    };
+    let camera = Camera::from(camera_config);
+
+    let mut world = World::new();
+    world.add(Sphere::new(
+        Vector3D::new(0.0, 0.0, -2.0),
+        0.5,
+        Material::Metal(Metal::new(Srgb::new(0.8, 0.6, 0.2), 0.0)),
+    ));
+
+    let depths = camera.render_depth(&world);
+    assert_eq!(depths.len(), camera.image_width * camera.image_height);
+
+    // The center pixel looks straight at the front of the sphere, the corners miss it
+    let center = depths[4 * camera.image_width + 4];
+    assert!(center.is_finite());
+    assert!(center > 1.5 && center < 2.0, "Unexpected depth {}", center);
+    assert!(center < depths[0]);
+    assert_eq!(depths[0], f64::INFINITY);
+    assert_eq!(depths[depths.len() - 1], f64::INFINITY);
+
+    // Moving the focus without an aperture changes nothing about the distances
+    let mut focused = camera.clone();
+    focused.set_depth_of_field(0.0, 5.0);
+    for (depth, focused_depth) in depths.iter().zip(focused.render_depth(&world)) {
+        if depth.is_finite() {
+            assert_approx_eq!(*depth, focused_depth);
+        } else {
+            assert_eq!(focused_depth, f64::INFINITY);
+        }
+    }
+
+    let mut depth_mode = camera.clone();
+    depth_mode.set_shade_mode(ShadeMode::Depth);
+    focused.set_shade_mode(ShadeMode::Depth);
+    let (u, v) = camera.pixel_uv(4.5, 4.5);
+    let gray = depth_mode.ray_color(&depth_mode.get_ray(u, v), &world, 1);
+    let focused_gray = focused.ray_color(&focused.get_ray(u, v), &world, 1);
+    assert_approx_eq!(gray.red, 1.0 / (1.0 + center as f32));
+    assert_approx_eq!(focused_gray.red, gray.red);
+}
+
+#[test]
+fn test_camera_encode_png_16bit() {
+    let camera_config = CameraConfig {
+        aspect: 4.0,
+        image_width: 4,
+        vertical_fov: 90.0,
+        horizontal_fov: None,
+        background: Background::default(),
+        vector_up: Vector3D::new(0.0, 1.0, 0.0),
+        look_from: Vector3D::new(0.0, 0.0, 0.0),
+        look_at: Vector3D::new(0.0, 0.0, -1.0),
+        settings: CameraSettings::default(),
+    };
+    let camera = Camera::from(camera_config);
+
+    // A ramp much finer than one 8 bit step
+    let mut pixels_8bit = vec![0u8; 4 * 3];
+    let mut pixels_16bit = vec![0u16; 4 * 3];
+    for i in 0..4 {
+        let value = 0.5 + i as f32 / 2000.0;
+        let color = Srgb::new(value, value, value);
+        write_pixel(&mut pixels_8bit, i, color);
+        write_pixel_16bit(&mut pixels_16bit, i, color);
+    }
+
+    for i in 0..3 {
+        assert_eq!(pixels_8bit[i * 3], pixels_8bit[(i + 1) * 3]);
+        assert!(pixels_16bit[i * 3] < pixels_16bit[(i + 1) * 3]);
+    }
+
+    let encoded = camera
+        .encode_png_16bit(&pixels_16bit, 4, 1)
+        .expect("Failed to encode image");
+    assert!(encoded.starts_with(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]));
+
+    // IHDR holds the width, height and then the bit depth
+    assert_eq!(encoded[16..24], [0, 0, 0, 4, 0, 0, 0, 1]);
+    assert_eq!(encoded[24], 16);
+}
+
+#[test]
+fn test_camera_set_look() {
+    let camera_config = CameraConfig {
+        aspect: 800.0 / 600.0,
+        image_width: 800,
+        vertical_fov: 60.0,
+        horizontal_fov: None,
+        background: Background::default(),
+        vector_up: Vector3D::new(0.0, 1.0, 0.0),
+        look_from: Vector3D::new(0.0, 0.0, 0.0),
+        look_at: Vector3D::new(0.0, 0.0, -1.0),
+        settings: CameraSettings::default(),
+    };
+    let moved_config = CameraConfig {
+        vector_up: Vector3D::new(0.0, 0.0, 1.0),
+        look_from: Vector3D::new(3.0, 2.0, 1.0),
+        look_at: Vector3D::new(-1.0, 0.5, 2.0),
+        settings: CameraSettings::default(),
+        ..camera_config.clone()
+    };
+
+    let mut camera = Camera::from(camera_config);
+    camera.set_look(
+        moved_config.look_from,
+        moved_config.look_at,
+        moved_config.vector_up,
+    );
+    let expected = Camera::from(moved_config);
+
+    assert_eq!(camera.origin, expected.origin);
+    assert_eq!(camera.lower_left_corner, expected.lower_left_corner);
+    assert_eq!(camera.horizontal, expected.horizontal);
+    assert_eq!(camera.vertical, expected.vertical);
+    assert_approx_eq!(camera.focal_length, expected.focal_length);
+}
+
+#[test]
+fn test_camera_sky_gradient() {
+    let camera_config = CameraConfig {
+        aspect: 1.0,
+        image_width: 4,
+        vertical_fov: 90.0,
+        horizontal_fov: None,
+        background: Background::default(),
+        vector_up: Vector3D::new(0.0, 1.0, 0.0),
+        look_from: Vector3D::new(0.0, 0.0, 0.0),
+        look_at: Vector3D::new(0.0, 0.0, -1.0),
+        settings: CameraSettings::default(),
+    };
+    let mut camera = Camera::from(camera_config);
+    let world = World::new();
+
+    let red = Srgb::new(1.0, 0.0, 0.0);
+    camera.set_sky_gradient(red, red);
+
+    for (u, v) in [(0.0, 0.0), (0.5, 0.5), (1.0, 1.0)] {
+        let ray = camera.get_ray(u, v);
+        assert_eq!(
+            camera.ray_color(&ray, &world, 50),
+            LinSrgb::new(1.0, 0.0, 0.0)
+        );
+    }
+}
+
+#[test]
+fn test_camera_render_into() {
+    let camera_config = CameraConfig {
+        aspect: 2.0,
+        image_width: 8,
+        vertical_fov: 90.0,
+        horizontal_fov: None,
+        background: Background::default(),
+        vector_up: Vector3D::new(0.0, 1.0, 0.0),
+        look_from: Vector3D::new(0.0, 0.0, 0.0),
+        look_at: Vector3D::new(0.0, 0.0, -1.0),
+        settings: CameraSettings::default(),
+    };
+    let camera = Camera::from(camera_config);
+
+    let mut world = World::new();
+    world.add(Sphere::new(
+        Vector3D::new(0.0, 0.0, -1.0),
+        0.5,
+        Material::Metal(Metal::new(Srgb::new(0.8, 0.6, 0.2), 0.0)),
+    ));
+    let anti_aliasing = AntiAliasing::new(1, AntiAliasingTechnique::None);
+
+    let mut pixels = vec![0; camera.image_width * camera.image_height * 3];
+    camera
+        .render_into(&world, &anti_aliasing, &mut pixels)
+        .expect("Buffer has the right size");
+    assert_eq!(pixels, camera.render(&world, &anti_aliasing));
+
+    let mut too_small = vec![0; pixels.len() - 1];
+    let error = camera
+        .render_into(&world, &anti_aliasing, &mut too_small)
+        .unwrap_err();
+    assert!(matches!(error, RayTracerError::InvalidInput(_)));
+}
+
+#[test]
+fn test_camera_render_parallel() {
+    let camera_config = CameraConfig {
+        aspect: 40.0 / 23.0,
+        image_width: 40,
+        vertical_fov: 90.0,
+        horizontal_fov: None,
+        background: Background::default(),
+        vector_up: Vector3D::new(0.0, 1.0, 0.0),
+        look_from: Vector3D::new(0.0, 0.0, 0.0),
+        look_at: Vector3D::new(0.0, 0.0, -1.0),
+        settings: CameraSettings::default(),
+    };
+    let camera = Camera::from(camera_config);
+
+    let count = Arc::new(AtomicUsize::new(0));
+    let mut world = World::new();
+    world.add(Sphere::new(
+        Vector3D::new(0.0, 0.0, -1.0),
+        0.5,
+        Material::Metal(Metal::new(Srgb::new(0.8, 0.6, 0.2), 0.0)),
+    ));
+    let anti_aliasing = AntiAliasing::new(1, AntiAliasingTechnique::None);
+
+    let serial = camera.render(&world, &anti_aliasing);
+    for num_threads in [1, 3, 8] {
+        let parallel = camera.render_parallel(&world, &anti_aliasing, num_threads);
+        assert_eq!(
+            parallel, serial,
+            "{} threads differ from serial",
+            num_threads
+        );
+    }
+
+    // Every pixel, and so every tile, is traced exactly once
+    let mut world = World::new();
+    world.add(CountingObject {
+        count: count.clone(),
+        object: None,
+    });
+    camera.render_parallel(&world, &anti_aliasing, 4);
+    assert_eq!(
+        count.load(Ordering::SeqCst),
+        camera.image_width * camera.image_height
+    );
+}
+
+#[test]
+fn test_camera_emission() {
+    let camera_config = CameraConfig {
+        aspect: 1.0,
+        image_width: 4,
+        vertical_fov: 90.0,
+        horizontal_fov: None,
+        background: Background::SolidColor(Srgb::new(0.0, 0.0, 0.0)),
+        vector_up: Vector3D::new(0.0, 1.0, 0.0),
+        look_from: Vector3D::new(0.0, 0.0, 0.0),
+        look_at: Vector3D::new(0.0, 0.0, -1.0),
+        settings: CameraSettings::default(),
+    };
+    let camera = Camera::from(camera_config);
+
+    let mut world = World::new();
+    world.add(Sphere::new(
+        Vector3D::new(0.0, 0.0, -1.0),
+        0.5,
+        Material::DiffuseLight(DiffuseLight::new(Srgb::new(4.0, 2.0, 1.0))),
+    ));
+
+    // Lights show their own radiance even against a black background
+    let ray = camera.get_ray(0.5, 0.5);
+    assert_eq!(
+        camera.ray_color(&ray, &world, 50),
+        LinSrgb::new(4.0, 2.0, 1.0)
+    );
+}
+
+#[test]
+fn test_camera_config_from_dimensions() {
+    let camera_config = CameraConfig::from_dimensions(
+        800,
+        600,
+        90.0,
+        Vector3D::new(0.0, 1.0, 0.0),
+        Vector3D::new(0.0, 0.0, 0.0),
+        Vector3D::new(0.0, 0.0, -1.0),
+    );
+    assert_eq!(camera_config.aspect, 800.0 / 600.0);
+
+    let camera = Camera::from(camera_config);
+    assert_eq!(camera.image_width, 800);
+    assert_eq!(camera.image_height, 600);
+
+    // Dimensions that do not reduce to a simple ratio still round trip
+    for (width, height) in [(1920, 1080), (333, 217), (7, 1000)] {
+        let camera = Camera::from(CameraConfig::from_dimensions(
+            width,
+            height,
+            90.0,
+            Vector3D::new(0.0, 1.0, 0.0),
+            Vector3D::new(0.0, 0.0, 0.0),
+            Vector3D::new(0.0, 0.0, -1.0),
+        ));
+        assert_eq!(camera.image_height, height);
+    }
+}
+
+#[test]
+fn test_camera_config_default() {
+    let camera = Camera::from(CameraConfig::default());
+    assert_eq!(camera.image_width, 400);
+    assert_eq!(camera.image_height, 225);
+
+    let ray = camera.get_ray(0.5, 0.5);
+    assert_approx_eq!(ray.direction.get_z(), -1.0);
+
+    let camera = Camera::from(CameraConfig {
+        image_width: 1920,
+        ..Default::default()
+    });
+    assert_eq!(camera.image_height, 1080);
+}
+
+#[test]
+fn test_camera_render_region() {
+    let camera_config = CameraConfig {
+        aspect: 10.0 / 7.0,
+        image_width: 10,
+        ..Default::default()
+    };
+    let camera = Camera::from(camera_config);
+
+    // A smooth mirror and single center samples make both renders trace identical rays
+    let mut world = World::new();
+    world.add(Sphere::new(
+        Vector3D::new(0.0, 0.0, -1.0),
+        0.5,
+        Material::Metal(Metal::new(Srgb::new(0.8, 0.6, 0.2), 0.0)),
+    ));
+    let anti_aliasing = AntiAliasing::new(1, AntiAliasingTechnique::None);
+
+    let full = camera.render(&world, &anti_aliasing);
+    let (x0, y0, x1, y1) = (2, 1, 7, 5);
+    let region = camera
+        .render_region(&world, &anti_aliasing, x0, y0, x1, y1)
+        .expect("Region lies inside the image");
+    assert_eq!(region.len(), (x1 - x0) * (y1 - y0) * 3);
+
+    let mut crop = Vec::new();
+    for y in y0..y1 {
+        crop.extend_from_slice(&full[(y * 10 + x0) * 3..(y * 10 + x1) * 3]);
+    }
+    assert_eq!(region, crop);
+
+    // The whole image is a valid region too
+    let whole = camera.render_region(&world, &anti_aliasing, 0, 0, 10, 7);
+    assert_eq!(whole.unwrap(), full);
+
+    for (x0, y0, x1, y1) in [(0, 0, 11, 7), (0, 0, 10, 8), (3, 2, 3, 4), (5, 4, 2, 6)] {
+        let error = camera
+            .render_region(&world, &anti_aliasing, x0, y0, x1, y1)
+            .unwrap_err();
+        assert!(matches!(error, RayTracerError::InvalidInput(_)));
+    }
+}
+
+#[test]
+fn test_camera_fog() {
+    let mut camera = Camera::from(CameraConfig::default());
+    assert!(camera.fog().is_none());
+
+    // Emissive spheres do not scatter, so each ray's color is exact
+    let mut world = World::new();
+    world.add(Sphere::new(
+        Vector3D::new(0.0, 0.0, -1000.0),
+        1.0,
+        Material::DiffuseLight(DiffuseLight::new(Srgb::new(1.0, 0.0, 0.0))),
+    ));
+    world.add(Sphere::new(
+        Vector3D::new(0.0, 10.0, -2.0),
+        1.0,
+        Material::DiffuseLight(DiffuseLight::new(Srgb::new(1.0, 0.0, 0.0))),
+    ));
+    let far_ray = Ray::new(Vector3D::new(0.0, 0.0, 0.0), Vector3D::new(0.0, 0.0, -1.0));
+    let near_ray = Ray::new(Vector3D::new(0.0, 10.0, 0.0), Vector3D::new(0.0, 0.0, -1.0));
+
+    let unfogged_far = camera.ray_color(&far_ray, &world, 10);
+    let unfogged_near = camera.ray_color(&near_ray, &world, 10);
+
+    // Zero density leaves every hit untouched
+    camera.set_fog(Some(Fog::new(Srgb::new(0.5, 0.5, 0.5), 0.0)));
+    assert_eq!(camera.ray_color(&far_ray, &world, 10), unfogged_far);
+    assert_eq!(camera.ray_color(&near_ray, &world, 10), unfogged_near);
+
+    // With fog the distant hit is almost pure fog, while the near one keeps most of its color
+    camera.set_fog(Some(Fog::new(Srgb::new(0.5, 0.5, 0.5), 0.01)));
+    let fogged_far = camera.ray_color(&far_ray, &world, 10);
+    assert_approx_eq!(fogged_far.red, 0.5, 1e-3);
+    assert_approx_eq!(fogged_far.green, 0.5, 1e-3);
+
+    let fogged_near = camera.ray_color(&near_ray, &world, 10);
+    assert!(fogged_near.red > 0.99);
+    assert!(fogged_near.green < 0.01);
+}
+
+#[test]
+fn test_camera_phong_highlight() {
+    let camera = Camera::from(CameraConfig::default());
+
+    // A floor lit from the upper right. Seen from the upper left, the half-vector between
+    // the viewer and the light lines up with the floor normal right below the origin
+    let mut world = World::new();
+    world.add(Plane::new(
+        Vector3D::new(0.0, 0.0, 0.0),
+        Vector3D::new(0.0, 1.0, 0.0),
+        Material::Phong(Phong::new(
+            Srgb::new(0.05, 0.05, 0.05),
+            Srgb::new(1.0, 1.0, 1.0),
+            64.0,
+        )),
+    ));
+    world.add_light(PointLight::new(
+        Vector3D::new(1.0, 1.0, 0.0),
+        Srgb::new(2.0, 2.0, 2.0),
+    ));
+    let eye = Vector3D::new(-1.0, 1.0, 0.0);
+
+    // A depth of one traces no bounces, so only the direct light is left
+    let color_at = |world: &World, x: f64| {
+        let ray = Ray::new(eye, Vector3D::new(x, 0.0, 0.0) - eye);
+        camera.ray_color(&ray, world, 1).red
+    };
+
+    // Diffuse cos(45) plus the full highlight, over the squared distance of 2
+    let highlight = color_at(&world, 0.0);
+    assert_approx_eq!(highlight, (0.05 * 0.5f32.sqrt() + 1.0) * 2.0 / 2.0, 1e-5);
+
+    // Away from the mirror point only the dim diffuse term remains
+    assert!(highlight > 5.0 * color_at(&world, 0.6));
+    assert!(highlight > 5.0 * color_at(&world, -0.6));
+
+    // A blocker between the floor and the light leaves the spot in shadow
+    world.add(Sphere::new(
+        Vector3D::new(0.5, 0.5, 0.0),
+        0.1,
+        Material::Lambertian(Lambertian::new(Srgb::new(0.5, 0.5, 0.5))),
+    ));
+    assert_approx_eq!(color_at(&world, 0.0), 0.0);
+}
+
+#[test]
+fn test_render_animation() {
+    let out_dir = std::env::temp_dir().join("ray_tracer_test_render_animation");
+    std::fs::create_dir_all(&out_dir).unwrap();
+
+    // Two frames of a camera stepping back from the origin
+    let frames = (0..2).map(|frame| {
+        Camera::from(CameraConfig {
+            image_width: 8,
+            look_from: Vector3D::new(0.0, 0.0, frame as f64),
+            look_at: Vector3D::new(0.0, 0.0, frame as f64 - 1.0),
+            settings: CameraSettings::default(),
+            ..Default::default()
+        })
+    });
+    let anti_aliasing = AntiAliasing::new(1, AntiAliasingTechnique::None);
+
+    let paths = render_animation(frames, &World::new(), &anti_aliasing, &out_dir, "orbit")
+        .expect("Failed to render animation");
+
+    let names: Vec<_> = paths
+        .iter()
+        .map(|path| path.file_name().unwrap().to_str().unwrap().to_string())
+        .collect();
+    assert_eq!(names, ["orbit_0000.png", "orbit_0001.png"]);
+    for path in paths.iter() {
+        assert!(path.starts_with(&out_dir));
+        assert!(std::fs::read(path).unwrap().starts_with(b"\x89PNG"));
+    }
+
+    std::fs::remove_dir_all(&out_dir).unwrap();
+
+    // Failing to create a frame's file is reported as an I/O error
+    let camera = Camera::from(CameraConfig {
+        image_width: 8,
+        ..Default::default()
+    });
+    let error = render_animation(
+        std::iter::once(camera),
+        &World::new(),
+        &anti_aliasing,
+        &out_dir,
+        "orbit",
+    )
+    .unwrap_err();
+    assert!(matches!(error, RayTracerError::Io(_)));
+}
+
+#[test]
+fn test_camera_write_jpeg_and_bmp() {
+    let camera = Camera::from(CameraConfig {
+        image_width: 3,
+        aspect: 3.0 / 2.0,
+        ..Default::default()
+    });
+    #[rustfmt::skip]
+    let pixels: [u8; 18] = [
+        255, 0, 0,   0, 255, 0,   0, 0, 255,
+        255, 255, 255,   0, 0, 0,   128, 128, 128,
+    ];
+
+    let jpeg_path = std::env::temp_dir().join("ray_tracer_test_camera_write.jpg");
+    camera
+        .write_jpeg(jpeg_path.to_str().unwrap(), &pixels, 3, 2, 80)
+        .expect("Failed to write JPEG");
+    let jpeg = std::fs::read(&jpeg_path).unwrap();
+    std::fs::remove_file(&jpeg_path).unwrap();
+    assert!(jpeg.starts_with(&[0xFF, 0xD8, 0xFF]));
+
+    for quality in [0, 101] {
+        let error = camera.encode_jpeg(&pixels, 3, 2, quality).unwrap_err();
+        assert!(matches!(error, RayTracerError::InvalidInput(_)));
+    }
+
+    // Buffers that do not fit the dimensions are rejected instead of encoding garbage
+    // or reading out of bounds
+    for (length, width, height) in [(10, 3, 2), (17, 3, 2), (18, 0, 2), (0, 0, 0)] {
+        let error = camera
+            .encode_bmp(&pixels[..length], width, height)
+            .unwrap_err();
+        assert!(matches!(error, RayTracerError::InvalidInput(_)));
+        let error = camera
+            .encode_jpeg(&pixels[..length], width, height, 80)
+            .unwrap_err();
+        assert!(matches!(error, RayTracerError::InvalidInput(_)));
+    }
+
+    // Both formats are also picked by extension
+    let bmp_path = std::env::temp_dir().join("ray_tracer_test_camera_write.bmp");
+    camera
+        .write_image_auto(bmp_path.to_str().unwrap(), &pixels, 3, 2)
+        .expect("Failed to write BMP");
+    let bmp = std::fs::read(&bmp_path).unwrap();
+    std::fs::remove_file(&bmp_path).unwrap();
+    assert!(bmp.starts_with(b"BM"));
+
+    // Two rows of 9 bytes padded to 12, bottom row first as BGR
+    assert_eq!(bmp.len(), 54 + 2 * 12);
+    assert_eq!(&bmp[54..57], &[255, 255, 255]);
+    assert_eq!(&bmp[66..69], &[0, 0, 255]);
+
+    let jpeg_path = std::env::temp_dir().join("ray_tracer_test_camera_write_auto.JPEG");
+    camera
+        .write_image_auto(jpeg_path.to_str().unwrap(), &pixels, 3, 2)
+        .expect("Failed to write JPEG");
+    let jpeg = std::fs::read(&jpeg_path).unwrap();
+    std::fs::remove_file(&jpeg_path).unwrap();
+    assert!(jpeg.starts_with(&[0xFF, 0xD8, 0xFF]));
+
+    // The BMP decodes back to the same pixels
+    let decoded = image::load_from_memory(&bmp).unwrap().to_rgb();
+    assert_eq!(decoded.into_raw(), pixels.to_vec());
+}
+
+#[test]
+fn test_bokeh_shape() {
+    assert_eq!(BokehShape::default(), BokehShape::Circle);
+
+    let square = BokehShape::Polygon { sides: 4 };
+    let mut near_corner = false;
+    for _ in 0..10000 {
+        let (x, y) = BokehShape::Circle.sample_lens();
+        assert!(x * x + y * y < 1.0);
+
+        // Four sides with a corner at the top is the square with corners on the axes
+        let (x, y) = square.sample_lens();
+        assert!(
+            x.abs() + y.abs() <= 1.0 + 1e-12,
+            "({}, {}) is outside",
+            x,
+            y
+        );
+        near_corner |= y > 0.9;
+    }
+    assert!(near_corner);
+
+    // Corners stick out further than the middle of the edges
+    let hexagon = BokehShape::Polygon { sides: 6 };
+    assert!(hexagon.contains(0.0, 0.99));
+    assert!(!hexagon.contains(0.99, 0.0));
+
+    // Mapped samples land inside the shape, with the corners of the square at the edge
+    for shape in [BokehShape::Circle, square, hexagon] {
+        for i in 0..32 {
+            for j in 0..32 {
+                let (x, y) = shape.map_sample((i as f64 + 0.5) / 32.0, (j as f64 + 0.5) / 32.0);
+                assert!(shape.contains(x, y), "{:?} maps to ({}, {})", shape, x, y);
+            }
+        }
+    }
+    assert_eq!(BokehShape::Circle.map_sample(0.5, 0.5), (0.0, 0.0));
+    let (x, y) = BokehShape::Circle.map_sample(1.0, 0.5);
+    assert_approx_eq!(x, 1.0);
+    assert_approx_eq!(y, 0.0);
+}
+
+#[test]
+fn test_camera_depth_of_field() {
+    let mut camera = Camera::from(CameraConfig::default());
+    let pinhole = camera.get_ray(0.3, 0.6);
+    assert_eq!(camera.aperture(), 0.0);
+
+    camera.set_depth_of_field(0.5, 4.0);
+    camera.set_bokeh_shape(BokehShape::Polygon { sides: 5 });
+    assert_eq!(camera.bokeh_shape(), BokehShape::Polygon { sides: 5 });
+
+    // Rays leave from different points on the lens, but all meet the pinhole ray on the
+    // focus plane four units in front of the camera
+    let focus_point = pinhole.at(4.0);
+    let mut origins = Vec::new();
+    for _ in 0..100 {
+        let ray = camera.get_ray(0.3, 0.6);
+        assert!((ray.origin - camera.origin).length() <= 0.25 + 1e-12);
+        assert!(ray.at(1.0).approx_eq(&focus_point, 1e-9));
+        origins.push(ray.origin);
+    }
+    assert!(origins.iter().any(|origin| *origin != camera.origin));
+}
+
+#[test]
+fn test_camera_russian_roulette_unbiased() {
+    let mut camera = Camera::from(CameraConfig::default());
+    assert!(camera.russian_roulette());
+
+    // A light inside a closed, bright diffuse room, so paths bounce many times before
+    // reaching the light, and never escape to the background
+    let mut world = World::new();
+    world.add(Sphere::new(
+        Vector3D::new(0.0, 0.0, 0.0),
+        10.0,
+        Material::Lambertian(Lambertian::new(Srgb::new(0.8, 0.8, 0.8))),
+    ));
+    world.add(Sphere::new(
+        Vector3D::new(0.0, 0.0, 0.0),
+        2.0,
+        Material::DiffuseLight(DiffuseLight::new(Srgb::new(1.0, 1.0, 1.0))),
+    ));
+
+    let samples = 40000;
+    let mean_brightness = |camera: &Camera| {
+        let mut total = 0.0;
+        for _ in 0..samples {
+            let ray = Ray::new(
+                Vector3D::new(0.0, 0.0, -6.0),
+                Vector3D::random_unit_vector(),
+            );
+            total += camera.ray_color(&ray, &world, 50).red as f64;
+        }
+        total / samples as f64
+    };
+
+    let with_roulette = mean_brightness(&camera);
+    camera.set_russian_roulette(false);
+    let fixed_depth = mean_brightness(&camera);
+
+    assert_approx_eq!(with_roulette, fixed_depth, 0.05 * fixed_depth);
+}
+
+#[test]
+fn test_camera_light_sampling_reduces_noise() {
+    let mut camera = Camera::from(CameraConfig::default());
+    camera.set_background(Background::SolidColor(Srgb::new(0.0, 0.0, 0.0)));
+    camera.set_russian_roulette(false);
+    assert!(camera.light_sampling());
+
+    // A diffuse floor lit only by a small rectangle hanging above it
+    let mut world = World::new();
+    world.add(AxisAlignedRect::new(
+        Axis::Y,
+        -50.0,
+        50.0,
+        -50.0,
+        50.0,
+        0.0,
+        Material::Lambertian(Lambertian::new(Srgb::new(0.5, 0.5, 0.5))),
+    ));
+    world.add(AxisAlignedRect::new(
+        Axis::Y,
+        -0.25,
+        0.25,
+        -0.25,
+        0.25,
+        1.0,
+        Material::DiffuseLight(DiffuseLight::new(Srgb::new(4.0, 4.0, 4.0))),
+    ));
+
+    // Mean and variance of single sample estimates of a floor point next to the light
+    let samples = 40000;
+    let ray = Ray::new(Vector3D::new(0.0, 0.5, 2.0), Vector3D::new(0.0, -0.5, -1.5));
+    let statistics = |camera: &Camera| {
+        let estimates: Vec<f64> = (0..samples)
+            .map(|_| camera.ray_color(&ray, &world, 10).red as f64)
+            .collect();
+        let mean = estimates.iter().sum::<f64>() / samples as f64;
+        let variance = estimates
+            .iter()
+            .map(|estimate| (estimate - mean) * (estimate - mean))
+            .sum::<f64>()
+            / samples as f64;
+        (mean, variance)
+    };
+
+    let (mean_sampled, variance_sampled) = statistics(&camera);
+    camera.set_light_sampling(false);
+    let (mean_naive, variance_naive) = statistics(&camera);
+
+    assert!(mean_naive > 0.0);
+    assert_approx_eq!(mean_sampled, mean_naive, 0.1 * mean_naive);
+    assert!(
+        variance_sampled * 4.0 < variance_naive,
+        "Light sampling variance {} vs naive {}",
+        variance_sampled,
+        variance_naive
+    );
+}
+
+#[test]
+fn test_camera_write_hdr_round_trip() {
+    let camera = Camera::from(CameraConfig::default());
+
+    // Wider than 8 pixels so the run length encoded scanlines are used
+    let (width, height) = (10, 2);
+    let radiance: Vec<f32> = (0..width * height * 3)
+        .map(|i| 0.01 + (i as f32) * (i as f32) * 0.37)
+        .collect();
+
+    let hdr_path = std::env::temp_dir().join("ray_tracer_test_camera_write.hdr");
+    camera
+        .write_hdr(hdr_path.to_str().unwrap(), &radiance, width, height)
+        .expect("Failed to write HDR");
+    let file = std::io::BufReader::new(File::open(&hdr_path).unwrap());
+    let decoded = image::hdr::HDRDecoder::new(file)
+        .and_then(|decoder| decoder.read_image_hdr())
+        .expect("Failed to decode HDR");
+    std::fs::remove_file(&hdr_path).unwrap();
+
+    // The brightest channel's mantissa is at least 128 and gets truncated, so the shared
+    // exponent leaves every channel within 1 / 128 of the pixel's brightest channel
+    assert_eq!(decoded.len(), width * height);
+    for (pixel, expected) in decoded.iter().zip(radiance.chunks(3)) {
+        let brightest = expected.iter().cloned().fold(0.0, f32::max);
+        for (channel, expected) in pixel.data.iter().zip(expected) {
+            assert_approx_eq!(channel, expected, brightest / 128.0);
+        }
+    }
+
+    let error = camera.encode_hdr(&radiance, width, height + 1).unwrap_err();
+    assert!(matches!(error, RayTracerError::InvalidInput(_)));
+}
+
+#[test]
+fn test_camera_ambient_on_miss() {
+    let mut camera = Camera::from(CameraConfig::default());
+    assert!(camera.ambient_on_miss());
+
+    // Nothing lights the floor except the sky
+    let mut world = World::new();
+    world.add(Plane::new(
+        Vector3D::new(0.0, 0.0, 0.0),
+        Vector3D::new(0.0, 1.0, 0.0),
+        Material::Lambertian(Lambertian::new(Srgb::new(0.5, 0.5, 0.5))),
+    ));
+    let to_floor = Ray::new(Vector3D::new(0.0, 1.0, 0.0), Vector3D::new(0.0, -1.0, -1.0));
+    let to_sky = Ray::new(Vector3D::new(0.0, 1.0, 0.0), Vector3D::new(0.0, 1.0, 0.0));
+
+    let floor = camera.ray_color(&to_floor, &world, 10);
+    assert!(floor.red > 0.0);
+
+    camera.set_ambient_on_miss(false);
+    for _ in 0..100 {
+        let floor = camera.ray_color(&to_floor, &world, 10);
+        assert_eq!((floor.red, floor.green, floor.blue), (0.0, 0.0, 0.0));
+    }
+
+    // The sky itself is still seen directly
+    let sky = camera.ray_color(&to_sky, &world, 10);
+    assert_approx_eq!(sky.blue, 1.0);
+}
+
+#[test]
+fn test_camera_chromatic_aberration() {
+    let mut camera = Camera::from(CameraConfig::default());
+    camera.set_background(Background::SolidColor(Srgb::new(0.0, 0.0, 0.0)));
+    camera.set_depth_of_field(2.0, 2.0);
+
+    // A white wall on the focus plane covering everything left of the center
+    let mut world = World::new();
+    world.add(AxisAlignedRect::new(
+        Axis::Z,
+        -10.0,
+        0.0,
+        -10.0,
+        10.0,
+        -2.0,
+        Material::DiffuseLight(DiffuseLight::new(Srgb::new(1.0, 1.0, 1.0))),
+    ));
+
+    // Look just right of the edge, where the in focus wall never reaches
+    let u = 0.5 + 0.05 / camera.horizontal.length();
+    let mean_color = |camera: &Camera| {
+        let mut total = LinSrgb::new(0.0, 0.0, 0.0);
+        for _ in 0..2000 {
+            total += camera.sample_color(u, 0.5, None, &world, 5);
+        }
+        total / 2000.0
+    };
+
+    let sharp = mean_color(&camera);
+    assert_eq!((sharp.red, sharp.green, sharp.blue), (0.0, 0.0, 0.0));
+
+    // Red and blue are out of focus and bleed past the edge, green stays sharp
+    camera.set_chromatic_aberration(0.2);
+    let fringed = mean_color(&camera);
+    assert_eq!(fringed.green, 0.0);
+    assert!(fringed.red > 0.01, "Red {}", fringed.red);
+    assert!(fringed.blue > 0.01, "Blue {}", fringed.blue);
+}
+
+#[test]
+fn test_camera_render_stats() {
+    let camera = Camera::from(CameraConfig::from_dimensions(
+        8,
+        6,
+        70.0,
+        Vector3D::new(0.0, 1.0, 0.0),
+        Vector3D::new(0.0, 0.0, 0.0),
+        Vector3D::new(0.0, 0.0, -1.0),
+    ));
+    let anti_aliasing = AntiAliasing::new(4, AntiAliasingTechnique::SuperSampling);
+    let world = World::demo_scene();
+
+    let (pixels, stats) = camera.with_stats(|camera| camera.render(&world, &anti_aliasing));
+    assert_eq!(pixels.len(), 8 * 6 * 3);
+
+    // One camera ray per sample, and the floor and spheres send most of them on
+    assert_eq!(stats.camera_rays, 8 * 6 * 4);
+    assert!(stats.rays > stats.camera_rays);
+    assert!(stats.bounces > 0);
+    assert_eq!(stats.paths, stats.camera_rays);
+    assert_eq!(stats.rays, stats.paths + stats.bounces);
+    assert_eq!(stats.shadow_rays, 0);
+    let path_length = stats.average_path_length();
+    assert!(
+        path_length > 1.0 && path_length <= anti_aliasing.max_depth() as f64,
+        "Average path length {}",
+        path_length
+    );
+
+    // Nothing is counted outside with_stats
+    assert!(camera.stats.is_none());
+
+    // A depth of one traces no bounces, and chromatic aberration traces three rays for
+    // every sample while still counting one camera ray
+    let mut anti_aliasing = AntiAliasing::builder()
+        .samples_per_pixel(4)
+        .technique(AntiAliasingTechnique::SuperSampling)
+        .max_depth(1)
+        .build();
+    let (_, stats) = camera.with_stats(|camera| camera.render(&world, &anti_aliasing));
+    assert_eq!(stats.camera_rays, 8 * 6 * 4);
+    assert_eq!(stats.bounces, 0);
+    assert_eq!(stats.rays, stats.camera_rays);
+
+    let mut camera = camera;
+    camera.set_depth_of_field(0.1, 1.0);
+    camera.set_chromatic_aberration(0.2);
+    let (_, stats) = camera.with_stats(|camera| camera.render(&world, &anti_aliasing));
+    assert_eq!(stats.camera_rays, 8 * 6 * 4);
+    assert_eq!(stats.bounces, 0);
+    assert_eq!(stats.paths, 3 * stats.camera_rays);
+    assert_eq!(stats.rays, stats.paths);
+    assert_eq!(stats.average_path_length(), 1.0);
+
+    anti_aliasing.set_max_depth(2);
+    let (_, stats) = camera.with_stats(|camera| camera.render(&world, &anti_aliasing));
+    assert_eq!(stats.camera_rays, 8 * 6 * 4);
+    assert!(stats.bounces > 0);
+    assert_eq!(stats.paths, 3 * stats.camera_rays);
+    assert_eq!(stats.rays, stats.paths + stats.bounces);
+    assert!(stats.average_path_length() > 1.0 && stats.average_path_length() <= 2.0);
+}
+
+#[test]
+fn test_camera_serde_settings() {
+    let mut camera = Camera::from(CameraConfig::default());
+    camera.set_exposure(2.0);
+    camera.set_fog(Some(Fog::new(Srgb::new(0.5, 0.6, 0.7), 0.1)));
+    camera.set_depth_of_field(0.2, 3.0);
+    camera.set_bokeh_shape(BokehShape::Polygon { sides: 6 });
+    camera.set_chromatic_aberration(0.1);
+    camera.set_russian_roulette(false);
+    camera.set_light_sampling(false);
+    camera.set_ambient_on_miss(false);
+    camera.set_min_roughness(0.2);
+
+    // The lens and rendering settings survive a round trip, not just the view
+    let json = serde_json::to_string(&camera).unwrap();
+    let loaded: Camera = serde_json::from_str(&json).unwrap();
+    assert_eq!(serde_json::to_string(&loaded).unwrap(), json);
+    assert_eq!(loaded.exposure(), 2.0);
+    assert_eq!(loaded.fog(), camera.fog());
+    assert_eq!((loaded.aperture(), loaded.focus_distance()), (0.2, 3.0));
+    assert_eq!(loaded.bokeh_shape(), BokehShape::Polygon { sides: 6 });
+    assert_eq!(loaded.chromatic_aberration(), 0.1);
+    assert!(!loaded.russian_roulette());
+    assert!(!loaded.light_sampling());
+    assert!(!loaded.ambient_on_miss());
+    assert_eq!(loaded.min_roughness(), 0.2);
+    assert_eq!(
+        loaded.lower_left_corner.get_x(),
+        camera.lower_left_corner.get_x()
+    );
+
+    // Settings missing from a file keep their defaults
+    let mut value = serde_json::to_value(&camera).unwrap();
+    let fields = value.as_object_mut().unwrap();
+    fields.remove("exposure");
+    fields.remove("aperture");
+    fields.remove("focus_distance");
+    let loaded: Camera = serde_json::from_value(value).unwrap();
+    assert_eq!(loaded.exposure(), 1.0);
+    assert_eq!((loaded.aperture(), loaded.focus_distance()), (0.0, 1.0));
+    assert_eq!(loaded.min_roughness(), 0.2);
+}
+
+#[test]
+fn test_camera_project() {
+    let camera = Camera::from(CameraConfig {
+        aspect: 800.0 / 600.0,
+        image_width: 800,
+        vertical_fov: 60.0,
+        horizontal_fov: None,
+        background: Background::default(),
+        vector_up: Vector3D::new(0.0, 1.0, 0.0),
+        look_from: Vector3D::new(-4.0, 4.0, 1.0),
+        look_at: Vector3D::new(0.0, 0.0, -1.0),
+        settings: CameraSettings::default(),
+    });
+
+    // The point looked at is in the middle of the image
+    let (x, y) = camera.project(&Vector3D::new(0.0, 0.0, -1.0)).unwrap();
+    assert!((x - 400.0).abs() <= 1.0, "x = {}", x);
+    assert!((y - 300.0).abs() <= 1.0, "y = {}", y);
+
+    // Projecting and casting a ray back passes through the original point
+    let point = Vector3D::new(1.0, 0.5, -2.0);
+    let (x, y) = camera.project(&point).unwrap();
+    assert!((0.0..800.0).contains(&x) && (0.0..600.0).contains(&y));
+    let ray = camera.pixel_to_ray(x, y);
+    let to_point = point - ray.origin;
+    assert_approx_eq!(
+        ray.direction.unit_vector().dot(&to_point.unit_vector()),
+        1.0
+    );
+
+    // And the other way around
+    let ray = camera.pixel_to_ray(123.5, 456.5);
+    let (x, y) = camera.project(&ray.at(3.0)).unwrap();
+    assert_approx_eq!(x, 123.5, 1e-6);
+    assert_approx_eq!(y, 456.5, 1e-6);
+
+    // Behind the camera
+    assert!(camera.project(&Vector3D::new(-8.0, 8.0, 3.0)).is_none());
+    assert!(camera.project(&camera.origin).is_none());
+}
+
+#[test]
+fn test_camera_render_image() {
+    let camera = Camera::from(CameraConfig::from_dimensions(
+        12,
+        8,
+        70.0,
+        Vector3D::new(0.0, 1.0, 0.0),
+        Vector3D::new(0.0, 0.0, 0.0),
+        Vector3D::new(0.0, 0.0, -1.0),
+    ));
+    let anti_aliasing = AntiAliasing::new(1, AntiAliasingTechnique::None);
+    let world = World::demo_scene();
+
+    let image = camera.render_image(&world, &anti_aliasing);
+    assert_eq!(image.width(), 12);
+    assert_eq!(image.height(), 8);
+    assert!(image.pixels().any(|pixel| pixel.data != [0, 0, 0]));
+
+    // Same layout as render, row by row from the top left
+    let pixels = camera.render(&world, &anti_aliasing);
+    assert_eq!(image.into_raw().len(), pixels.len());
+}
+
+#[test]
+fn test_camera_render_sample_heatmap() {
+    let camera = Camera::from(CameraConfig {
+        aspect: 1.0,
+        image_width: 16,
+        vertical_fov: 90.0,
+        horizontal_fov: None,
+        background: Background::SolidColor(Srgb::new(1.0, 1.0, 1.0)),
+        vector_up: Vector3D::new(0.0, 1.0, 0.0),
+        look_from: Vector3D::new(0.0, 0.0, 0.0),
+        look_at: Vector3D::new(0.0, 0.0, -1.0),
+        settings: CameraSettings::default(),
+    });
+
+    // White sky over a black floor, with the horizon running through pixel row 8
+    let mut world = World::new();
+    world.add(Plane::new(
+        Vector3D::new(0.0, -0.5, 0.0),
+        Vector3D::new(0.0, 1.0, 0.0),
+        Material::Lambertian(Lambertian::new(Srgb::new(0.0, 0.0, 0.0))),
+    ));
+
+    let anti_aliasing = AntiAliasing::new(256, AntiAliasingTechnique::Adaptive);
+    let heatmap = camera.render_sample_heatmap(&world, &anti_aliasing);
+    assert_eq!(heatmap.len(), 16 * 16 * 3);
+
+    let row_max = |y: usize| {
+        return (0..16).map(|x| heatmap[(y * 16 + x) * 3]).max().unwrap();
+    };
+    assert!(
+        heatmap
+            .chunks(3)
+            .all(|pixel| pixel[0] == pixel[1] && pixel[1] == pixel[2])
+    );
+
+    // Flat sky and floor stop after the first batch, the horizon keeps sampling
+    let edge = row_max(8);
+    for flat in [row_max(2), row_max(14)] {
+        assert!(
+            edge > flat,
+            "Edge row peaks at {}, flat row at {}",
+            edge,
+            flat
+        );
+    }
+
+    // Without adaptive sampling every pixel takes every sample
+    let anti_aliasing = AntiAliasing::new(4, AntiAliasingTechnique::SuperSampling);
+    let heatmap = camera.render_sample_heatmap(&world, &anti_aliasing);
+    assert!(heatmap.iter().all(|&value| value == 255));
 }
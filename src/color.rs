@@ -0,0 +1,61 @@
+use palette::LinSrgb;
+
+#[cfg(test)]
+use assert_approx_eq::assert_approx_eq;
+
+// Encode a linear channel value in [0, 1] with the piecewise sRGB transfer curve, the same
+// encoding the renderer applies to every pixel after tone mapping
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        return 12.92 * c;
+    }
+    return 1.055 * c.powf(1.0 / 2.4) - 0.055;
+}
+
+// Inverse of linear_to_srgb, e.g. to recover linear radiance from a rendered 8 bit buffer
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        return c / 12.92;
+    }
+    return ((c + 0.055) / 1.055).powf(2.4);
+}
+
+// Perceived brightness of linear radiance, weighting the channels by Rec. 709
+pub fn luminance(color: LinSrgb) -> f32 {
+    return 0.2126 * color.red + 0.7152 * color.green + 0.0722 * color.blue;
+}
+
+#[test]
+fn test_srgb_breakpoint() {
+    // The linear segment and the power curve meet at 0.0031308, which encodes to 0.04045
+    let below = 0.0031308 - 1e-6;
+    let above = 0.0031308 + 1e-6;
+    assert_approx_eq!(linear_to_srgb(below), 12.92 * below, 1e-7);
+    assert_approx_eq!(linear_to_srgb(0.0031308), 0.04045, 1e-5);
+    assert_approx_eq!(linear_to_srgb(above), linear_to_srgb(below), 1e-4);
+
+    assert_approx_eq!(linear_to_srgb(0.0), 0.0);
+    assert_approx_eq!(linear_to_srgb(1.0), 1.0);
+    assert_approx_eq!(srgb_to_linear(0.04045), 0.0031308, 1e-6);
+
+    // Mid gray in linear light is much brighter than 0.5 once encoded
+    assert_approx_eq!(linear_to_srgb(0.5), 0.7354, 1e-4);
+}
+
+#[test]
+fn test_luminance() {
+    assert_approx_eq!(luminance(LinSrgb::new(1.0, 1.0, 1.0)), 1.0);
+    assert_approx_eq!(luminance(LinSrgb::new(0.0, 0.0, 0.0)), 0.0);
+
+    // Green looks far brighter than blue at the same radiance
+    assert!(luminance(LinSrgb::new(0.0, 1.0, 0.0)) > 5.0 * luminance(LinSrgb::new(0.0, 0.0, 1.0)));
+}
+
+#[test]
+fn test_srgb_round_trip() {
+    for i in 0..=100 {
+        let c = i as f32 / 100.0;
+        assert_approx_eq!(srgb_to_linear(linear_to_srgb(c)), c, 1e-5);
+        assert_approx_eq!(linear_to_srgb(srgb_to_linear(c)), c, 1e-5);
+    }
+}
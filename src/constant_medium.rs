@@ -0,0 +1,127 @@
+use std::sync::Arc;
+
+use rand::Rng;
+
+use crate::aabb::Aabb;
+use crate::material::Material;
+use crate::object::{Object, ObjectHitRecord};
+use crate::ray::Ray;
+use crate::vector_3d::Vector3D;
+
+#[cfg(test)]
+use crate::material::{Isotropic, Lambertian};
+#[cfg(test)]
+use crate::sphere::Sphere;
+#[cfg(test)]
+use palette::Srgb;
+
+// A volume of uniform density (smoke, fog) enclosed by a convex boundary object
+#[derive(Clone)]
+pub struct ConstantMedium {
+    boundary: Box<dyn Object>,
+    density: f64,
+    phase_material: Arc<Material>,
+}
+
+impl ConstantMedium {
+    pub fn new<T: Object + 'static>(
+        boundary: T,
+        density: f64,
+        phase_material: impl Into<Arc<Material>>,
+    ) -> ConstantMedium {
+        return ConstantMedium {
+            boundary: Box::new(boundary),
+            density: density,
+            phase_material: phase_material.into(),
+        };
+    }
+}
+
+impl Object for ConstantMedium {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<ObjectHitRecord> {
+        // Find where the ray enters and exits the boundary along the whole line
+        let entry = self.boundary.hit(ray, f64::MIN, f64::MAX)?;
+        let exit = self.boundary.hit(ray, entry.t + 0.0001, f64::MAX)?;
+
+        let mut t_enter = entry.t.max(t_min);
+        let t_exit = exit.t.min(t_max);
+        if t_enter >= t_exit {
+            return None;
+        }
+
+        // Never scatter behind the ray origin
+        t_enter = t_enter.max(0.0);
+
+        let ray_length = ray.direction.length();
+        let distance_inside = (t_exit - t_enter) * ray_length;
+
+        // Sample the free path length from the exponential distribution
+        let mut rng = rand::thread_rng();
+        let hit_distance = -(1.0 / self.density) * rng.r#gen::<f64>().ln();
+        if hit_distance > distance_inside {
+            return None;
+        }
+
+        let t = t_enter + hit_distance / ray_length;
+
+        // The normal and face are meaningless inside a volume, so pick arbitrary values
+        return Some(ObjectHitRecord {
+            t: t,
+            point: ray.at(t),
+            normal: Vector3D::new(1.0, 0.0, 0.0),
+            front_face: true,
+            material: self.phase_material.clone(),
+            u: 0.0,
+            v: 0.0,
+            tangent: Vector3D::new(0.0, 0.0, 0.0),
+            bitangent: Vector3D::new(0.0, 0.0, 0.0),
+        });
+    }
+
+    fn material(&self) -> Option<&Material> {
+        return Some(self.phase_material.as_ref());
+    }
+
+    fn set_material(&mut self, material: Arc<Material>) -> bool {
+        self.phase_material = material;
+        return true;
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        return self.boundary.bounding_box();
+    }
+}
+
+#[cfg(test)]
+fn count_scattered_rays(density: f64, ray_count: usize) -> usize {
+    let medium = ConstantMedium::new(
+        Sphere::new(
+            Vector3D::new(0.0, 0.0, 0.0),
+            1.0,
+            Material::Lambertian(Lambertian::new(Srgb::new(0.5, 0.5, 0.5))),
+        ),
+        density,
+        Material::Isotropic(Isotropic::new(Srgb::new(1.0, 1.0, 1.0))),
+    );
+    let ray = Ray::new(Vector3D::new(0.0, 0.0, 5.0), Vector3D::new(0.0, 0.0, -1.0));
+
+    return (0..ray_count)
+        .filter(|_| medium.hit(&ray, 0.001, f64::MAX).is_some())
+        .count();
+}
+
+#[test]
+fn test_constant_medium_dense() {
+    let scattered = count_scattered_rays(1000.0, 1000);
+    assert!(
+        scattered >= 990,
+        "Only {} of 1000 rays scattered",
+        scattered
+    );
+}
+
+#[test]
+fn test_constant_medium_sparse() {
+    let scattered = count_scattered_rays(1e-9, 1000);
+    assert!(scattered <= 10, "{} of 1000 rays scattered", scattered);
+}
@@ -0,0 +1,144 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::material::Material;
+use crate::object::{Object, ObjectHitRecord};
+use crate::ray::Ray;
+use crate::vector_3d::Vector3D;
+
+#[cfg(test)]
+use crate::material::Lambertian;
+#[cfg(test)]
+use assert_approx_eq::assert_approx_eq;
+#[cfg(test)]
+use palette::Srgb;
+
+#[derive(Clone)]
+pub struct Disk {
+    center: Vector3D,
+    normal: Vector3D, // Unit normal of the supporting plane
+    radius: f64,
+    material: Arc<Material>,
+}
+
+impl Disk {
+    pub fn new(
+        center: Vector3D,
+        normal: Vector3D,
+        radius: f64,
+        material: impl Into<Arc<Material>>,
+    ) -> Disk {
+        return Disk {
+            center: center,
+            normal: normal.unit_vector(),
+            radius: radius,
+            material: material.into(),
+        };
+    }
+}
+
+impl Object for Disk {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<ObjectHitRecord> {
+        let denominator = ray.direction.dot(&self.normal);
+
+        // A ray (nearly) parallel to the disk never intersects it
+        if denominator.abs() < 1e-8 {
+            return None;
+        }
+
+        // Intersect the supporting plane first
+        let t = (self.center - ray.origin).dot(&self.normal) / denominator;
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        // Then only keep hits within the radius of the center
+        let intersect_point = ray.at(t);
+        if (intersect_point - self.center).length_squared() > self.radius * self.radius {
+            return None;
+        }
+
+        let front_face = denominator < 0.0;
+
+        return Some(ObjectHitRecord {
+            t: t,
+            point: intersect_point,
+            normal: if front_face {
+                self.normal
+            } else {
+                -self.normal
+            },
+            front_face: front_face,
+            material: self.material.clone(),
+            u: 0.0,
+            v: 0.0,
+            tangent: Vector3D::new(0.0, 0.0, 0.0),
+            bitangent: Vector3D::new(0.0, 0.0, 0.0),
+        });
+    }
+
+    fn material(&self) -> Option<&Material> {
+        return Some(self.material.as_ref());
+    }
+
+    fn set_material(&mut self, material: Arc<Material>) -> bool {
+        self.material = material;
+        return true;
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        // The disk extends radius * sin(angle between the normal and the axis) along each axis
+        let mut extent = Vector3D::new(0.0, 0.0, 0.0);
+        for axis in 0..3 {
+            let sin_angle = (1.0 - self.normal[axis] * self.normal[axis])
+                .max(0.0)
+                .sqrt();
+            extent[axis] = self.radius * sin_angle;
+        }
+        return Some(Aabb::new(self.center - extent, self.center + extent));
+    }
+}
+
+#[cfg(test)]
+fn test_disk() -> Disk {
+    Disk::new(
+        Vector3D::new(0.0, 0.0, -1.0),
+        Vector3D::new(0.0, 0.0, 1.0),
+        0.5,
+        Material::Lambertian(Lambertian::new(Srgb::new(0.5, 0.5, 0.5))),
+    )
+}
+
+#[test]
+fn test_disk_hit_center() {
+    let disk = test_disk();
+    let ray = Ray::new(Vector3D::new(0.0, 0.0, 0.0), Vector3D::new(0.0, 0.0, -1.0));
+
+    let hit = disk
+        .hit(&ray, 0.001, f64::MAX)
+        .expect("Ray should hit the disk");
+
+    assert_approx_eq!(hit.t, 1.0);
+    assert_approx_eq!(hit.point.get_z(), -1.0);
+    assert_approx_eq!(hit.normal.get_z(), 1.0);
+    assert!(hit.front_face);
+}
+
+#[test]
+fn test_disk_miss_outside_radius() {
+    let disk = test_disk();
+    let ray = Ray::new(
+        Vector3D::new(0.501, 0.0, 0.0),
+        Vector3D::new(0.0, 0.0, -1.0),
+    );
+
+    assert!(disk.hit(&ray, 0.001, f64::MAX).is_none());
+}
+
+#[test]
+fn test_disk_parallel_miss() {
+    let disk = test_disk();
+    let ray = Ray::new(Vector3D::new(-2.0, 0.0, -1.0), Vector3D::new(1.0, 0.0, 0.0));
+
+    assert!(disk.hit(&ray, 0.001, f64::MAX).is_none());
+}
@@ -0,0 +1,64 @@
+use std::fmt;
+
+// Single error type for everything that loads, decodes or encodes data
+#[derive(Debug)]
+pub enum RayTracerError {
+    Io(std::io::Error),
+    Image(image::ImageError), // The file was read but could not be decoded as an image
+    Parse(serde_json::Error), // Malformed scene or settings file
+    InvalidInput(String),     // Arguments that do not fit together, e.g. mismatched dimensions
+}
+
+impl fmt::Display for RayTracerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RayTracerError::Io(error) => write!(f, "I/O error: {}", error),
+            RayTracerError::Image(error) => write!(f, "Image error: {}", error),
+            RayTracerError::Parse(error) => write!(f, "Parse error: {}", error),
+            RayTracerError::InvalidInput(message) => write!(f, "Invalid input: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for RayTracerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RayTracerError::Io(error) => Some(error),
+            RayTracerError::Image(error) => Some(error),
+            RayTracerError::Parse(error) => Some(error),
+            RayTracerError::InvalidInput(_) => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for RayTracerError {
+    fn from(error: std::io::Error) -> Self {
+        RayTracerError::Io(error)
+    }
+}
+
+impl From<image::ImageError> for RayTracerError {
+    fn from(error: image::ImageError) -> Self {
+        match error {
+            // Surface file system failures (e.g. a missing file) as plain I/O errors
+            image::ImageError::IoError(error) => RayTracerError::Io(error),
+            error => RayTracerError::Image(error),
+        }
+    }
+}
+
+impl From<serde_json::Error> for RayTracerError {
+    fn from(error: serde_json::Error) -> Self {
+        RayTracerError::Parse(error)
+    }
+}
+
+#[test]
+fn test_error_conversions() {
+    let error = RayTracerError::from(serde_json::from_str::<f64>("not json").unwrap_err());
+    assert!(matches!(error, RayTracerError::Parse(_)));
+    assert!(error.to_string().starts_with("Parse error"));
+
+    let error = RayTracerError::from(image::ImageError::DimensionError);
+    assert!(matches!(error, RayTracerError::Image(_)));
+}
@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+
+use palette::LinSrgb;
+
+#[cfg(test)]
+use assert_approx_eq::assert_approx_eq;
+#[cfg(test)]
+use rand::Rng;
+
+// Weighs the samples of a pixel by where in the pixel they were taken
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum ReconstructionFilter {
+    Box,      // Every sample counts the same, a plain average
+    Tent,     // Falls off linearly, reaching 0 one pixel from the center
+    Gaussian, // Falls off like a Gaussian with a standard deviation of half a pixel
+}
+
+impl ReconstructionFilter {
+    // Weight of a sample taken (dx, dy) pixels from the pixel center
+    pub fn weight(&self, dx: f64, dy: f64) -> f32 {
+        match self {
+            ReconstructionFilter::Box => 1.0,
+            ReconstructionFilter::Tent => {
+                ((1.0 - dx.abs()).max(0.0) * (1.0 - dy.abs()).max(0.0)) as f32
+            }
+            ReconstructionFilter::Gaussian => (-2.0 * (dx * dx + dy * dy)).exp() as f32,
+        }
+    }
+
+    // Average of colors sampled at offsets in [0, 1) within the pixel, each weighted by
+    // its distance from the center and normalized by the total weight
+    pub fn average(&self, samples: &[((f64, f64), LinSrgb)]) -> LinSrgb {
+        let mut total = LinSrgb::new(0.0, 0.0, 0.0);
+        let mut total_weight = 0.0;
+        for &((offset_x, offset_y), color) in samples {
+            let weight = self.weight(offset_x - 0.5, offset_y - 0.5);
+            total += color * weight;
+            total_weight += weight;
+        }
+
+        if total_weight <= 0.0 {
+            return total;
+        }
+        return total / total_weight;
+    }
+}
+
+#[test]
+fn test_box_filter_is_uniform_average() {
+    let mut rng = rand::thread_rng();
+    for count in [1, 4, 7, 16, 64] {
+        let samples: Vec<((f64, f64), LinSrgb)> = (0..count)
+            .map(|_| {
+                (
+                    (rng.r#gen(), rng.r#gen()),
+                    LinSrgb::new(rng.r#gen(), rng.r#gen::<f32>() * 10.0, rng.r#gen()),
+                )
+            })
+            .collect();
+
+        // Summed then divided by the count, exactly like unweighted supersampling
+        let mut expected = LinSrgb::new(0.0, 0.0, 0.0);
+        for (_, color) in &samples {
+            expected += *color;
+        }
+        expected /= count as f32;
+
+        let average = ReconstructionFilter::Box.average(&samples);
+        assert_eq!(average, expected);
+    }
+}
+
+#[test]
+fn test_filter_weights() {
+    for filter in [ReconstructionFilter::Tent, ReconstructionFilter::Gaussian] {
+        // Highest at the center, falling off towards the edges and corners of the pixel
+        assert_approx_eq!(filter.weight(0.0, 0.0), 1.0);
+        assert!(filter.weight(0.25, 0.0) < filter.weight(0.0, 0.0));
+        assert!(filter.weight(0.5, 0.0) < filter.weight(0.25, 0.0));
+        assert!(filter.weight(0.5, 0.5) < filter.weight(0.5, 0.0));
+        assert!(filter.weight(0.5, 0.5) > 0.0);
+        assert_approx_eq!(filter.weight(-0.3, 0.2), filter.weight(0.3, -0.2));
+
+        // A flat color stays the same however it is weighted
+        let flat = LinSrgb::new(0.2, 0.4, 0.8);
+        let average = filter.average(&[((0.1, 0.9), flat), ((0.5, 0.5), flat), ((0.7, 0.2), flat)]);
+        assert_approx_eq!(average.red, 0.2);
+        assert_approx_eq!(average.blue, 0.8);
+    }
+
+    assert_approx_eq!(ReconstructionFilter::Tent.weight(0.5, 0.0), 0.5);
+    assert_approx_eq!(ReconstructionFilter::Tent.weight(1.0, 0.0), 0.0);
+
+    // Samples near the center pull the average towards their color
+    let center = ((0.5, 0.5), LinSrgb::new(1.0, 1.0, 1.0));
+    let corner = ((0.0, 0.0), LinSrgb::new(0.0, 0.0, 0.0));
+    assert_approx_eq!(
+        ReconstructionFilter::Box.average(&[center, corner]).red,
+        0.5
+    );
+    assert!(ReconstructionFilter::Tent.average(&[center, corner]).red > 0.5);
+    assert!(
+        ReconstructionFilter::Gaussian
+            .average(&[center, corner])
+            .red
+            > 0.5
+    );
+}
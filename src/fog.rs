@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+
+use palette::{LinSrgb, Srgb};
+
+use crate::material::SrgbAsArray;
+
+#[cfg(test)]
+use assert_approx_eq::assert_approx_eq;
+
+// Exponential distance fog, blending what a ray hits towards color the further away it is
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct Fog {
+    #[serde(with = "SrgbAsArray")]
+    pub color: Srgb,
+    pub density: f64, // Extinction per unit distance, 0 disables the fog
+}
+
+impl Fog {
+    pub fn new(color: Srgb, density: f64) -> Fog {
+        return Fog {
+            color: color,
+            density: density,
+        };
+    }
+
+    // Blend a hit's radiance towards the fog color by 1 - exp(-density * distance)
+    pub fn apply(&self, color: LinSrgb, distance: f64) -> LinSrgb {
+        let amount = (1.0 - (-self.density * distance).exp()) as f32;
+        let fog_color = LinSrgb::new(self.color.red, self.color.green, self.color.blue);
+        return color * (1.0 - amount) + fog_color * amount;
+    }
+}
+
+#[test]
+fn test_fog_apply() {
+    let color: LinSrgb = LinSrgb::new(1.0, 0.0, 0.0);
+
+    let fog = Fog::new(Srgb::new(0.5, 0.5, 0.5), 0.1);
+    assert_eq!(fog.apply(color, 0.0), color);
+
+    // One mean free path away the fog covers 1 - 1/e of the color
+    let blended = fog.apply(color, 10.0);
+    let amount = 1.0 - (-1.0f32).exp();
+    assert_approx_eq!(blended.red, 1.0 - amount * 0.5);
+    assert_approx_eq!(blended.green, amount * 0.5);
+}
@@ -0,0 +1,255 @@
+use crate::aabb::Aabb;
+use crate::error::RayTracerError;
+use crate::object::{Object, ObjectHitRecord};
+use crate::ray::Ray;
+use crate::vector_3d::Vector3D;
+
+#[cfg(test)]
+use crate::material::{Lambertian, Material};
+#[cfg(test)]
+use crate::sphere::Sphere;
+#[cfg(test)]
+use palette::Srgb;
+#[cfg(test)]
+use rand::{Rng, SeedableRng};
+#[cfg(test)]
+use std::sync::Arc;
+
+// Average number of cells per object, more cells mean fewer objects to test per cell
+// but more cells to step through
+const CELLS_PER_OBJECT: f64 = 2.0;
+
+// Upper limit on the cells along each axis, so huge scenes do not allocate huge grids
+const MAX_RESOLUTION: usize = 128;
+
+// Splits the scene's bounding box into equally sized cells, each listing the objects
+// overlapping it. Rays step from cell to cell (3D-DDA) and only test the objects they pass,
+// which suits evenly spread geometry where a BVH does not gain much
+#[derive(Clone)]
+pub struct UniformGrid {
+    objects: Vec<Box<dyn Object>>,
+    cells: Vec<Vec<usize>>, // Indices into objects, x varies fastest, then y, then z
+    resolution: [usize; 3],
+    cell_size: Vector3D,
+    bounds: Aabb,
+}
+
+impl UniformGrid {
+    // Every object needs a bounding box, so unbounded objects (e.g. planes) have to stay
+    // outside the grid
+    pub fn new(objects: Vec<Box<dyn Object>>) -> Result<UniformGrid, RayTracerError> {
+        if objects.is_empty() {
+            return Err(RayTracerError::InvalidInput(
+                "A uniform grid needs at least one object".to_string(),
+            ));
+        }
+
+        let mut boxes = Vec::with_capacity(objects.len());
+        for (index, object) in objects.iter().enumerate() {
+            match object.bounding_box() {
+                Some(bounds) => boxes.push(bounds),
+                None => {
+                    return Err(RayTracerError::InvalidInput(format!(
+                        "Object {} has no bounding box and cannot be put in a uniform grid",
+                        index
+                    )));
+                }
+            }
+        }
+        let bounds = boxes
+            .iter()
+            .skip(1)
+            .fold(boxes[0], |bounds, object_bounds| {
+                bounds.surrounding(object_bounds)
+            });
+
+        // Roughly cubic cells, about CELLS_PER_OBJECT of them per object
+        let size = bounds.diagonal();
+        let volume = (size[0] * size[1] * size[2]).max(1e-12);
+        let cells_per_unit = (CELLS_PER_OBJECT * objects.len() as f64 / volume).cbrt();
+        let mut resolution = [1; 3];
+        let mut cell_size = Vector3D::new(0.0, 0.0, 0.0);
+        for axis in 0..3 {
+            resolution[axis] =
+                ((size[axis] * cells_per_unit).round() as usize).clamp(1, MAX_RESOLUTION);
+            cell_size[axis] = (size[axis] / resolution[axis] as f64).max(1e-9);
+        }
+
+        let mut grid = UniformGrid {
+            objects: Vec::new(),
+            cells: vec![Vec::new(); resolution[0] * resolution[1] * resolution[2]],
+            resolution: resolution,
+            cell_size: cell_size,
+            bounds: bounds,
+        };
+
+        for (index, object_bounds) in boxes.iter().enumerate() {
+            let low = grid.cell_of(&object_bounds.min());
+            let high = grid.cell_of(&object_bounds.max());
+            for z in low[2]..=high[2] {
+                for y in low[1]..=high[1] {
+                    for x in low[0]..=high[0] {
+                        let cell = grid.cell_index([x, y, z]);
+                        grid.cells[cell].push(index);
+                    }
+                }
+            }
+        }
+        grid.objects = objects;
+
+        return Ok(grid);
+    }
+
+    pub fn resolution(&self) -> [usize; 3] {
+        return self.resolution;
+    }
+
+    // Cell containing point, clamped to the grid
+    fn cell_of(&self, point: &Vector3D) -> [usize; 3] {
+        let mut cell = [0; 3];
+        for axis in 0..3 {
+            let offset = (point[axis] - self.bounds.min()[axis]) / self.cell_size[axis];
+            cell[axis] = (offset.max(0.0) as usize).min(self.resolution[axis] - 1);
+        }
+        return cell;
+    }
+
+    fn cell_index(&self, cell: [usize; 3]) -> usize {
+        return cell[0] + self.resolution[0] * (cell[1] + self.resolution[1] * cell[2]);
+    }
+}
+
+impl Object for UniformGrid {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<ObjectHitRecord> {
+        let (t_enter, t_exit) = self.bounds.hit_range(ray, t_min, t_max)?;
+        let mut cell = self.cell_of(&ray.at(t_enter));
+
+        // Distance along the ray to the next cell boundary on each axis, and between boundaries
+        let mut t_next = [f64::INFINITY; 3];
+        let mut t_delta = [f64::INFINITY; 3];
+        let mut step = [0isize; 3];
+        for axis in 0..3 {
+            let direction = ray.direction[axis];
+            if direction == 0.0 {
+                continue;
+            }
+
+            let boundary_cell = if direction > 0.0 {
+                cell[axis] + 1
+            } else {
+                cell[axis]
+            };
+            let boundary = self.bounds.min()[axis] + boundary_cell as f64 * self.cell_size[axis];
+            t_next[axis] = (boundary - ray.origin[axis]) / direction;
+            t_delta[axis] = self.cell_size[axis] / direction.abs();
+            step[axis] = if direction > 0.0 { 1 } else { -1 };
+        }
+
+        let mut closest_so_far = t_max;
+        let mut hit_record = None;
+        loop {
+            for &index in &self.cells[self.cell_index(cell)] {
+                if let Some(hit) = self.objects[index].hit(ray, t_min, closest_so_far) {
+                    closest_so_far = hit.t;
+                    hit_record = Some(hit);
+                }
+            }
+
+            let axis = if t_next[0] < t_next[1] {
+                if t_next[0] < t_next[2] { 0 } else { 2 }
+            } else if t_next[1] < t_next[2] {
+                1
+            } else {
+                2
+            };
+
+            // Objects span several cells, so a hit is only certain to be the closest once
+            // it lies inside the cells visited so far
+            if closest_so_far <= t_next[axis] || t_next[axis] > t_exit {
+                return hit_record;
+            }
+
+            let next = cell[axis] as isize + step[axis];
+            if next < 0 || next >= self.resolution[axis] as isize {
+                return hit_record;
+            }
+            cell[axis] = next as usize;
+            t_next[axis] += t_delta[axis];
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        return Some(self.bounds);
+    }
+}
+
+// Small spheres spread evenly through a 40 unit cube, so the grid has many cells to step
+// through with only a few objects in each. Scenes like World::random_spheres, where one huge
+// ground sphere stretches the grid, leave nearly every object in the same few cells
+#[cfg(test)]
+fn spread_spheres(count: usize, seed: u64) -> Vec<Box<dyn Object>> {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+    let material = Arc::new(Material::Lambertian(Lambertian::new(Srgb::new(
+        0.5, 0.5, 0.5,
+    ))));
+
+    return (0..count)
+        .map(|_| {
+            let center = Vector3D::new(
+                rng.gen_range(-20.0..20.0),
+                rng.gen_range(-20.0..20.0),
+                rng.gen_range(-20.0..20.0),
+            );
+            let sphere = Sphere::new(center, rng.gen_range(0.05..0.5), material.clone());
+            Box::new(sphere) as Box<dyn Object>
+        })
+        .collect();
+}
+
+#[test]
+fn test_uniform_grid_matches_linear_scan() {
+    let grid = UniformGrid::new(spread_spheres(500, 3)).unwrap();
+    let objects = spread_spheres(500, 3);
+    assert!(grid.resolution().iter().all(|&cells| cells > 1));
+
+    let mut rng = rand::rngs::StdRng::seed_from_u64(5);
+    let mut hits = 0;
+    for _ in 0..2000 {
+        // Rays from outside and from inside the grid
+        let origin = Vector3D::new(
+            rng.gen_range(-30.0..30.0),
+            rng.gen_range(-30.0..30.0),
+            rng.gen_range(-30.0..30.0),
+        );
+        let target = Vector3D::new(
+            rng.gen_range(-20.0..20.0),
+            rng.gen_range(-20.0..20.0),
+            rng.gen_range(-20.0..20.0),
+        );
+        let ray = Ray::new(origin, target - origin);
+
+        let expected = objects
+            .iter()
+            .filter_map(|sphere| sphere.hit(&ray, 0.001, f64::MAX))
+            .map(|hit| hit.t)
+            .min_by(|a, b| a.total_cmp(b));
+        assert_eq!(grid.hit(&ray, 0.001, f64::MAX).map(|hit| hit.t), expected);
+        hits += expected.is_some() as usize;
+    }
+    assert!(hits > 0);
+
+    // Axis-aligned rays never cross cell boundaries on the other two axes
+    for i in 0..50 {
+        let offset = i as f64 * 0.8 - 20.0;
+        let ray = Ray::new(
+            Vector3D::new(offset, 0.3, -30.0),
+            Vector3D::new(0.0, 0.0, 1.0),
+        );
+        let expected = objects
+            .iter()
+            .filter_map(|sphere| sphere.hit(&ray, 0.001, f64::MAX))
+            .map(|hit| hit.t)
+            .min_by(|a, b| a.total_cmp(b));
+        assert_eq!(grid.hit(&ray, 0.001, f64::MAX).map(|hit| hit.t), expected);
+    }
+}
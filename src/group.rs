@@ -0,0 +1,120 @@
+use crate::aabb::Aabb;
+use crate::object::{Object, ObjectHitRecord};
+use crate::ray::Ray;
+
+#[cfg(test)]
+use crate::anti_aliasing::CountingObject;
+#[cfg(test)]
+use crate::material::{Lambertian, Material};
+#[cfg(test)]
+use crate::sphere::Sphere;
+#[cfg(test)]
+use crate::vector_3d::Vector3D;
+#[cfg(test)]
+use palette::Srgb;
+#[cfg(test)]
+use std::sync::Arc;
+#[cfg(test)]
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Collection of objects behind one bounding box, so rays that miss the box skip every child
+// A single level alternative to a full BVH for organizing scenes
+#[derive(Clone)]
+pub struct Group {
+    children: Vec<Box<dyn Object>>,
+    bounds: Option<Aabb>, // Union of the child boxes, None if empty or any child is unbounded
+}
+
+impl Group {
+    pub fn new() -> Group {
+        return Group {
+            children: Vec::new(),
+            bounds: None,
+        };
+    }
+
+    pub fn add<T: Object + 'static>(&mut self, object: T) {
+        let child_bounds = object.bounding_box();
+        self.bounds = match (self.children.is_empty(), self.bounds, child_bounds) {
+            (true, _, child_bounds) => child_bounds,
+            (false, Some(bounds), Some(child_bounds)) => Some(bounds.surrounding(&child_bounds)),
+            _ => None,
+        };
+        self.children.push(Box::new(object));
+    }
+
+    pub fn len(&self) -> usize {
+        return self.children.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.children.is_empty();
+    }
+}
+
+impl Default for Group {
+    fn default() -> Self {
+        Group::new()
+    }
+}
+
+impl Object for Group {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<ObjectHitRecord> {
+        // Unbounded groups have to test every child
+        if let Some(bounds) = &self.bounds
+            && !bounds.hit(ray, t_min, t_max)
+        {
+            return None;
+        }
+
+        let mut closest_so_far = t_max;
+        let mut hit_record = None;
+        for child in &self.children {
+            if let Some(hit) = child.hit(ray, t_min, closest_so_far) {
+                closest_so_far = hit.t;
+                hit_record = Some(hit);
+            }
+        }
+        return hit_record;
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        return self.bounds;
+    }
+}
+
+#[test]
+fn test_group_hit() {
+    let count = Arc::new(AtomicUsize::new(0));
+    let mut group = Group::new();
+    for x in [-1.0, 1.0] {
+        group.add(CountingObject {
+            count: count.clone(),
+            object: Some(Box::new(Sphere::new(
+                Vector3D::new(x, 0.0, -2.0),
+                0.5,
+                Material::Lambertian(Lambertian::new(Srgb::new(0.5, 0.5, 0.5))),
+            ))),
+        });
+    }
+    assert_eq!(group.len(), 2);
+
+    let bounds = group.bounding_box().expect("Spheres are bounded");
+    assert_eq!(bounds.min(), Vector3D::new(-1.5, -0.5, -2.5));
+    assert_eq!(bounds.max(), Vector3D::new(1.5, 0.5, -1.5));
+
+    // A ray through the box tests every child and finds the closest hit
+    let origin = Vector3D::new(0.0, 0.0, 0.0);
+    let ray = Ray::new(origin, Vector3D::new(1.0, 0.0, -2.0));
+    let hit = group
+        .hit(&ray, 0.001, f64::MAX)
+        .expect("Ray should hit the right sphere");
+    assert!(hit.point.get_x() > 0.0);
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+
+    // A ray missing the box never reaches the children
+    count.store(0, Ordering::SeqCst);
+    let ray = Ray::new(origin, Vector3D::new(0.0, 1.0, -1.0));
+    assert!(group.hit(&ray, 0.001, f64::MAX).is_none());
+    assert_eq!(count.load(Ordering::SeqCst), 0);
+}
@@ -0,0 +1,206 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::material::Material;
+use crate::object::{Object, ObjectHitRecord};
+use crate::ray::Ray;
+use crate::vector_3d::Vector3D;
+
+#[cfg(test)]
+use crate::material::Lambertian;
+#[cfg(test)]
+use crate::sphere::Sphere;
+#[cfg(test)]
+use assert_approx_eq::assert_approx_eq;
+#[cfg(test)]
+use palette::Srgb;
+
+// Moves an object by a fixed offset without rebuilding it
+#[derive(Clone)]
+pub struct Translate {
+    object: Box<dyn Object>,
+    offset: Vector3D,
+}
+
+impl Translate {
+    pub fn new<T: Object + 'static>(object: T, offset: Vector3D) -> Translate {
+        return Translate {
+            object: Box::new(object),
+            offset: offset,
+        };
+    }
+}
+
+impl Object for Translate {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<ObjectHitRecord> {
+        // Move the ray into the object's local space instead of moving the object
+        let local_ray = Ray::new(ray.origin - self.offset, ray.direction);
+
+        let mut hit_record = self.object.hit(&local_ray, t_min, t_max)?;
+        hit_record.point += self.offset;
+        return Some(hit_record);
+    }
+
+    fn material(&self) -> Option<&Material> {
+        return self.object.material();
+    }
+
+    fn set_material(&mut self, material: Arc<Material>) -> bool {
+        return self.object.set_material(material);
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let aabb = self.object.bounding_box()?;
+        return Some(Aabb::new(
+            aabb.min() + self.offset,
+            aabb.max() + self.offset,
+        ));
+    }
+}
+
+// Rotates an object about the y axis by a fixed angle
+#[derive(Clone)]
+pub struct RotateY {
+    object: Box<dyn Object>,
+    sin_theta: f64,
+    cos_theta: f64,
+}
+
+impl RotateY {
+    pub fn new<T: Object + 'static>(object: T, angle_deg: f64) -> RotateY {
+        let theta = angle_deg.to_radians();
+        return RotateY {
+            object: Box::new(object),
+            sin_theta: theta.sin(),
+            cos_theta: theta.cos(),
+        };
+    }
+
+    // Rotate a world space vector into object space (by -theta)
+    fn to_local(&self, vec: &Vector3D) -> Vector3D {
+        return Vector3D::new(
+            self.cos_theta * vec.get_x() - self.sin_theta * vec.get_z(),
+            vec.get_y(),
+            self.sin_theta * vec.get_x() + self.cos_theta * vec.get_z(),
+        );
+    }
+
+    // Rotate an object space vector back into world space (by +theta)
+    fn to_world(&self, vec: &Vector3D) -> Vector3D {
+        return Vector3D::new(
+            self.cos_theta * vec.get_x() + self.sin_theta * vec.get_z(),
+            vec.get_y(),
+            -self.sin_theta * vec.get_x() + self.cos_theta * vec.get_z(),
+        );
+    }
+}
+
+impl Object for RotateY {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<ObjectHitRecord> {
+        let local_ray = Ray::new(self.to_local(&ray.origin), self.to_local(&ray.direction));
+
+        // Rotations preserve orientation, so front_face carries over unchanged
+        let mut hit_record = self.object.hit(&local_ray, t_min, t_max)?;
+        hit_record.point = self.to_world(&hit_record.point);
+        hit_record.normal = self.to_world(&hit_record.normal);
+        hit_record.tangent = self.to_world(&hit_record.tangent);
+        hit_record.bitangent = self.to_world(&hit_record.bitangent);
+        return Some(hit_record);
+    }
+
+    fn material(&self) -> Option<&Material> {
+        return self.object.material();
+    }
+
+    fn set_material(&mut self, material: Arc<Material>) -> bool {
+        return self.object.set_material(material);
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let aabb = self.object.bounding_box()?;
+
+        // Rotate all eight corners and take the box around them
+        let mut min = Vector3D::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+        let mut max = Vector3D::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for corner in 0..8 {
+            let mut point = aabb.min();
+            for axis in 0..3 {
+                if corner & (1 << axis) != 0 {
+                    point[axis] = aabb.max()[axis];
+                }
+            }
+
+            let rotated = self.to_world(&point);
+            min = min.component_min(&rotated);
+            max = max.component_max(&rotated);
+        }
+
+        return Some(Aabb::new(min, max));
+    }
+}
+
+#[cfg(test)]
+fn test_unit_sphere(center: Vector3D) -> Sphere {
+    Sphere::new(
+        center,
+        1.0,
+        Material::Lambertian(Lambertian::new(Srgb::new(0.5, 0.5, 0.5))),
+    )
+}
+
+#[test]
+fn test_translate() {
+    let sphere = Translate::new(
+        test_unit_sphere(Vector3D::new(0.0, 0.0, 0.0)),
+        Vector3D::new(2.0, 0.0, 0.0),
+    );
+    let ray = Ray::new(Vector3D::new(2.0, 0.0, 5.0), Vector3D::new(0.0, 0.0, -1.0));
+
+    let hit = sphere
+        .hit(&ray, 0.001, f64::MAX)
+        .expect("Ray should hit the translated sphere");
+
+    assert_approx_eq!(hit.t, 4.0);
+    assert_approx_eq!(hit.point.get_x(), 2.0);
+    assert_approx_eq!(hit.point.get_y(), 0.0);
+    assert_approx_eq!(hit.point.get_z(), 1.0);
+    assert_approx_eq!(hit.normal.get_z(), 1.0);
+
+    // The original position of the sphere should now be empty
+    let ray = Ray::new(Vector3D::new(0.0, 0.0, 5.0), Vector3D::new(0.0, 0.0, -1.0));
+    assert!(sphere.hit(&ray, 0.001, f64::MAX).is_none());
+}
+
+#[test]
+fn test_rotate_y() {
+    // Rotating +90 degrees about y moves (1, 0, 0) to (0, 0, -1)
+    let sphere = RotateY::new(test_unit_sphere(Vector3D::new(1.0, 0.0, 0.0)), 90.0);
+    let ray = Ray::new(Vector3D::new(0.0, 0.0, 5.0), Vector3D::new(0.0, 0.0, -1.0));
+
+    let hit = sphere
+        .hit(&ray, 0.001, f64::MAX)
+        .expect("Ray should hit the rotated sphere");
+
+    assert_approx_eq!(hit.point.get_x(), 0.0);
+    assert_approx_eq!(hit.point.get_z(), 0.0);
+    assert_approx_eq!(hit.normal.get_x(), 0.0);
+    assert_approx_eq!(hit.normal.get_z(), 1.0);
+}
+
+#[test]
+fn test_instance_bounding_box() {
+    let translated = Translate::new(
+        test_unit_sphere(Vector3D::new(0.0, 0.0, 0.0)),
+        Vector3D::new(2.0, 0.0, 0.0),
+    );
+    let aabb = translated.bounding_box().unwrap();
+    assert_eq!(aabb.min(), Vector3D::new(1.0, -1.0, -1.0));
+    assert_eq!(aabb.max(), Vector3D::new(3.0, 1.0, 1.0));
+
+    let rotated = RotateY::new(test_unit_sphere(Vector3D::new(2.0, 0.0, 0.0)), 90.0);
+    let aabb = rotated.bounding_box().unwrap();
+    assert_approx_eq!(aabb.min().get_x(), -1.0);
+    assert_approx_eq!(aabb.max().get_x(), 1.0);
+    assert_approx_eq!(aabb.min().get_z(), -3.0);
+    assert_approx_eq!(aabb.max().get_z(), -1.0);
+}
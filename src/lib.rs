@@ -1,10 +1,35 @@
 #![cfg_attr(feature = "simd", feature(portable_simd))]
 
+pub mod aabb;
 pub mod anti_aliasing;
+pub mod background;
+pub mod box_prim;
+pub mod bvh;
 pub mod camera;
+pub mod color;
+pub mod constant_medium;
+pub mod disk;
+pub mod error;
+pub mod filter;
+pub mod fog;
+pub mod grid;
+pub mod group;
+pub mod instance;
+pub mod light;
 pub mod material;
 pub mod object;
+pub mod onb;
+pub mod perlin;
+pub mod plane;
+pub mod primitive;
+pub mod quad;
 pub mod ray;
+pub mod rectangle;
+pub mod sampler;
+pub mod sdf;
 pub mod sphere;
+pub mod texture;
+pub mod tone_map;
+pub mod triangle;
 pub mod vector_3d;
 pub mod world;
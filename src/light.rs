@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+
+use palette::Srgb;
+
+use rand::Rng;
+
+use crate::material::SrgbAsArray;
+use crate::object::Object;
+use crate::vector_3d::Vector3D;
+use crate::world::World;
+
+// Infinitely small light that cannot be hit by rays, only Phong surfaces are lit by it directly
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub struct PointLight {
+    position: Vector3D,
+    #[serde(with = "SrgbAsArray")]
+    intensity: Srgb, // Linear radiant intensity, falls off with the squared distance
+}
+
+impl PointLight {
+    pub fn new(position: Vector3D, intensity: Srgb) -> PointLight {
+        return PointLight {
+            position: position,
+            intensity: intensity,
+        };
+    }
+
+    pub fn position(&self) -> Vector3D {
+        return self.position;
+    }
+
+    pub fn intensity(&self) -> Srgb {
+        return self.intensity;
+    }
+}
+
+// Samples directions from one point towards the emissive objects of a world, picking one of
+// the lights that support sampling uniformly (e.g. rectangles and quads)
+pub struct PdfLight<'a> {
+    origin: Vector3D,
+    lights: Vec<&'a dyn Object>,
+    samples: Vec<Vector3D>, // One direction drawn towards each light
+}
+
+impl<'a> PdfLight<'a> {
+    pub fn new(origin: Vector3D, world: &'a World) -> PdfLight<'a> {
+        // Drawing a direction up front is also how lights that cannot be sampled are found,
+        // and keeping it means generate picks from independent samples at no extra cost
+        let mut lights = Vec::new();
+        let mut samples = Vec::new();
+        for light in world
+            .emissive_objects()
+            .iter()
+            .filter_map(|&index| world.get(index))
+        {
+            if let Some(direction) = light.random_direction(&origin) {
+                lights.push(light);
+                samples.push(direction);
+            }
+        }
+
+        return PdfLight {
+            origin: origin,
+            lights: lights,
+            samples: samples,
+        };
+    }
+
+    // True when the world has no light that can be sampled
+    pub fn is_empty(&self) -> bool {
+        return self.lights.is_empty();
+    }
+
+    // Direction towards a random point on a random light, not normalized
+    pub fn generate(&self) -> Vector3D {
+        let index = rand::thread_rng().gen_range(0..self.samples.len());
+        return self.samples[index];
+    }
+
+    // Solid angle density of generate producing direction, averaged over the lights
+    pub fn value(&self, direction: &Vector3D) -> f64 {
+        if self.lights.is_empty() {
+            return 0.0;
+        }
+
+        let total: f64 = self
+            .lights
+            .iter()
+            .map(|light| light.pdf_value(&self.origin, direction))
+            .sum();
+        return total / self.lights.len() as f64;
+    }
+}
@@ -1,11 +1,8 @@
 use std::env;
 
-use palette::Srgb;
-
 use ray_tracer::anti_aliasing::{AntiAliasing, AntiAliasingTechnique};
-use ray_tracer::camera::{Camera, CameraConfig};
-use ray_tracer::material::{Lambertian, Material, Metal};
-use ray_tracer::sphere::Sphere;
+use ray_tracer::background::Background;
+use ray_tracer::camera::{Camera, CameraConfig, CameraSettings};
 use ray_tracer::vector_3d::Vector3D;
 use ray_tracer::world::World;
 
@@ -22,49 +19,23 @@ fn main() {
         aspect: 800.0 / 600.0,
         image_width: 800,
         vertical_fov: 70.0,
+        horizontal_fov: None,
+        background: Background::default(),
         vector_up: Vector3D::new(0.0, 1.0, 0.0),
         look_from: Vector3D::new(0.0, 0.0, 0.0),
         look_at: Vector3D::new(0.0, 0.0, -1.0),
+        settings: CameraSettings::default(),
     };
 
     let camera = Camera::from(camera_config);
 
     let anti_aliasing = AntiAliasing::new(10, AntiAliasingTechnique::SuperSampling);
 
-    let mut world = World::new();
-
-    let metal_1_config = (Srgb::new(0.05, 0.0, 0.05), 0.0);
-    let metal_2_config = (Srgb::new(1.0, 0.5, 1.0), 0.15);
-    let metal_3_config = (Srgb::new(0.5, 1.0, 0.5), 0.15);
-    let lambertian_config = Srgb::new(0.5, 0.5, 0.5);
-
-    world.add(Sphere::new(
-        Vector3D::new(0.0, 0.0, -1.0),
-        0.1,
-        Material::Metal(Metal::new(metal_1_config.0, metal_1_config.1)),
-    ));
-
-    world.add(Sphere::new(
-        Vector3D::new(-0.5, 0.0, -1.0),
-        0.25,
-        Material::Metal(Metal::new(metal_2_config.0, metal_2_config.1)),
-    ));
-
-    world.add(Sphere::new(
-        Vector3D::new(0.5, 0.0, -1.0),
-        0.25,
-        Material::Metal(Metal::new(metal_3_config.0, metal_3_config.1)),
-    ));
-
-    world.add(Sphere::new(
-        Vector3D::new(0.0, -100.5, -1.0),
-        100.0,
-        Material::Lambertian(Lambertian::new(lambertian_config)),
-    ));
+    let world = World::demo_scene();
 
     let pixels = camera.render(&world, &anti_aliasing);
 
     camera
-        .write_image(&args[1], &pixels, camera.image_width, camera.image_height)
+        .write_image_auto(&args[1], &pixels, camera.image_width, camera.image_height)
         .expect("Failed to write image");
 }
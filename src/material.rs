@@ -1,14 +1,36 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+
 use serde::{Deserialize, Serialize};
 
 use palette::Srgb;
 
+use rand::Rng;
+
 use crate::object::ObjectHitRecord;
-use crate::ray::Ray;
+use crate::onb::Onb;
+use crate::ray::{Ray, SURFACE_EPSILON};
+use crate::texture::Texture;
 use crate::vector_3d::Vector3D;
 
+#[cfg(test)]
+use crate::instance::RotateY;
+#[cfg(test)]
+use crate::object::Object;
+#[cfg(test)]
+use crate::plane::Plane;
+#[cfg(test)]
+use crate::quad::Quad;
+#[cfg(test)]
+use crate::sphere::Sphere;
+#[cfg(test)]
+use crate::triangle::Triangle;
+#[cfg(test)]
+use assert_approx_eq::assert_approx_eq;
+
 // https://docs.rs/serde_with/1.9.4/serde_with/macro.serde_conv.html
 serde_with::serde_conv!(
-    SrgbAsArray,
+    pub(crate) SrgbAsArray,
     Srgb,
     |srgb: &Srgb| [srgb.red, srgb.green, srgb.blue],
     |value: [f32; 3]| -> Result<_, std::convert::Infallible> {
@@ -16,14 +38,151 @@ serde_with::serde_conv!(
     }
 );
 
+// Times a rough metal reflection is drawn before settling for the mirror direction
+const METAL_FUZZ_ATTEMPTS: usize = 16;
+
+// Fraction of light a clear coat reflects head on, for a varnish with refractive index 1.5
+const COAT_NORMAL_REFLECTANCE: f64 = 0.04;
+
+// Step in u and v between the height samples a bump map's slope is estimated from
+const BUMP_DELTA: f64 = 1e-3;
+
 fn reflect(vec_1: &Vector3D, vec_2: &Vector3D) -> Vector3D {
-    *vec_1 - *vec_2 * (2.0 * vec_1.dot(vec_2))
+    vec_1 - &(vec_2 * (2.0 * vec_1.dot(vec_2)))
+}
+
+// Snell's law for a unit direction crossing a surface with unit normal facing against it,
+// where ratio is the refractive index on the incoming side over the outgoing side
+fn refract(direction: &Vector3D, normal: &Vector3D, ratio: f64) -> Vector3D {
+    let cos_theta = (-direction.dot(normal)).min(1.0);
+    let perpendicular = (direction + &(normal * cos_theta)) * ratio;
+    let parallel = normal * -(1.0 - perpendicular.length_squared()).abs().sqrt();
+    return perpendicular + parallel;
+}
+
+// Schlick's approximation of the Fresnel reflectance at an angle with the given cosine
+fn schlick(cosine: f64, normal_reflectance: f64) -> f64 {
+    return normal_reflectance + (1.0 - normal_reflectance) * (1.0 - cosine).powi(5);
+}
+
+// Grayscale height texture whose slopes tilt the shading normal, so flat geometry looks
+// dented or embossed. strength scales the slopes, negative values invert the bumps
+#[derive(Debug, Clone)]
+pub struct BumpMap {
+    pub height: Arc<dyn Texture>,
+    pub strength: f64,
+}
+
+impl BumpMap {
+    pub fn new(height: Arc<dyn Texture>, strength: f64) -> BumpMap {
+        return BumpMap {
+            height: height,
+            strength: strength,
+        };
+    }
+
+    // Height at (u, v), the average of the texture's channels
+    fn height_at(&self, u: f64, v: f64, point: &Vector3D) -> f64 {
+        let color = self.height.value(u, v, point);
+        return (color.red + color.green + color.blue) as f64 / 3.0;
+    }
+
+    // Slope of the height along u and along v, by central differences
+    fn gradient(&self, hit_record: &ObjectHitRecord) -> (f64, f64) {
+        let (u, v, point) = (hit_record.u, hit_record.v, &hit_record.point);
+        let du =
+            self.height_at(u + BUMP_DELTA, v, point) - self.height_at(u - BUMP_DELTA, v, point);
+        let dv =
+            self.height_at(u, v + BUMP_DELTA, point) - self.height_at(u, v - BUMP_DELTA, point);
+        return (du / (2.0 * BUMP_DELTA), dv / (2.0 * BUMP_DELTA));
+    }
+}
+
+// Unit tangent and bitangent the normal and bump maps are laid out along, following the
+// directions of increasing u and v on the surface. The bitangent is flipped to follow v where
+// the texture is mirrored. Surfaces without texture directions fall back to a tangent around
+// the y axis, or along the x axis where the normal points straight up or down
+fn tangent_frame(normal: &Vector3D, hit_record: &ObjectHitRecord) -> (Vector3D, Vector3D) {
+    let mut tangent = hit_record.tangent.reject_from(normal);
+    if tangent.length_squared() < 1e-12 {
+        tangent = Vector3D::new(0.0, 1.0, 0.0).cross(normal);
+    }
+    if tangent.length_squared() < 1e-12 {
+        tangent = Vector3D::new(1.0, 0.0, 0.0).reject_from(normal);
+    }
+    let tangent = tangent.unit_vector();
+
+    let mut bitangent = normal.cross(&tangent);
+    if bitangent.dot(&hit_record.bitangent) < 0.0 {
+        bitangent = -bitangent;
+    }
+    return (tangent, bitangent);
+}
+
+// Shading normal at the hit, tilted by a tangent-space normal map and then by a bump map,
+// if either is set. The normal map encodes normals as RGB = (n + 1) / 2, so flat areas are
+// (0.5, 0.5, 1.0), and the normal leans away from where the bump map's height rises
+fn shading_normal(
+    normal_map: &Option<Arc<dyn Texture>>,
+    bump_map: &Option<BumpMap>,
+    hit_record: &ObjectHitRecord,
+) -> Vector3D {
+    let mut normal = hit_record.normal;
+
+    if let Some(normal_map) = normal_map {
+        let color = normal_map.value(hit_record.u, hit_record.v, &hit_record.point);
+        let local = Vector3D::new(
+            2.0 * color.red as f64 - 1.0,
+            2.0 * color.green as f64 - 1.0,
+            2.0 * color.blue as f64 - 1.0,
+        );
+
+        // A texel encoding no direction at all leaves the normal as it is
+        let (tangent, bitangent) = tangent_frame(&normal, hit_record);
+        let mapped = (tangent * local.get_x() + bitangent * local.get_y() + normal * local.get_z())
+            .normalize_or_zero();
+        if !mapped.near_zero() {
+            normal = mapped;
+        }
+    }
+
+    if let Some(bump_map) = bump_map {
+        let (du, dv) = bump_map.gradient(hit_record);
+        let (tangent, bitangent) = tangent_frame(&normal, hit_record);
+        normal = (normal - (tangent * du + bitangent * dv) * bump_map.strength).unit_vector();
+    }
+
+    return normal;
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub enum Material {
     Lambertian(Lambertian),
     Metal(Metal),
+    Isotropic(Isotropic),
+    DiffuseLight(DiffuseLight),
+    Phong(Phong),
+    Coated(Coated),
+    Dielectric(Dielectric),
+}
+
+impl Material {
+    // Light given off by the surface itself, black for everything except lights
+    pub fn emitted(&self) -> Srgb {
+        match self {
+            Material::DiffuseLight(d) => d.emit,
+            Material::Coated(c) => c.base.emitted(),
+            _ => Srgb::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    pub fn is_emissive(&self) -> bool {
+        match self {
+            Material::DiffuseLight(_) => true,
+            Material::Coated(c) => c.base.is_emissive(),
+            _ => false,
+        }
+    }
 }
 
 pub trait Scatterable {
@@ -35,6 +194,11 @@ impl Scatterable for Material {
         match self {
             Material::Lambertian(l) => l.scatter(ray, hit_record),
             Material::Metal(m) => m.scatter(ray, hit_record),
+            Material::Isotropic(i) => i.scatter(ray, hit_record),
+            Material::DiffuseLight(d) => d.scatter(ray, hit_record),
+            Material::Phong(p) => p.scatter(ray, hit_record),
+            Material::Coated(c) => c.scatter(ray, hit_record),
+            Material::Dielectric(d) => d.scatter(ray, hit_record),
         }
     }
 }
@@ -43,24 +207,49 @@ impl Scatterable for Material {
 pub struct Lambertian {
     #[serde(with = "SrgbAsArray")]
     pub albedo: Srgb,
+    #[serde(skip)]
+    pub normal_map: Option<Arc<dyn Texture>>,
+    #[serde(skip)]
+    pub bump_map: Option<BumpMap>,
 }
 
 impl Lambertian {
     pub fn new(albedo: Srgb) -> Lambertian {
-        return Lambertian { albedo: albedo };
+        return Lambertian {
+            albedo: albedo,
+            normal_map: None,
+            bump_map: None,
+        };
+    }
+
+    pub fn with_normal_map(mut self, normal_map: Arc<dyn Texture>) -> Lambertian {
+        self.normal_map = Some(normal_map);
+        return self;
+    }
+
+    pub fn with_bump_map(mut self, bump_map: BumpMap) -> Lambertian {
+        self.bump_map = Some(bump_map);
+        return self;
+    }
+
+    // Density scatter draws direction with, cos(theta) / pi around the shading normal
+    pub fn scattering_pdf(&self, hit_record: &ObjectHitRecord, direction: &Vector3D) -> f64 {
+        let normal = shading_normal(&self.normal_map, &self.bump_map, hit_record);
+        let cosine = normal.dot(&direction.unit_vector());
+        return cosine.max(0.0) / PI;
     }
 }
 
 impl Scatterable for Lambertian {
     fn scatter(&self, ray: &Ray, hit_record: &ObjectHitRecord) -> Option<(Ray, Srgb)> {
-        let mut scatter_direction = hit_record.normal + Vector3D::random_in_unit_sphere();
+        let normal = shading_normal(&self.normal_map, &self.bump_map, hit_record);
 
-        if scatter_direction.near_zero() {
-            scatter_direction = hit_record.normal;
-        }
+        // Importance sample the cosine term, directions are drawn with pdf cos(theta) / pi
+        // around the normal, which cancels the Lambertian BRDF's cosine so the weight is the albedo
+        let scatter_direction = Onb::new(&normal).local(&Vector3D::random_cosine_direction());
 
-        let target = hit_record.point + scatter_direction;
-        let scattered = Ray::new(hit_record.point, target - hit_record.point);
+        let scattered = Ray::new(hit_record.point, scatter_direction)
+            .offset_origin(&hit_record.normal, SURFACE_EPSILON);
         let attenuation = self.albedo;
         Some((scattered, attenuation))
     }
@@ -71,6 +260,17 @@ pub struct Metal {
     #[serde(with = "SrgbAsArray")]
     pub albedo: Srgb,
     pub roughness: f64,
+    // Brushing direction for anisotropic reflection, isotropic roughness is used when unset
+    #[serde(default)]
+    pub tangent: Option<Vector3D>,
+    #[serde(default)]
+    pub roughness_u: f64, // Roughness along the tangent
+    #[serde(default)]
+    pub roughness_v: f64, // Roughness along the bitangent
+    #[serde(skip)]
+    pub normal_map: Option<Arc<dyn Texture>>,
+    #[serde(skip)]
+    pub bump_map: Option<BumpMap>,
 }
 
 impl Metal {
@@ -78,21 +278,742 @@ impl Metal {
         return Metal {
             albedo: albedo,
             roughness: roughness,
+            tangent: None,
+            roughness_u: roughness,
+            roughness_v: roughness,
+            normal_map: None,
+            bump_map: None,
+        };
+    }
+
+    // Brushed metal, with the reflection blurred by roughness_u along the tangent
+    // and by roughness_v across it
+    pub fn anisotropic(
+        albedo: Srgb,
+        tangent: Vector3D,
+        roughness_u: f64,
+        roughness_v: f64,
+    ) -> Metal {
+        return Metal {
+            albedo: albedo,
+            roughness: (roughness_u + roughness_v) / 2.0,
+            tangent: Some(tangent),
+            roughness_u: roughness_u,
+            roughness_v: roughness_v,
+            normal_map: None,
+            bump_map: None,
         };
     }
+
+    pub fn with_normal_map(mut self, normal_map: Arc<dyn Texture>) -> Metal {
+        self.normal_map = Some(normal_map);
+        return self;
+    }
+
+    pub fn with_bump_map(mut self, bump_map: BumpMap) -> Metal {
+        self.bump_map = Some(bump_map);
+        return self;
+    }
+
+    // Random offset added to the mirror direction, at least min_roughness in every direction
+    fn perturbation(&self, normal: &Vector3D, min_roughness: f64) -> Vector3D {
+        let offset = Vector3D::random_in_unit_sphere();
+        let roughness = self.roughness.max(min_roughness);
+
+        let tangent = match self.tangent {
+            Some(tangent) => tangent,
+            None => return offset * roughness,
+        };
+
+        // Project the tangent onto the surface, falling back to isotropic if it is along the normal
+        let tangent = tangent.reject_from(normal);
+        if tangent.near_zero() {
+            return offset * roughness;
+        }
+        let tangent = tangent.unit_vector();
+        let bitangent = normal.cross(&tangent);
+
+        return tangent * (offset.get_x() * self.roughness_u.max(min_roughness))
+            + bitangent * (offset.get_y() * self.roughness_v.max(min_roughness))
+            + normal * (offset.get_z() * roughness);
+    }
+
+    // Scatter as if the metal were at least min_roughness rough, without copying it
+    pub fn scatter_with_min_roughness(
+        &self,
+        ray: &Ray,
+        hit_record: &ObjectHitRecord,
+        min_roughness: f64,
+    ) -> Option<(Ray, Srgb)> {
+        let normal = shading_normal(&self.normal_map, &self.bump_map, hit_record);
+        let reflected = reflect(&ray.direction, &normal);
+
+        // Fuzz that pushes the reflection below the surface is drawn again instead of absorbing
+        // the ray, which made rough metal darker than its albedo, especially at grazing angles
+        let mut direction = reflected;
+        for _ in 0..METAL_FUZZ_ATTEMPTS {
+            let rough_direction = reflected + self.perturbation(&normal, min_roughness);
+            if rough_direction.dot(&normal) > 0.0 {
+                direction = rough_direction;
+                break;
+            }
+        }
+
+        // Only rays arriving from below the shading normal are still absorbed
+        if direction.dot(&normal) <= 0.0 {
+            return None;
+        }
+
+        let scattered = Ray::new(hit_record.point, direction)
+            .offset_origin(&hit_record.normal, SURFACE_EPSILON);
+        Some((scattered, self.albedo))
+    }
 }
 
 impl Scatterable for Metal {
     fn scatter(&self, ray: &Ray, hit_record: &ObjectHitRecord) -> Option<(Ray, Srgb)> {
-        let reflected = reflect(&ray.direction, &hit_record.normal);
-        let rough_direction = reflected + Vector3D::random_in_unit_sphere() * self.roughness;
-        let scattered = Ray::new(hit_record.point, rough_direction);
-        let attenuation = self.albedo;
+        return self.scatter_with_min_roughness(ray, hit_record, 0.0);
+    }
+}
+
+// Clear or tinted glass, water and other transparent materials that both reflect and refract
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Dielectric {
+    pub refraction_index: f64,
+    // Fraction of each channel absorbed per unit of distance travelled inside (Beer-Lambert law),
+    // black for clear glass
+    #[serde(with = "SrgbAsArray", default = "no_absorption")]
+    pub absorption: Srgb,
+}
+
+fn no_absorption() -> Srgb {
+    return Srgb::new(0.0, 0.0, 0.0);
+}
+
+impl Dielectric {
+    pub fn new(refraction_index: f64) -> Dielectric {
+        return Dielectric {
+            refraction_index: refraction_index,
+            absorption: no_absorption(),
+        };
+    }
 
-        if scattered.direction.dot(&hit_record.normal) > 0.0 {
-            Some((scattered, attenuation))
+    // Tint the glass, thicker parts absorb more and look darker
+    pub fn with_absorption(mut self, absorption: Srgb) -> Dielectric {
+        self.absorption = absorption;
+        return self;
+    }
+
+    // Share of the light left after travelling distance through the material
+    pub fn transmittance(&self, distance: f64) -> Srgb {
+        let transmit = |absorption: f32| (-(absorption as f64) * distance).exp() as f32;
+        return Srgb::new(
+            transmit(self.absorption.red),
+            transmit(self.absorption.green),
+            transmit(self.absorption.blue),
+        );
+    }
+}
+
+impl Scatterable for Dielectric {
+    fn scatter(&self, ray: &Ray, hit_record: &ObjectHitRecord) -> Option<(Ray, Srgb)> {
+        let ratio = if hit_record.front_face {
+            1.0 / self.refraction_index
         } else {
-            None
+            self.refraction_index
+        };
+
+        let direction = ray.direction.unit_vector();
+        let cos_theta = (-direction.dot(&hit_record.normal)).min(1.0);
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+
+        // Past the critical angle there is no refracted ray, otherwise reflect with
+        // the Fresnel probability
+        let normal_reflectance = ((1.0 - ratio) / (1.0 + ratio)).powi(2);
+        let cannot_refract = ratio * sin_theta > 1.0;
+        let scatter_direction = if cannot_refract
+            || rand::thread_rng().r#gen::<f64>() < schlick(cos_theta, normal_reflectance)
+        {
+            reflect(&direction, &hit_record.normal)
+        } else {
+            refract(&direction, &hit_record.normal, ratio)
+        };
+
+        // Leaving the material, the ray has travelled inside it since it last scattered
+        let attenuation = if hit_record.front_face {
+            Srgb::new(1.0, 1.0, 1.0)
+        } else {
+            self.transmittance(hit_record.t * ray.direction.length())
+        };
+
+        let scattered = Ray::new(hit_record.point, scatter_direction)
+            .offset_origin(&hit_record.normal, SURFACE_EPSILON);
+        Some((scattered, attenuation))
+    }
+}
+
+// Phase function for participating media, scattering uniformly in all directions
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Isotropic {
+    #[serde(with = "SrgbAsArray")]
+    pub albedo: Srgb,
+}
+
+impl Isotropic {
+    pub fn new(albedo: Srgb) -> Isotropic {
+        return Isotropic { albedo: albedo };
+    }
+}
+
+impl Scatterable for Isotropic {
+    fn scatter(&self, _ray: &Ray, hit_record: &ObjectHitRecord) -> Option<(Ray, Srgb)> {
+        let direction = Vector3D::random_unit_vector();
+        let scattered = Ray::new(hit_record.point, direction);
+        Some((scattered, self.albedo))
+    }
+}
+
+// Light source that emits the same radiance in all directions and reflects nothing
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DiffuseLight {
+    #[serde(with = "SrgbAsArray")]
+    pub emit: Srgb, // Linear radiance, may go above 1.0 for bright lights
+}
+
+impl DiffuseLight {
+    pub fn new(emit: Srgb) -> DiffuseLight {
+        return DiffuseLight { emit: emit };
+    }
+}
+
+impl Scatterable for DiffuseLight {
+    fn scatter(&self, _ray: &Ray, _hit_record: &ObjectHitRecord) -> Option<(Ray, Srgb)> {
+        None
+    }
+}
+
+// Classic Blinn-Phong shading, lit directly by the world's point lights with a diffuse term
+// plus a specular highlight, and scattering diffusely like Lambertian for indirect light
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Phong {
+    #[serde(with = "SrgbAsArray")]
+    pub diffuse: Srgb,
+    #[serde(with = "SrgbAsArray")]
+    pub specular: Srgb,
+    pub shininess: f64, // Higher values give smaller, sharper highlights
+}
+
+impl Phong {
+    pub fn new(diffuse: Srgb, specular: Srgb, shininess: f64) -> Phong {
+        return Phong {
+            diffuse: diffuse,
+            specular: specular,
+            shininess: shininess,
+        };
+    }
+
+    // Fraction of a point light's intensity reflected towards the viewer, for unit vectors
+    // from the surface towards the viewer and towards the light
+    pub fn reflectance(
+        &self,
+        normal: &Vector3D,
+        to_viewer: &Vector3D,
+        to_light: &Vector3D,
+    ) -> Srgb {
+        let cos_light = normal.dot(to_light);
+        if cos_light <= 0.0 {
+            return Srgb::new(0.0, 0.0, 0.0);
+        }
+
+        // The highlight peaks where the half-vector lines up with the normal. Viewer and light
+        // on exactly opposite sides have no half-vector, and so no highlight
+        let half_vector = (to_viewer + to_light).normalize_or_zero();
+        let highlight = normal.dot(&half_vector).max(0.0).powf(self.shininess);
+
+        let diffuse = cos_light as f32;
+        let specular = highlight as f32;
+        return Srgb::new(
+            self.diffuse.red * diffuse + self.specular.red * specular,
+            self.diffuse.green * diffuse + self.specular.green * specular,
+            self.diffuse.blue * diffuse + self.specular.blue * specular,
+        );
+    }
+}
+
+impl Scatterable for Phong {
+    fn scatter(&self, _ray: &Ray, hit_record: &ObjectHitRecord) -> Option<(Ray, Srgb)> {
+        let scatter_direction =
+            Onb::new(&hit_record.normal).local(&Vector3D::random_cosine_direction());
+        let scattered = Ray::new(hit_record.point, scatter_direction)
+            .offset_origin(&hit_record.normal, SURFACE_EPSILON);
+        Some((scattered, self.diffuse))
+    }
+}
+
+// A reflective layer (e.g. clear coat or varnish) over another material. Each scatter either
+// reflects off the coat or passes through to the base, with the coat reflecting more at
+// grazing angles. weight scales the coat from absent (0) to a full varnish layer (1)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Coated {
+    pub base: Box<Material>,
+    pub coat: Metal,
+    pub weight: f64,
+}
+
+impl Coated {
+    pub fn new(base: Material, coat: Metal, weight: f64) -> Coated {
+        return Coated {
+            base: Box::new(base),
+            coat: coat,
+            weight: weight,
+        };
+    }
+
+    // Chance that a ray arriving along direction reflects off the coat, from Schlick's
+    // approximation of the Fresnel reflectance
+    pub fn coat_probability(&self, direction: &Vector3D, normal: &Vector3D) -> f64 {
+        let cosine = (-direction.unit_vector().dot(normal)).clamp(0.0, 1.0);
+        return self.weight.clamp(0.0, 1.0) * schlick(cosine, COAT_NORMAL_REFLECTANCE);
+    }
+}
+
+impl Scatterable for Coated {
+    fn scatter(&self, ray: &Ray, hit_record: &ObjectHitRecord) -> Option<(Ray, Srgb)> {
+        // Choosing each layer with its own probability keeps the weights of both as they are
+        let coat_probability = self.coat_probability(&ray.direction, &hit_record.normal);
+        if rand::thread_rng().r#gen::<f64>() < coat_probability {
+            return self.coat.scatter(ray, hit_record);
+        }
+        return self.base.scatter(ray, hit_record);
+    }
+}
+
+#[cfg(test)]
+fn test_hit_record(material: &Material) -> ObjectHitRecord {
+    ObjectHitRecord {
+        t: 1.0,
+        point: Vector3D::new(0.0, 0.0, 0.0),
+        normal: Vector3D::new(0.0, 1.0, 0.0),
+        front_face: true,
+        material: Arc::new(material.clone()),
+        u: 0.0,
+        v: 0.0,
+        tangent: Vector3D::new(1.0, 0.0, 0.0),
+        bitangent: Vector3D::new(0.0, 0.0, -1.0),
+    }
+}
+
+#[cfg(test)]
+// Mean scattered direction and mean squared distance from the mirror direction
+fn metal_scatter_statistics(metal: &Metal) -> (Vector3D, f64) {
+    let material = Material::Metal(metal.clone());
+    let hit_record = test_hit_record(&material);
+    let ray = Ray::new(Vector3D::new(-1.0, 1.0, 0.0), Vector3D::new(1.0, -1.0, 0.0));
+    let reflected = Vector3D::new(1.0, 1.0, 0.0);
+
+    let mut sum = Vector3D::new(0.0, 0.0, 0.0);
+    let mut spread = 0.0;
+    let mut count = 0;
+    for _ in 0..20000 {
+        if let Some((scattered, _)) = metal.scatter(&ray, &hit_record) {
+            sum += scattered.direction;
+            spread += (scattered.direction - reflected).length_squared();
+            count += 1;
+        }
+    }
+
+    return (sum * (1.0 / count as f64), spread / count as f64);
+}
+
+#[test]
+fn test_metal_anisotropic_matches_isotropic() {
+    let albedo = Srgb::new(0.8, 0.8, 0.8);
+    let isotropic = Metal::new(albedo, 0.3);
+    let anisotropic = Metal::anisotropic(albedo, Vector3D::new(0.0, 0.0, 1.0), 0.3, 0.3);
+
+    let (isotropic_mean, isotropic_spread) = metal_scatter_statistics(&isotropic);
+    let (anisotropic_mean, anisotropic_spread) = metal_scatter_statistics(&anisotropic);
+
+    assert_approx_eq!(isotropic_mean.get_x(), anisotropic_mean.get_x(), 0.01);
+    assert_approx_eq!(isotropic_mean.get_y(), anisotropic_mean.get_y(), 0.01);
+    assert_approx_eq!(isotropic_mean.get_z(), anisotropic_mean.get_z(), 0.01);
+    assert_approx_eq!(isotropic_spread, anisotropic_spread, 0.005);
+}
+
+#[test]
+fn test_metal_anisotropic_stretch() {
+    // Rough along z, smooth along x, so reflections only spread along z
+    let metal = Metal::anisotropic(
+        Srgb::new(0.8, 0.8, 0.8),
+        Vector3D::new(0.0, 0.0, 1.0),
+        0.5,
+        0.0,
+    );
+    let material = Material::Metal(metal.clone());
+    let hit_record = test_hit_record(&material);
+    let ray = Ray::new(Vector3D::new(0.0, 1.0, 0.0), Vector3D::new(0.0, -1.0, 0.0));
+
+    let mut spread_x = 0.0;
+    let mut spread_z = 0.0;
+    for _ in 0..1000 {
+        let (scattered, _) = metal.scatter(&ray, &hit_record).unwrap();
+        spread_x += scattered.direction.get_x().abs();
+        spread_z += scattered.direction.get_z().abs();
+    }
+
+    assert_approx_eq!(spread_x, 0.0);
+    assert!(spread_z > 100.0);
+}
+
+#[test]
+fn test_metal_rough_reflection_stays_above_surface() {
+    let roughness = 0.9;
+    let metal = Metal::new(Srgb::new(0.8, 0.8, 0.8), roughness);
+    let material = Material::Metal(metal.clone());
+    let hit_record = test_hit_record(&material);
+    let normal = hit_record.normal;
+
+    // At a grazing angle most of the fuzz sphere around the mirror direction is below the surface
+    let ray = Ray::new(Vector3D::new(-1.0, 0.2, 0.0), Vector3D::new(1.0, -0.2, 0.0));
+    let reflected = reflect(&ray.direction, &normal);
+
+    let samples = 10000;
+    let mut fuzz_rejected = 0;
+    let mut scatter_rejected = 0;
+    for _ in 0..samples {
+        // Adding the fuzz once and absorbing rays below the surface, as scatter used to
+        let fuzzed = reflected + Vector3D::random_in_unit_sphere() * roughness;
+        if fuzzed.dot(&normal) <= 0.0 {
+            fuzz_rejected += 1;
+        }
+
+        match metal.scatter(&ray, &hit_record) {
+            Some((scattered, _)) => assert!(scattered.direction.dot(&normal) > 0.0),
+            None => scatter_rejected += 1,
+        }
+    }
+
+    assert!(
+        fuzz_rejected > samples / 10,
+        "Only {} fuzzed rays were below the surface",
+        fuzz_rejected
+    );
+    assert!(
+        scatter_rejected * 100 < fuzz_rejected,
+        "{} rays absorbed, versus {} before",
+        scatter_rejected,
+        fuzz_rejected
+    );
+}
+
+#[cfg(test)]
+#[derive(Debug)]
+struct FlatNormalMap;
+
+#[cfg(test)]
+impl Texture for FlatNormalMap {
+    fn value(&self, _u: f64, _v: f64, _point: &Vector3D) -> Srgb {
+        return Srgb::new(0.5, 0.5, 1.0);
+    }
+}
+
+#[test]
+fn test_flat_normal_map() {
+    let plain = Metal::new(Srgb::new(0.8, 0.8, 0.8), 0.0);
+    let mapped = plain.clone().with_normal_map(Arc::new(FlatNormalMap));
+
+    let material = Material::Metal(plain.clone());
+    let ray = Ray::new(
+        Vector3D::new(-1.0, 2.0, 0.5),
+        Vector3D::new(1.0, -2.0, -0.5),
+    );
+    for normal in [
+        Vector3D::new(0.0, 1.0, 0.0),
+        Vector3D::new(0.6, 0.8, 0.0),
+        Vector3D::new(0.0, 0.6, 0.8),
+    ] {
+        let mut hit_record = test_hit_record(&material);
+        hit_record.normal = normal;
+
+        let shading = shading_normal(&mapped.normal_map, &mapped.bump_map, &hit_record);
+        assert_approx_eq!(shading.get_x(), normal.get_x());
+        assert_approx_eq!(shading.get_y(), normal.get_y());
+        assert_approx_eq!(shading.get_z(), normal.get_z());
+
+        let (expected, _) = plain.scatter(&ray, &hit_record).unwrap();
+        let (scattered, _) = mapped.scatter(&ray, &hit_record).unwrap();
+        assert_approx_eq!(scattered.direction.get_x(), expected.direction.get_x());
+        assert_approx_eq!(scattered.direction.get_y(), expected.direction.get_y());
+        assert_approx_eq!(scattered.direction.get_z(), expected.direction.get_z());
+    }
+}
+
+#[cfg(test)]
+#[derive(Debug)]
+struct HeightRamp {
+    slope_u: f64,
+    slope_v: f64,
+}
+
+#[cfg(test)]
+impl Texture for HeightRamp {
+    fn value(&self, u: f64, v: f64, _point: &Vector3D) -> Srgb {
+        let height = (0.5 + self.slope_u * u + self.slope_v * v) as f32;
+        return Srgb::new(height, height, height);
+    }
+}
+
+#[test]
+fn test_bump_map() {
+    let material = Material::Lambertian(Lambertian::new(Srgb::new(0.5, 0.5, 0.5)));
+    let mut hit_record = test_hit_record(&material);
+    hit_record.u = 0.3;
+    hit_record.v = 0.6;
+    let normal = hit_record.normal;
+
+    // A flat height leaves the normal alone, however strong the bumps
+    let flat = BumpMap::new(
+        Arc::new(HeightRamp {
+            slope_u: 0.0,
+            slope_v: 0.0,
+        }),
+        10.0,
+    );
+    let shading = shading_normal(&None, &Some(flat), &hit_record);
+    assert_approx_eq!(shading.get_x(), normal.get_x());
+    assert_approx_eq!(shading.get_y(), normal.get_y());
+    assert_approx_eq!(shading.get_z(), normal.get_z());
+
+    // Height rising along u, the tangent (+x for an upward normal), tilts the normal along
+    // the tangent axis, leaning back away from the rise like the face of a slope. Heights
+    // pass through the texture's f32 channels, so the tilt is only close to exact
+    let (tangent, bitangent) = tangent_frame(&normal, &hit_record);
+    let ramp = BumpMap::new(
+        Arc::new(HeightRamp {
+            slope_u: 0.4,
+            slope_v: 0.0,
+        }),
+        1.0,
+    );
+    let shading = shading_normal(&None, &Some(ramp), &hit_record);
+    assert_approx_eq!(shading.dot(&tangent), -0.4 / 1.16_f64.sqrt(), 1e-4);
+    assert_approx_eq!(shading.dot(&bitangent), 0.0);
+    assert_approx_eq!(shading.length(), 1.0);
+
+    // Along v it tilts along the bitangent instead, and strength scales the tilt
+    let ramp = BumpMap::new(
+        Arc::new(HeightRamp {
+            slope_u: 0.0,
+            slope_v: 0.4,
+        }),
+        0.5,
+    );
+    let shading = shading_normal(&None, &Some(ramp.clone()), &hit_record);
+    assert_approx_eq!(shading.dot(&tangent), 0.0);
+    assert_approx_eq!(shading.dot(&bitangent), -0.2 / 1.04_f64.sqrt(), 1e-4);
+
+    // Scattering follows the bumped normal
+    let lambertian = Lambertian::new(Srgb::new(0.5, 0.5, 0.5)).with_bump_map(ramp);
+    let toward_bump = -bitangent * 0.2 + normal;
+    let away_from_bump = bitangent * 0.2 + normal;
+    assert!(
+        lambertian.scattering_pdf(&hit_record, &toward_bump)
+            > lambertian.scattering_pdf(&hit_record, &away_from_bump)
+    );
+}
+
+#[cfg(test)]
+#[derive(Debug)]
+struct LeaningNormalMap; // Leans towards +u in tangent space
+
+#[cfg(test)]
+impl Texture for LeaningNormalMap {
+    fn value(&self, _u: f64, _v: f64, _point: &Vector3D) -> Srgb {
+        return Srgb::new(0.8, 0.5, 0.9);
+    }
+}
+
+#[cfg(test)]
+// Hit record straight down the normal onto the object at point
+fn hit_from_above(object: &dyn Object, point: Vector3D, normal: Vector3D) -> ObjectHitRecord {
+    let ray = Ray::new(point + normal * 0.5, -normal);
+    return object.hit(&ray, 0.001, f64::MAX).unwrap();
+}
+
+#[test]
+fn test_tangent_frame_follows_uv() {
+    let material = Material::Lambertian(Lambertian::new(Srgb::new(0.5, 0.5, 0.5)));
+    let quad = Quad::new(
+        Vector3D::new(-1.0, 0.0, -1.0),
+        Vector3D::new(0.0, 0.0, 2.0),
+        Vector3D::new(2.0, 0.0, 0.0),
+        material.clone(),
+    );
+    // Texture coordinates mirrored against the winding
+    let triangle = Triangle::new(
+        Vector3D::new(-1.0, 0.0, 1.0),
+        Vector3D::new(1.0, 0.0, 1.0),
+        Vector3D::new(0.0, 0.0, -1.0),
+        material.clone(),
+    )
+    .with_vertex_uvs([(1.0, 0.0), (0.0, 0.0), (0.5, 1.0)]);
+    let objects: Vec<(Box<dyn Object>, Vector3D, Vector3D)> = vec![
+        (
+            Box::new(Plane::new(
+                Vector3D::new(0.0, 0.0, 0.0),
+                Vector3D::new(0.0, 1.0, 0.0),
+                material.clone(),
+            )),
+            Vector3D::new(0.2, 0.0, 0.3),
+            Vector3D::new(0.0, 1.0, 0.0),
+        ),
+        (
+            Box::new(quad.clone()),
+            Vector3D::new(0.2, 0.0, 0.3),
+            Vector3D::new(0.0, -1.0, 0.0),
+        ),
+        (
+            Box::new(RotateY::new(quad, 60.0)),
+            Vector3D::new(0.2, 0.0, 0.3),
+            Vector3D::new(0.0, -1.0, 0.0),
+        ),
+        (
+            Box::new(triangle),
+            Vector3D::new(0.1, 0.0, 0.2),
+            Vector3D::new(0.0, 1.0, 0.0),
+        ),
+        (
+            Box::new(Sphere::new(
+                Vector3D::new(0.0, 0.0, 0.0),
+                1.0,
+                material.clone(),
+            )),
+            Vector3D::new(0.6, 0.0, 0.8),
+            Vector3D::new(0.6, 0.0, 0.8),
+        ),
+    ];
+
+    for (object, point, normal) in &objects {
+        let hit_record = hit_from_above(object.as_ref(), *point, *normal);
+        let (tangent, bitangent) = tangent_frame(&hit_record.normal, &hit_record);
+
+        // Stepping along the tangent grows u, along the bitangent grows v
+        let step = 1e-3;
+        let along_tangent = hit_from_above(object.as_ref(), *point + tangent * step, *normal);
+        assert!(along_tangent.u > hit_record.u);
+        assert_approx_eq!(along_tangent.v, hit_record.v, 1e-5);
+        let along_bitangent = hit_from_above(object.as_ref(), *point + bitangent * step, *normal);
+        assert!(along_bitangent.v > hit_record.v);
+        assert_approx_eq!(along_bitangent.u, hit_record.u, 1e-5);
+
+        // So normal maps lean towards u and bumps rising along v lean away from v
+        let shading = shading_normal(&Some(Arc::new(LeaningNormalMap)), &None, &hit_record);
+        assert!(shading.dot(&hit_record.tangent) > 0.0);
+        assert_approx_eq!(shading.dot(&hit_record.bitangent), 0.0, 1e-6);
+        let ramp = BumpMap::new(
+            Arc::new(HeightRamp {
+                slope_u: 0.0,
+                slope_v: 0.4,
+            }),
+            1.0,
+        );
+        let shading = shading_normal(&None, &Some(ramp), &hit_record);
+        assert!(shading.dot(&hit_record.bitangent) < 0.0);
+    }
+}
+
+#[test]
+fn test_lambertian_cosine_sampling() {
+    let lambertian = Lambertian::new(Srgb::new(0.5, 0.5, 0.5));
+    let material = Material::Lambertian(lambertian.clone());
+    let mut hit_record = test_hit_record(&material);
+    hit_record.normal = Vector3D::new(0.0, 0.6, 0.8);
+    let ray = Ray::new(Vector3D::new(0.0, 1.0, 1.0), Vector3D::new(0.0, -1.0, -1.0));
+
+    let samples = 10000;
+    let mut mean_cos_theta = 0.0;
+    for _ in 0..samples {
+        let (scattered, _) = lambertian.scatter(&ray, &hit_record).unwrap();
+        let cos_theta = scattered.direction.unit_vector().dot(&hit_record.normal);
+        assert!(cos_theta >= 0.0);
+        mean_cos_theta += cos_theta / samples as f64;
+    }
+
+    // Directions bunch up around the normal, E[cos(theta)] = 2/3
+    assert_approx_eq!(mean_cos_theta, 2.0 / 3.0, 0.02);
+}
+
+#[test]
+fn test_coated() {
+    let base = Material::Lambertian(Lambertian::new(Srgb::new(0.8, 0.1, 0.1)));
+    let coat = Metal::new(Srgb::new(1.0, 1.0, 1.0), 0.0);
+    let hit_record = test_hit_record(&base);
+    let head_on = Ray::new(Vector3D::new(0.0, 1.0, 0.0), Vector3D::new(0.0, -1.0, 0.0));
+    let grazing = Ray::new(
+        Vector3D::new(-1.0, 0.05, 0.0),
+        Vector3D::new(1.0, -0.05, 0.0),
+    );
+
+    // Without weight the coat is never chosen, so every scatter is the base's
+    let uncoated = Coated::new(base.clone(), coat.clone(), 0.0);
+    for ray in [&head_on, &grazing] {
+        assert_eq!(
+            uncoated.coat_probability(&ray.direction, &hit_record.normal),
+            0.0
+        );
+        for _ in 0..1000 {
+            let (scattered, attenuation) = uncoated.scatter(ray, &hit_record).unwrap();
+            assert_eq!(attenuation, Srgb::new(0.8, 0.1, 0.1));
+            assert!(scattered.direction.dot(&hit_record.normal) > 0.0);
         }
     }
+
+    // A full coat reflects a little head on and most light at grazing angles
+    let coated = Coated::new(base, coat, 1.0);
+    assert_approx_eq!(
+        coated.coat_probability(&head_on.direction, &hit_record.normal),
+        COAT_NORMAL_REFLECTANCE
+    );
+    let grazing_probability = coated.coat_probability(&grazing.direction, &hit_record.normal);
+    assert!(grazing_probability > 0.5, "{}", grazing_probability);
+}
+
+#[test]
+fn test_dielectric_absorption() {
+    // A ray leaving the bottom of a slab after crossing it straight down from the top
+    let exit_hit = |material: &Material, thickness: f64| {
+        let mut hit_record = test_hit_record(material);
+        hit_record.t = thickness;
+        hit_record.front_face = false;
+        let ray = Ray::new(
+            Vector3D::new(0.0, thickness, 0.0),
+            Vector3D::new(0.0, -1.0, 0.0),
+        );
+        (ray, hit_record)
+    };
+
+    let clear = Dielectric::new(1.5);
+    let tinted = Dielectric::new(1.5).with_absorption(Srgb::new(0.1, 0.5, 1.0));
+    for _ in 0..100 {
+        let (ray, hit_record) = exit_hit(&Material::Dielectric(clear.clone()), 3.0);
+        let (_, attenuation) = clear.scatter(&ray, &hit_record).unwrap();
+        assert_eq!(attenuation, Srgb::new(1.0, 1.0, 1.0));
+    }
+
+    let (ray, hit_record) = exit_hit(&Material::Dielectric(tinted.clone()), 0.5);
+    let (_, thin) = tinted.scatter(&ray, &hit_record).unwrap();
+    let (ray, hit_record) = exit_hit(&Material::Dielectric(tinted.clone()), 3.0);
+    let (_, thick) = tinted.scatter(&ray, &hit_record).unwrap();
+
+    assert_approx_eq!(thin.green, (-0.25f32).exp());
+    assert!(thick.red < thin.red);
+    assert!(thick.green < thin.green);
+    assert!(thick.blue < thin.blue);
+
+    // Entering the slab does not absorb anything yet
+    let hit_record = test_hit_record(&Material::Dielectric(tinted.clone()));
+    let ray = Ray::new(Vector3D::new(0.0, 1.0, 0.0), Vector3D::new(0.0, -1.0, 0.0));
+    let (scattered, attenuation) = tinted.scatter(&ray, &hit_record).unwrap();
+    assert_eq!(attenuation, Srgb::new(1.0, 1.0, 1.0));
+    assert_approx_eq!(scattered.direction.unit_vector().get_y().abs(), 1.0);
 }
@@ -1,17 +1,86 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
 use crate::material::Material;
 use crate::ray::Ray;
 use crate::vector_3d::Vector3D;
 
-pub struct ObjectHitRecord<'material> {
+pub struct ObjectHitRecord {
     pub t: f64,
     pub point: Vector3D,
     pub normal: Vector3D,
     pub front_face: bool,
-    pub material: &'material Material,
-    // pub u: f64,
-    // pub v: f64,
+    pub material: Arc<Material>, // Shared with the object that was hit
+    pub u: f64,                  // Surface coordinates for texture lookups
+    pub v: f64,
+    pub tangent: Vector3D, // Direction of increasing u along the surface (dp/du), not normalized
+    pub bitangent: Vector3D, // Direction of increasing v (dp/dv), both zero without coordinates
+}
+
+// Lets boxed objects be cloned, which Clone itself cannot do for trait objects
+// Implemented for every Object that is Clone, so objects only need to derive Clone
+pub trait CloneObject {
+    fn clone_box(&self) -> Box<dyn Object>;
+}
+
+impl<T: Object + Clone + 'static> CloneObject for T {
+    fn clone_box(&self) -> Box<dyn Object> {
+        return Box::new(self.clone());
+    }
+}
+
+impl Clone for Box<dyn Object> {
+    fn clone(&self) -> Box<dyn Object> {
+        return self.clone_box();
+    }
 }
 
-pub trait Object {
+// Objects are shared by the render threads, so they must be Send + Sync
+pub trait Object: Send + Sync + CloneObject {
     fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<ObjectHitRecord>;
+
+    // Intersect four rays at once, each with its own t_max. Objects with a vectorized
+    // intersection override this, everything else is tested one ray at a time
+    fn hit4(&self, rays: &[Ray; 4], t_min: f64, t_max: [f64; 4]) -> [Option<ObjectHitRecord>; 4] {
+        return [0, 1, 2, 3].map(|lane| self.hit(&rays[lane], t_min, t_max[lane]));
+    }
+
+    // Box enclosing the whole object, or None if the object is unbounded (e.g. a plane)
+    fn bounding_box(&self) -> Option<Aabb>;
+
+    // Material covering the whole surface, None for objects without a single material
+    fn material(&self) -> Option<&Material> {
+        return None;
+    }
+
+    // Replace the material covering the whole surface, returns false for objects
+    // without a single material, which are left unchanged. Objects keep their material in
+    // an Arc, so one material can be shared by every object using it without copies
+    fn set_material(&mut self, _material: Arc<Material>) -> bool {
+        return false;
+    }
+
+    // Direction from origin towards a random point on the surface, used to aim rays at
+    // lights directly. None for objects that cannot be sampled, which are only found by chance
+    fn random_direction(&self, _origin: &Vector3D) -> Option<Vector3D> {
+        return None;
+    }
+
+    // Solid angle density of random_direction producing direction from origin,
+    // 0 when the direction misses the object
+    fn pdf_value(&self, _origin: &Vector3D, _direction: &Vector3D) -> f64 {
+        return 0.0;
+    }
+}
+
+// Solid angle density of a direction that hit a flat surface of the given area, when points
+// are picked uniformly over that area: distance squared over the cosine-projected area
+pub(crate) fn area_pdf_value(hit_record: &ObjectHitRecord, direction: &Vector3D, area: f64) -> f64 {
+    let distance_squared = hit_record.t * hit_record.t * direction.length_squared();
+    let cosine = (direction.dot(&hit_record.normal) / direction.length()).abs();
+    if cosine < 1e-8 {
+        return 0.0;
+    }
+
+    return distance_squared / (cosine * area);
 }
@@ -0,0 +1,71 @@
+use crate::vector_3d::Vector3D;
+
+#[cfg(test)]
+use assert_approx_eq::assert_approx_eq;
+
+// Orthonormal basis, used to carry directions sampled around +z over to an arbitrary axis
+#[derive(Debug, Clone, Copy)]
+pub struct Onb {
+    u: Vector3D,
+    v: Vector3D,
+    w: Vector3D,
+}
+
+impl Onb {
+    // Basis whose w axis points along the given direction
+    pub fn new(w: &Vector3D) -> Onb {
+        let w = w.unit_vector();
+
+        // Any helper vector that is not parallel to w works
+        let helper = if w.get_x().abs() > 0.9 {
+            Vector3D::new(0.0, 1.0, 0.0)
+        } else {
+            Vector3D::new(1.0, 0.0, 0.0)
+        };
+        let v = w.cross(&helper).unit_vector();
+        let u = w.cross(&v);
+
+        return Onb { u: u, v: v, w: w };
+    }
+
+    pub fn u(&self) -> Vector3D {
+        return self.u;
+    }
+
+    pub fn v(&self) -> Vector3D {
+        return self.v;
+    }
+
+    pub fn w(&self) -> Vector3D {
+        return self.w;
+    }
+
+    // Transform a vector given in (u, v, w) coordinates into world space
+    pub fn local(&self, a: &Vector3D) -> Vector3D {
+        return self.u * a.get_x() + self.v * a.get_y() + self.w * a.get_z();
+    }
+}
+
+#[test]
+fn test_onb() {
+    for w in [
+        Vector3D::new(0.0, 0.0, 1.0),
+        Vector3D::new(1.0, 0.0, 0.0),
+        Vector3D::new(1.0, -2.0, 3.0),
+    ] {
+        let onb = Onb::new(&w);
+
+        assert_approx_eq!(onb.u().length(), 1.0);
+        assert_approx_eq!(onb.v().length(), 1.0);
+        assert_approx_eq!(onb.u().dot(&onb.v()), 0.0);
+        assert_approx_eq!(onb.u().dot(&onb.w()), 0.0);
+        assert_approx_eq!(onb.v().dot(&onb.w()), 0.0);
+
+        // +z in local coordinates maps onto w
+        let mapped = onb.local(&Vector3D::new(0.0, 0.0, 1.0));
+        let expected = w.unit_vector();
+        assert_approx_eq!(mapped.get_x(), expected.get_x());
+        assert_approx_eq!(mapped.get_y(), expected.get_y());
+        assert_approx_eq!(mapped.get_z(), expected.get_z());
+    }
+}
@@ -0,0 +1,150 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::vector_3d::Vector3D;
+
+const POINT_COUNT: usize = 256;
+
+// Gradient (Perlin) noise over a lattice of random unit vectors
+#[derive(Debug, Clone)]
+pub struct Perlin {
+    gradients: Vec<Vector3D>,
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>,
+}
+
+impl Perlin {
+    // The same seed always produces the same noise field
+    pub fn new(seed: u64) -> Perlin {
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        let gradients = (0..POINT_COUNT)
+            .map(|_| {
+                Vector3D::new(
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-1.0..1.0),
+                )
+                .normalize_or_zero()
+            })
+            .collect();
+
+        return Perlin {
+            gradients: gradients,
+            perm_x: Perlin::generate_perm(&mut rng),
+            perm_y: Perlin::generate_perm(&mut rng),
+            perm_z: Perlin::generate_perm(&mut rng),
+        };
+    }
+
+    fn generate_perm(rng: &mut StdRng) -> Vec<usize> {
+        let mut perm: Vec<usize> = (0..POINT_COUNT).collect();
+        perm.shuffle(rng);
+        return perm;
+    }
+
+    // Smooth noise value in [-1, 1]
+    pub fn noise(&self, point: &Vector3D) -> f64 {
+        let x = point.get_x().floor();
+        let y = point.get_y().floor();
+        let z = point.get_z().floor();
+
+        let u = point.get_x() - x;
+        let v = point.get_y() - y;
+        let w = point.get_z() - z;
+
+        let i = x as i64;
+        let j = y as i64;
+        let k = z as i64;
+
+        // Gradients at the eight corners of the surrounding lattice cell
+        let mut corners = [[[Vector3D::new(0.0, 0.0, 0.0); 2]; 2]; 2];
+        for (di, plane) in corners.iter_mut().enumerate() {
+            for (dj, row) in plane.iter_mut().enumerate() {
+                for (dk, corner) in row.iter_mut().enumerate() {
+                    let index = self.perm_x[((i + di as i64) & 255) as usize]
+                        ^ self.perm_y[((j + dj as i64) & 255) as usize]
+                        ^ self.perm_z[((k + dk as i64) & 255) as usize];
+                    *corner = self.gradients[index];
+                }
+            }
+        }
+
+        return Perlin::trilinear_interpolate(&corners, u, v, w);
+    }
+
+    // Sum of several octaves of noise, each at double the frequency and half the weight
+    pub fn turbulence(&self, point: &Vector3D, depth: usize) -> f64 {
+        let mut accumulator = 0.0;
+        let mut temp_point = *point;
+        let mut weight = 1.0;
+
+        for _ in 0..depth {
+            accumulator += weight * self.noise(&temp_point);
+            weight *= 0.5;
+            temp_point *= 2.0;
+        }
+
+        return accumulator.abs();
+    }
+
+    fn trilinear_interpolate(corners: &[[[Vector3D; 2]; 2]; 2], u: f64, v: f64, w: f64) -> f64 {
+        // Hermite smoothing removes the grid artifacts of plain linear interpolation
+        let uu = u * u * (3.0 - 2.0 * u);
+        let vv = v * v * (3.0 - 2.0 * v);
+        let ww = w * w * (3.0 - 2.0 * w);
+
+        let mut accumulator = 0.0;
+        for (i, plane) in corners.iter().enumerate() {
+            for (j, row) in plane.iter().enumerate() {
+                for (k, gradient) in row.iter().enumerate() {
+                    let (fi, fj, fk) = (i as f64, j as f64, k as f64);
+                    let weight = Vector3D::new(u - fi, v - fj, w - fk);
+
+                    accumulator += (fi * uu + (1.0 - fi) * (1.0 - uu))
+                        * (fj * vv + (1.0 - fj) * (1.0 - vv))
+                        * (fk * ww + (1.0 - fk) * (1.0 - ww))
+                        * gradient.dot(&weight);
+                }
+            }
+        }
+
+        return accumulator;
+    }
+}
+
+#[test]
+fn test_perlin_bounded() {
+    let perlin = Perlin::new(42);
+
+    for _ in 0..1000 {
+        let point = Vector3D::random(-50.0, 50.0);
+        let noise = perlin.noise(&point);
+        assert!(
+            (-1.0..=1.0).contains(&noise),
+            "Noise {} out of range",
+            noise
+        );
+        assert!(perlin.turbulence(&point, 7) >= 0.0);
+    }
+}
+
+#[test]
+fn test_perlin_continuous() {
+    let perlin = Perlin::new(42);
+    let offset = Vector3D::new(1e-4, 1e-4, 1e-4);
+
+    for _ in 0..1000 {
+        let point = Vector3D::random(-50.0, 50.0);
+        let difference = (perlin.noise(&point) - perlin.noise(&(point + offset))).abs();
+        assert!(difference < 1e-2, "Noise jumped by {}", difference);
+    }
+}
+
+#[test]
+fn test_perlin_deterministic() {
+    let point = Vector3D::new(1.3, -2.7, 4.1);
+    assert_eq!(Perlin::new(7).noise(&point), Perlin::new(7).noise(&point));
+}
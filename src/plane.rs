@@ -0,0 +1,184 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::aabb::Aabb;
+use crate::material::Material;
+use crate::object::{Object, ObjectHitRecord};
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::vector_3d::Vector3D;
+
+#[cfg(test)]
+use crate::material::Lambertian;
+#[cfg(test)]
+use crate::texture::{CheckerTexture, Texture};
+#[cfg(test)]
+use assert_approx_eq::assert_approx_eq;
+#[cfg(test)]
+use palette::Srgb;
+
+// Scene files only list the point, normal and material, the basis is rebuilt from the normal
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(from = "PlaneConfig")]
+pub struct Plane {
+    point: Vector3D,  // Any point lying on the plane
+    normal: Vector3D, // Unit normal of the plane
+    #[serde(skip_serializing)]
+    basis: Onb, // Tangents spanning the plane, the directions of increasing u and v
+    material: Arc<Material>,
+}
+
+#[derive(Deserialize)]
+struct PlaneConfig {
+    point: Vector3D,
+    normal: Vector3D,
+    material: Arc<Material>,
+}
+
+impl From<PlaneConfig> for Plane {
+    fn from(config: PlaneConfig) -> Self {
+        Plane::new(config.point, config.normal, config.material)
+    }
+}
+
+impl Plane {
+    pub fn new(point: Vector3D, normal: Vector3D, material: impl Into<Arc<Material>>) -> Plane {
+        return Plane {
+            point: point,
+            normal: normal.unit_vector(),
+            basis: Onb::new(&normal),
+            material: material.into(),
+        };
+    }
+}
+
+impl Object for Plane {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<ObjectHitRecord> {
+        let denominator = ray.direction.dot(&self.normal);
+
+        // A ray (nearly) parallel to the plane never intersects it
+        if denominator.abs() < 1e-8 {
+            return None;
+        }
+
+        // Solve (origin + t * direction - point) . normal = 0 for t
+        let t = (self.point - ray.origin).dot(&self.normal) / denominator;
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        // The ray hits the front face if it travels against the plane normal
+        let front_face = denominator < 0.0;
+
+        // Surface coordinates are distances along the tangents from the plane's point,
+        // so u and v grow by one per world unit and tiling textures repeat across the plane
+        let point = ray.at(t);
+        let offset = point - self.point;
+
+        return Some(ObjectHitRecord {
+            t: t,
+            point: point,
+            normal: if front_face {
+                self.normal
+            } else {
+                -self.normal
+            },
+            front_face: front_face,
+            material: self.material.clone(),
+            u: offset.dot(&self.basis.u()),
+            v: offset.dot(&self.basis.v()),
+            tangent: self.basis.u(),
+            bitangent: self.basis.v(),
+        });
+    }
+
+    fn material(&self) -> Option<&Material> {
+        return Some(self.material.as_ref());
+    }
+
+    fn set_material(&mut self, material: Arc<Material>) -> bool {
+        self.material = material;
+        return true;
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        // An infinite plane cannot be bounded
+        return None;
+    }
+}
+
+#[cfg(test)]
+fn test_plane() -> Plane {
+    Plane::new(
+        Vector3D::new(0.0, 0.0, 0.0),
+        Vector3D::new(0.0, 1.0, 0.0),
+        Material::Lambertian(Lambertian::new(Srgb::new(0.5, 0.5, 0.5))),
+    )
+}
+
+#[test]
+fn test_plane_hit_head_on() {
+    let plane = test_plane();
+    let ray = Ray::new(Vector3D::new(0.0, 2.0, 0.0), Vector3D::new(0.0, -1.0, 0.0));
+
+    let hit = plane
+        .hit(&ray, 0.001, f64::MAX)
+        .expect("Ray should hit the plane");
+
+    assert_approx_eq!(hit.t, 2.0);
+    assert_approx_eq!(hit.point.get_y(), 0.0);
+    assert_approx_eq!(hit.normal.get_y(), 1.0);
+    assert!(hit.front_face);
+}
+
+#[test]
+fn test_plane_parallel_miss() {
+    let plane = test_plane();
+    let ray = Ray::new(Vector3D::new(0.0, 1.0, 0.0), Vector3D::new(1.0, 0.0, 0.0));
+
+    assert!(plane.hit(&ray, 0.001, f64::MAX).is_none());
+}
+
+#[test]
+fn test_plane_hit_from_behind() {
+    let plane = test_plane();
+    let ray = Ray::new(Vector3D::new(0.0, -3.0, 0.0), Vector3D::new(0.0, 1.0, 0.0));
+
+    let hit = plane
+        .hit(&ray, 0.001, f64::MAX)
+        .expect("Ray should hit the plane");
+
+    assert_approx_eq!(hit.t, 3.0);
+    assert_approx_eq!(hit.normal.get_y(), -1.0);
+    assert!(!hit.front_face);
+}
+
+#[test]
+fn test_plane_unbounded() {
+    assert!(test_plane().bounding_box().is_none());
+}
+
+#[test]
+fn test_plane_checker_texture() {
+    let plane = test_plane();
+    let checker = CheckerTexture::new(Srgb::new(1.0, 1.0, 1.0), Srgb::new(0.0, 0.0, 0.0), 1.0);
+
+    // Step one unit at a time along x across the floor, which crosses one checker square each time
+    let colors: Vec<f32> = (0..6)
+        .map(|i| {
+            let ray = Ray::new(
+                Vector3D::new(i as f64 + 0.3, 1.0, 0.4),
+                Vector3D::new(0.0, -1.0, 0.0),
+            );
+            let hit = plane
+                .hit(&ray, 0.001, f64::MAX)
+                .expect("Ray should hit the plane");
+            checker.value(hit.u, hit.v, &hit.point).red
+        })
+        .collect();
+
+    for pair in colors.windows(2) {
+        assert_ne!(pair[0], pair[1], "Neighboring squares match: {:?}", colors);
+    }
+}
@@ -0,0 +1,111 @@
+use serde::{Deserialize, Serialize};
+
+use crate::object::Object;
+use crate::plane::Plane;
+use crate::sphere::Sphere;
+
+#[cfg(test)]
+use crate::material::{Lambertian, Material, Metal};
+#[cfg(test)]
+use crate::ray::Ray;
+#[cfg(test)]
+use crate::vector_3d::Vector3D;
+#[cfg(test)]
+use palette::Srgb;
+
+// Every primitive a scene file can list, told apart by a "type" field, e.g.
+// { "type": "Sphere", "center": [0.0, 1.0, 0.0], "radius": 0.5, "material": { ... } }
+// Primitives are added here as they gain serde support
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(tag = "type")]
+pub enum Primitive {
+    Sphere(Sphere),
+    Plane(Plane),
+}
+
+impl Primitive {
+    // The primitive as an object ready to be added to a World
+    pub fn into_object(self) -> Box<dyn Object> {
+        match self {
+            Primitive::Sphere(sphere) => Box::new(sphere),
+            Primitive::Plane(plane) => Box::new(plane),
+        }
+    }
+}
+
+#[test]
+fn test_sphere_serde_round_trip() {
+    let sphere = Sphere::new(
+        Vector3D::new(1.0, -2.0, 3.5),
+        0.75,
+        Material::Metal(Metal::new(Srgb::new(0.8, 0.6, 0.2), 0.1)),
+    );
+
+    let json = serde_json::to_string(&sphere).unwrap();
+    let loaded: Sphere = serde_json::from_str(&json).unwrap();
+    assert_eq!(loaded.center(), sphere.center());
+    assert_eq!(loaded.radius(), sphere.radius());
+    match loaded.material() {
+        Material::Metal(metal) => {
+            assert_eq!(metal.albedo, Srgb::new(0.8, 0.6, 0.2));
+            assert_eq!(metal.roughness, 0.1);
+        }
+        _ => panic!("Expected a metal sphere"),
+    }
+
+    // Nothing is lost, so writing it out again gives the same file
+    assert_eq!(serde_json::to_string(&loaded).unwrap(), json);
+}
+
+#[test]
+fn test_primitive_list() {
+    let json = r#"[
+        { "type": "Sphere", "center": [0.0, -100.5, -1.0], "radius": 100.0,
+          "material": { "Lambertian": { "albedo": [0.5, 0.5, 0.5] } } },
+        { "type": "Sphere", "center": [0.0, 0.0, -1.0], "radius": 0.5,
+          "material": { "Metal": { "albedo": [0.8, 0.8, 0.8], "roughness": 0.0 } } },
+        { "type": "Plane", "point": [0.0, 0.0, -5.0], "normal": [0.0, 0.0, 2.0],
+          "material": { "Lambertian": { "albedo": [0.2, 0.3, 0.4] } } }
+    ]"#;
+    let primitives: Vec<Primitive> = serde_json::from_str(json).unwrap();
+    assert_eq!(primitives.len(), 3);
+
+    match &primitives[0] {
+        Primitive::Sphere(ground) => {
+            assert_eq!(ground.radius(), 100.0);
+            assert!(matches!(ground.material(), Material::Lambertian(_)));
+        }
+        _ => panic!("Expected a sphere first"),
+    }
+    assert!(matches!(primitives[2], Primitive::Plane(_)));
+
+    // Each one becomes an object that can be hit, the plane behind the small sphere
+    let objects: Vec<Box<dyn Object>> =
+        primitives.into_iter().map(Primitive::into_object).collect();
+    let ray = Ray::new(Vector3D::new(0.0, 0.0, 0.0), Vector3D::new(0.0, 0.0, -1.0));
+    assert!(objects[1].hit(&ray, 0.001, f64::MAX).is_some());
+    let hit_record = objects[2].hit(&ray, 0.001, f64::MAX).unwrap();
+    assert_eq!(hit_record.t, 5.0);
+    assert_eq!(hit_record.normal.get_z(), 1.0);
+
+    // The tag is written back out
+    let sphere = Sphere::new(
+        Vector3D::new(0.0, 0.0, 0.0),
+        1.0,
+        Material::Lambertian(Lambertian::new(Srgb::new(0.1, 0.2, 0.3))),
+    );
+    let json = serde_json::to_string(&Primitive::Sphere(sphere)).unwrap();
+    assert!(json.starts_with(r#"{"type":"Sphere","#), "{}", json);
+
+    // A plane writes only what it is built from and comes back the same
+    let plane = Plane::new(
+        Vector3D::new(0.0, -1.0, 0.0),
+        Vector3D::new(0.0, 1.0, 0.0),
+        Material::Lambertian(Lambertian::new(Srgb::new(0.1, 0.2, 0.3))),
+    );
+    let json = serde_json::to_string(&Primitive::Plane(plane)).unwrap();
+    assert!(json.starts_with(r#"{"type":"Plane","#), "{}", json);
+    assert!(!json.contains("basis"), "{}", json);
+    let loaded: Primitive = serde_json::from_str(&json).unwrap();
+    assert_eq!(serde_json::to_string(&loaded).unwrap(), json);
+}
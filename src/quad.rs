@@ -0,0 +1,183 @@
+use std::sync::Arc;
+
+use rand::Rng;
+
+use crate::aabb::Aabb;
+use crate::material::Material;
+use crate::object::{Object, ObjectHitRecord, area_pdf_value};
+use crate::ray::{Ray, T_MIN};
+use crate::vector_3d::Vector3D;
+
+#[cfg(test)]
+use crate::material::Lambertian;
+#[cfg(test)]
+use assert_approx_eq::assert_approx_eq;
+#[cfg(test)]
+use palette::Srgb;
+
+// Parallelogram with corner q and edges u and v, covering q + alpha * u + beta * v
+// for alpha and beta in [0, 1]
+#[derive(Clone)]
+pub struct Quad {
+    q: Vector3D,
+    u: Vector3D,
+    v: Vector3D,
+    normal: Vector3D, // Unit normal along u x v
+    w: Vector3D,      // (u x v) / |u x v|^2, recovers alpha and beta from a point on the plane
+    material: Arc<Material>,
+}
+
+impl Quad {
+    pub fn new(q: Vector3D, u: Vector3D, v: Vector3D, material: impl Into<Arc<Material>>) -> Quad {
+        let n = u.cross(&v);
+
+        return Quad {
+            q: q,
+            u: u,
+            v: v,
+            normal: n.unit_vector(),
+            w: n / n.length_squared(),
+            material: material.into(),
+        };
+    }
+
+    pub fn area(&self) -> f64 {
+        return self.u.cross(&self.v).length();
+    }
+}
+
+impl Object for Quad {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<ObjectHitRecord> {
+        let denominator = ray.direction.dot(&self.normal);
+
+        // A ray (nearly) parallel to the quad never intersects it
+        if denominator.abs() < 1e-8 {
+            return None;
+        }
+
+        // Intersect the plane containing the quad
+        let t = (self.q - ray.origin).dot(&self.normal) / denominator;
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        // Then express the hit in the quad's own (alpha, beta) coordinates
+        let intersect_point = ray.at(t);
+        let planar = intersect_point - self.q;
+        let alpha = self.w.dot(&planar.cross(&self.v));
+        let beta = self.w.dot(&self.u.cross(&planar));
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return None;
+        }
+
+        let front_face = denominator < 0.0;
+
+        return Some(ObjectHitRecord {
+            t: t,
+            point: intersect_point,
+            normal: if front_face {
+                self.normal
+            } else {
+                -self.normal
+            },
+            front_face: front_face,
+            material: self.material.clone(),
+            u: alpha,
+            v: beta,
+            tangent: self.u,
+            bitangent: self.v,
+        });
+    }
+
+    fn material(&self) -> Option<&Material> {
+        return Some(self.material.as_ref());
+    }
+
+    fn set_material(&mut self, material: Arc<Material>) -> bool {
+        self.material = material;
+        return true;
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let corners = [
+            self.q,
+            self.q + self.u,
+            self.q + self.v,
+            self.q + self.u + self.v,
+        ];
+
+        let mut min = corners[0];
+        let mut max = corners[0];
+        for corner in corners.iter() {
+            min = min.component_min(corner);
+            max = max.component_max(corner);
+        }
+
+        // Pad the box so axis-aligned quads do not produce a flat box
+        let padding = Vector3D::new(0.0001, 0.0001, 0.0001);
+        return Some(Aabb::new(min - padding, max + padding));
+    }
+
+    fn random_direction(&self, origin: &Vector3D) -> Option<Vector3D> {
+        let mut rng = rand::thread_rng();
+        let point = self.q + self.u * rng.r#gen::<f64>() + self.v * rng.r#gen::<f64>();
+        return Some(point - *origin);
+    }
+
+    fn pdf_value(&self, origin: &Vector3D, direction: &Vector3D) -> f64 {
+        let hit_record = match self.hit(&Ray::new(*origin, *direction), T_MIN, f64::MAX) {
+            Some(hit_record) => hit_record,
+            None => return 0.0,
+        };
+
+        return area_pdf_value(&hit_record, direction, self.area());
+    }
+}
+
+#[cfg(test)]
+fn test_quad() -> Quad {
+    // Slanted 2 x 1 quad facing +z and tilted around the x axis
+    Quad::new(
+        Vector3D::new(-1.0, -0.5, -2.0),
+        Vector3D::new(2.0, 0.0, 0.0),
+        Vector3D::new(0.0, 1.0, -0.5),
+        Material::Lambertian(Lambertian::new(Srgb::new(0.5, 0.5, 0.5))),
+    )
+}
+
+#[test]
+fn test_quad_hit_interior() {
+    let quad = test_quad();
+    let ray = Ray::new(Vector3D::new(0.5, 0.0, 0.0), Vector3D::new(0.0, 0.0, -1.0));
+
+    let hit = quad
+        .hit(&ray, 0.001, f64::MAX)
+        .expect("Ray should hit the quad");
+
+    assert_approx_eq!(hit.t, 2.25);
+    assert_approx_eq!(hit.u, 0.75);
+    assert_approx_eq!(hit.v, 0.5);
+    assert!(hit.front_face);
+
+    let expected_normal = Vector3D::new(0.0, 0.5, 1.0).unit_vector();
+    assert!(hit.normal.approx_eq(&expected_normal, 1e-9));
+}
+
+#[test]
+fn test_quad_miss_past_edge() {
+    let quad = test_quad();
+
+    // Just past the alpha = 1 edge at x = 1
+    let ray = Ray::new(
+        Vector3D::new(1.001, 0.0, 0.0),
+        Vector3D::new(0.0, 0.0, -1.0),
+    );
+    assert!(quad.hit(&ray, 0.001, f64::MAX).is_none());
+
+    // Just inside it
+    let ray = Ray::new(
+        Vector3D::new(0.999, 0.0, 0.0),
+        Vector3D::new(0.0, 0.0, -1.0),
+    );
+    assert!(quad.hit(&ray, 0.001, f64::MAX).is_some());
+}
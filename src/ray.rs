@@ -1,7 +1,24 @@
+use std::fmt;
+
 use crate::vector_3d::Vector3D;
 
+#[cfg(test)]
+use crate::material::{Lambertian, Material};
+#[cfg(test)]
+use crate::object::Object;
+#[cfg(test)]
+use crate::plane::Plane;
 #[cfg(test)]
 use assert_approx_eq::assert_approx_eq;
+#[cfg(test)]
+use palette::Srgb;
+
+// Distance new rays are pushed off the surface they start on, so they do not hit it again
+pub const SURFACE_EPSILON: f64 = 1e-4;
+
+// Nearest t a ray can hit anything at. Rays leaving a surface are already pushed off it by
+// SURFACE_EPSILON, this only keeps hits from landing right on a ray's origin
+pub const T_MIN: f64 = 1e-3;
 
 #[derive(Debug, Clone, Copy)]
 pub struct Ray {
@@ -17,6 +34,27 @@ impl Ray {
     pub fn at(&self, t: f64) -> Vector3D {
         self.origin + self.direction * t
     }
+
+    // Copy of the ray with the origin moved epsilon along the normal, towards the side the
+    // ray travels to, so rounding errors cannot make it hit the surface it starts on
+    pub fn offset_origin(&self, normal: &Vector3D, epsilon: f64) -> Ray {
+        let offset = if self.direction.dot(normal) >= 0.0 {
+            normal * epsilon
+        } else {
+            normal * -epsilon
+        };
+
+        Ray::new(self.origin + offset, self.direction)
+    }
+}
+
+// Prints origin -> direction, e.g. (0, 0, 0) -> (0, 0, -1)
+impl fmt::Display for Ray {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.origin, f)?;
+        write!(f, " -> ")?;
+        fmt::Display::fmt(&self.direction, f)
+    }
 }
 
 #[test]
@@ -46,3 +84,34 @@ fn test_ray_at() {
     assert_approx_eq!(s.get_y(), 1.0);
     assert_approx_eq!(s.get_z(), 1.5);
 }
+
+#[test]
+fn test_ray_offset_origin() {
+    let plane = Plane::new(
+        Vector3D::new(0.0, 0.0, 0.0),
+        Vector3D::new(0.0, 1.0, 0.0),
+        Material::Lambertian(Lambertian::new(Srgb::new(0.5, 0.5, 0.5))),
+    );
+    let normal = Vector3D::new(0.0, 1.0, 0.0);
+
+    // A ray starting exactly on the plane hits it again at t = 0
+    let ray = Ray::new(Vector3D::new(0.0, 0.0, 0.0), Vector3D::new(1.0, 1.0, 0.0));
+    assert!(plane.hit(&ray, 0.0, f64::MAX).is_some());
+
+    let offset = ray.offset_origin(&normal, SURFACE_EPSILON);
+    assert_approx_eq!(offset.origin.get_y(), SURFACE_EPSILON);
+    assert!(plane.hit(&offset, 0.0, f64::MAX).is_none());
+
+    // Rays heading into the surface are pushed to the other side
+    let ray = Ray::new(Vector3D::new(0.0, 0.0, 0.0), Vector3D::new(1.0, -1.0, 0.0));
+    let offset = ray.offset_origin(&normal, SURFACE_EPSILON);
+    assert_approx_eq!(offset.origin.get_y(), -SURFACE_EPSILON);
+    assert!(plane.hit(&offset, 0.0, f64::MAX).is_none());
+}
+
+#[test]
+fn test_ray_display() {
+    let ray = Ray::new(Vector3D::new(0.0, 1.0, 0.0), Vector3D::new(0.0, 0.0, -1.0));
+    assert_eq!(ray.to_string(), "(0, 1, 0) -> (0, 0, -1)");
+    assert_eq!(format!("{:.1}", ray), "(0.0, 1.0, 0.0) -> (0.0, 0.0, -1.0)");
+}
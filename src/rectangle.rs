@@ -0,0 +1,214 @@
+use std::sync::Arc;
+
+use rand::Rng;
+
+use crate::aabb::Aabb;
+use crate::material::Material;
+use crate::object::{Object, ObjectHitRecord, area_pdf_value};
+use crate::ray::{Ray, T_MIN};
+use crate::vector_3d::Vector3D;
+
+#[cfg(test)]
+use crate::material::Lambertian;
+#[cfg(test)]
+use assert_approx_eq::assert_approx_eq;
+#[cfg(test)]
+use palette::Srgb;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl Axis {
+    pub fn index(&self) -> usize {
+        match self {
+            Axis::X => 0,
+            Axis::Y => 1,
+            Axis::Z => 2,
+        }
+    }
+
+    // The two axes spanning a plane perpendicular to this axis
+    fn plane_axes(&self) -> (usize, usize) {
+        match self {
+            Axis::X => (1, 2),
+            Axis::Y => (0, 2),
+            Axis::Z => (0, 1),
+        }
+    }
+}
+
+// Rectangle perpendicular to one of the coordinate axes
+// For Axis::Z the rectangle spans x in [a0, a1] and y in [b0, b1] at z = k,
+// for Axis::Y it spans x and z, and for Axis::X it spans y and z
+#[derive(Clone)]
+pub struct AxisAlignedRect {
+    axis: Axis,
+    a0: f64,
+    a1: f64,
+    b0: f64,
+    b1: f64,
+    k: f64,
+    normal: Vector3D, // Points along +axis unless flipped
+    material: Arc<Material>,
+}
+
+impl AxisAlignedRect {
+    pub fn new(
+        axis: Axis,
+        a0: f64,
+        a1: f64,
+        b0: f64,
+        b1: f64,
+        k: f64,
+        material: impl Into<Arc<Material>>,
+    ) -> AxisAlignedRect {
+        let mut normal = Vector3D::new(0.0, 0.0, 0.0);
+        normal[axis.index()] = 1.0;
+
+        return AxisAlignedRect {
+            axis: axis,
+            a0: a0,
+            a1: a1,
+            b0: b0,
+            b1: b1,
+            k: k,
+            normal: normal,
+            material: material.into(),
+        };
+    }
+
+    // Make the outward normal point along -axis instead (e.g. the bottom face of a box)
+    pub fn flipped(mut self) -> AxisAlignedRect {
+        self.normal = -self.normal;
+        return self;
+    }
+
+    pub fn area(&self) -> f64 {
+        return (self.a1 - self.a0) * (self.b1 - self.b0);
+    }
+}
+
+impl Object for AxisAlignedRect {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<ObjectHitRecord> {
+        let axis = self.axis.index();
+        let (a_axis, b_axis) = self.axis.plane_axes();
+
+        // A ray (nearly) parallel to the rectangle never intersects it
+        if ray.direction[axis].abs() < 1e-8 {
+            return None;
+        }
+
+        let t = (self.k - ray.origin[axis]) / ray.direction[axis];
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        let intersect_point = ray.at(t);
+        let a = intersect_point[a_axis];
+        let b = intersect_point[b_axis];
+        if a < self.a0 || a > self.a1 || b < self.b0 || b > self.b1 {
+            return None;
+        }
+
+        let front_face = ray.direction.dot(&self.normal) < 0.0;
+
+        let mut tangent = Vector3D::new(0.0, 0.0, 0.0);
+        tangent[a_axis] = self.a1 - self.a0;
+        let mut bitangent = Vector3D::new(0.0, 0.0, 0.0);
+        bitangent[b_axis] = self.b1 - self.b0;
+
+        return Some(ObjectHitRecord {
+            t: t,
+            point: intersect_point,
+            normal: if front_face {
+                self.normal
+            } else {
+                -self.normal
+            },
+            front_face: front_face,
+            material: self.material.clone(),
+            u: (a - self.a0) / (self.a1 - self.a0),
+            v: (b - self.b0) / (self.b1 - self.b0),
+            tangent: tangent,
+            bitangent: bitangent,
+        });
+    }
+
+    fn material(&self) -> Option<&Material> {
+        return Some(self.material.as_ref());
+    }
+
+    fn set_material(&mut self, material: Arc<Material>) -> bool {
+        self.material = material;
+        return true;
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let axis = self.axis.index();
+        let (a_axis, b_axis) = self.axis.plane_axes();
+
+        // Pad the flat axis slightly so the box never has zero thickness
+        let mut min = Vector3D::new(0.0, 0.0, 0.0);
+        let mut max = Vector3D::new(0.0, 0.0, 0.0);
+        min[axis] = self.k - 0.0001;
+        max[axis] = self.k + 0.0001;
+        min[a_axis] = self.a0;
+        max[a_axis] = self.a1;
+        min[b_axis] = self.b0;
+        max[b_axis] = self.b1;
+
+        return Some(Aabb::new(min, max));
+    }
+
+    fn random_direction(&self, origin: &Vector3D) -> Option<Vector3D> {
+        let (a_axis, b_axis) = self.axis.plane_axes();
+        let mut rng = rand::thread_rng();
+
+        let mut point = Vector3D::new(0.0, 0.0, 0.0);
+        point[self.axis.index()] = self.k;
+        point[a_axis] = rng.gen_range(self.a0..self.a1);
+        point[b_axis] = rng.gen_range(self.b0..self.b1);
+
+        return Some(point - *origin);
+    }
+
+    fn pdf_value(&self, origin: &Vector3D, direction: &Vector3D) -> f64 {
+        let hit_record = match self.hit(&Ray::new(*origin, *direction), T_MIN, f64::MAX) {
+            Some(hit_record) => hit_record,
+            None => return 0.0,
+        };
+
+        return area_pdf_value(&hit_record, direction, self.area());
+    }
+}
+
+#[test]
+fn test_axis_aligned_rect() {
+    let rect = AxisAlignedRect::new(
+        Axis::Y,
+        -1.0,
+        1.0,
+        -2.0,
+        2.0,
+        0.5,
+        Material::Lambertian(Lambertian::new(Srgb::new(0.5, 0.5, 0.5))),
+    );
+
+    let ray = Ray::new(Vector3D::new(0.5, 3.0, 1.0), Vector3D::new(0.0, -1.0, 0.0));
+    let hit = rect
+        .hit(&ray, 0.001, f64::MAX)
+        .expect("Ray should hit the rectangle");
+
+    assert_approx_eq!(hit.t, 2.5);
+    assert_approx_eq!(hit.normal.get_y(), 1.0);
+    assert_approx_eq!(hit.u, 0.75);
+    assert_approx_eq!(hit.v, 0.75);
+
+    // Outside the x extent of the rectangle
+    let ray = Ray::new(Vector3D::new(1.5, 3.0, 0.0), Vector3D::new(0.0, -1.0, 0.0));
+    assert!(rect.hit(&ray, 0.001, f64::MAX).is_none());
+}
@@ -0,0 +1,181 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+// One camera sample, both halves in [0, 1) x [0, 1)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sample {
+    pub pixel: (f64, f64), // Offset within the pixel
+    pub lens: (f64, f64),  // Point on the lens, mapped onto the aperture by BokehShape::map_sample
+}
+
+// Correlated multi-jittered samples (Kensler, 2013) for one pixel. The pixel and lens
+// offsets each fall into their own cell of an m x n grid while also covering every one of
+// the finer column and row strata exactly once, and the lens pattern is shuffled against
+// the pixel pattern so the two pairs of dimensions are not correlated with each other
+#[derive(Debug, Clone)]
+pub struct SampleGenerator {
+    samples: Vec<Sample>,
+}
+
+impl SampleGenerator {
+    // The same seed always produces the same samples. The grid is the most square
+    // m x n with m * n >= sample_count, so counts like 4, 8, 12 or 16 fill it exactly
+    pub fn new(sample_count: usize, seed: u64) -> SampleGenerator {
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        let pixel = SampleGenerator::pattern(sample_count, &mut rng);
+        let mut lens = SampleGenerator::pattern(sample_count, &mut rng);
+        lens.shuffle(&mut rng);
+
+        let samples = pixel
+            .into_iter()
+            .zip(lens)
+            .map(|(pixel, lens)| Sample {
+                pixel: pixel,
+                lens: lens,
+            })
+            .collect();
+
+        return SampleGenerator { samples: samples };
+    }
+
+    pub fn samples(&self) -> &[Sample] {
+        return &self.samples;
+    }
+
+    pub fn sample_count(&self) -> usize {
+        return self.samples.len();
+    }
+
+    // Grid dimensions used for sample_count samples, m columns by n rows
+    pub fn grid_size(sample_count: usize) -> (usize, usize) {
+        let m = ((sample_count as f64).sqrt() as usize).max(1);
+        let n = sample_count.div_ceil(m).max(1);
+        return (m, n);
+    }
+
+    // Sample (i, j) sits in column i and row j of the grid. Within its cell it is offset by
+    // a permuted row index horizontally and a permuted column index vertically, so the
+    // samples also form a Latin hypercube over m * n strata on each axis. Using one
+    // permutation for every column (and one for every row) is what makes it correlated.
+    // When sample_count does not fill the grid, the whole grid is generated and the spare
+    // cells are dropped at random, so no part of the pixel is left out every time
+    fn pattern<R: Rng>(sample_count: usize, rng: &mut R) -> Vec<(f64, f64)> {
+        let (m, n) = SampleGenerator::grid_size(sample_count);
+
+        let mut column_shuffle: Vec<usize> = (0..m).collect();
+        let mut row_shuffle: Vec<usize> = (0..n).collect();
+        column_shuffle.shuffle(rng);
+        row_shuffle.shuffle(rng);
+
+        let mut points: Vec<(f64, f64)> = (0..m * n)
+            .map(|s| {
+                let i = s % m;
+                let j = s / m;
+                let x =
+                    (i as f64 + (row_shuffle[j] as f64 + rng.r#gen::<f64>()) / n as f64) / m as f64;
+                let y = (j as f64 + (column_shuffle[i] as f64 + rng.r#gen::<f64>()) / m as f64)
+                    / n as f64;
+                (x, y)
+            })
+            .collect();
+
+        // Fewer than m cells are spare. Dropping them from distinct columns and rows, which
+        // the shuffles above pick at random, keeps every column and row of the grid sampled
+        let spare = m * n - sample_count;
+        let mut dropped: Vec<usize> = (0..spare)
+            .map(|k| row_shuffle[k] * m + column_shuffle[k])
+            .collect();
+        dropped.sort_unstable();
+        for s in dropped.into_iter().rev() {
+            points.remove(s);
+        }
+
+        return points;
+    }
+}
+
+#[cfg(test)]
+fn count_strata(values: &[f64], strata: usize) -> Vec<usize> {
+    let mut counts = vec![0; strata];
+    for value in values {
+        assert!((0.0..1.0).contains(value), "{} is outside [0, 1)", value);
+        counts[(value * strata as f64) as usize] += 1;
+    }
+    return counts;
+}
+
+#[test]
+fn test_sample_generator_strata() {
+    // Square and rectangular grids
+    for (sample_count, m, n) in [(16, 4, 4), (8, 2, 4), (9, 3, 3)] {
+        assert_eq!(SampleGenerator::grid_size(sample_count), (m, n));
+
+        for seed in 0..10 {
+            let generator = SampleGenerator::new(sample_count, seed);
+            assert_eq!(generator.sample_count(), sample_count);
+
+            for half in [|s: &Sample| s.pixel, |s: &Sample| s.lens] {
+                let points: Vec<(f64, f64)> = generator.samples().iter().map(half).collect();
+                let xs: Vec<f64> = points.iter().map(|p| p.0).collect();
+                let ys: Vec<f64> = points.iter().map(|p| p.1).collect();
+
+                // Every fine stratum on each axis exactly once
+                assert!(count_strata(&xs, sample_count).iter().all(|&c| c == 1));
+                assert!(count_strata(&ys, sample_count).iter().all(|&c| c == 1));
+
+                // And every cell of the m x n grid exactly once
+                let mut cells = vec![0; m * n];
+                for (x, y) in &points {
+                    cells[(y * n as f64) as usize * m + (x * m as f64) as usize] += 1;
+                }
+                assert!(cells.iter().all(|&c| c == 1), "{:?}", cells);
+            }
+        }
+    }
+
+    // Counts that leave spare cells in the grid, including primes
+    for (sample_count, m, n) in [(10, 3, 4), (7, 2, 4), (13, 3, 5), (5, 2, 3)] {
+        assert_eq!(SampleGenerator::grid_size(sample_count), (m, n));
+
+        let mut covered = vec![false; m * n];
+        for seed in 0..50 {
+            let generator = SampleGenerator::new(sample_count, seed);
+            assert_eq!(generator.sample_count(), sample_count);
+
+            for half in [|s: &Sample| s.pixel, |s: &Sample| s.lens] {
+                let points: Vec<(f64, f64)> = generator.samples().iter().map(half).collect();
+                let xs: Vec<f64> = points.iter().map(|p| p.0).collect();
+                let ys: Vec<f64> = points.iter().map(|p| p.1).collect();
+
+                // Every column and row of the grid is hit, no fine stratum more than once
+                assert!(count_strata(&xs, m).iter().all(|&c| c > 0));
+                assert!(count_strata(&ys, n).iter().all(|&c| c > 0));
+                assert!(count_strata(&xs, m * n).iter().all(|&c| c <= 1));
+                assert!(count_strata(&ys, m * n).iter().all(|&c| c <= 1));
+
+                for (x, y) in &points {
+                    covered[(y * n as f64) as usize * m + (x * m as f64) as usize] = true;
+                }
+            }
+        }
+
+        // The spare cells move around, so no part of the pixel is always skipped
+        assert!(covered.iter().all(|&c| c), "{:?}", covered);
+    }
+}
+
+#[test]
+fn test_sample_generator_deterministic() {
+    let first = SampleGenerator::new(16, 3);
+    let second = SampleGenerator::new(16, 3);
+    assert_eq!(first.samples(), second.samples());
+
+    let other = SampleGenerator::new(16, 4);
+    assert_ne!(first.samples(), other.samples());
+
+    // A sample count without an exact grid still gives that many samples
+    assert_eq!(SampleGenerator::new(10, 0).sample_count(), 10);
+    assert_eq!(SampleGenerator::new(1, 0).sample_count(), 1);
+}
@@ -0,0 +1,218 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::material::Material;
+use crate::object::{Object, ObjectHitRecord};
+use crate::ray::Ray;
+use crate::vector_3d::Vector3D;
+
+#[cfg(test)]
+use crate::material::Lambertian;
+#[cfg(test)]
+use crate::sphere::Sphere;
+#[cfg(test)]
+use assert_approx_eq::assert_approx_eq;
+#[cfg(test)]
+use palette::Srgb;
+
+// Steps taken along a ray before giving up on finding the surface
+const DEFAULT_MAX_STEPS: usize = 256;
+
+// Distance to the surface that counts as a hit. Below SURFACE_EPSILON, so rays leaving
+// the surface start outside this band and do not hit the surface they start on
+const DEFAULT_EPSILON: f64 = 1e-5;
+
+// Signed distance from a point to the surface, negative inside
+pub type SignedDistance = dyn Fn(Vector3D) -> f64 + Send + Sync;
+
+// Implicit surface given by a signed distance function, found by sphere tracing: step along
+// the ray by the distance to the surface, which can never overshoot it, until close enough
+#[derive(Clone)]
+pub struct SdfObject {
+    sdf: Arc<SignedDistance>, // Arc rather than Box so the object can be cloned
+    material: Arc<Material>,
+    max_steps: usize,
+    epsilon: f64,
+    bounds: Option<Aabb>, // Limits marching to the box, None for unbounded surfaces
+}
+
+impl SdfObject {
+    // The distance function must never overestimate the distance, or rays step through
+    // the surface. Underestimating only costs extra steps
+    pub fn new(
+        sdf: impl Fn(Vector3D) -> f64 + Send + Sync + 'static,
+        material: impl Into<Arc<Material>>,
+    ) -> SdfObject {
+        return SdfObject {
+            sdf: Arc::new(sdf),
+            material: material.into(),
+            max_steps: DEFAULT_MAX_STEPS,
+            epsilon: DEFAULT_EPSILON,
+            bounds: None,
+        };
+    }
+
+    pub fn with_max_steps(mut self, max_steps: usize) -> SdfObject {
+        self.max_steps = max_steps;
+        return self;
+    }
+
+    pub fn with_epsilon(mut self, epsilon: f64) -> SdfObject {
+        self.epsilon = epsilon;
+        return self;
+    }
+
+    // Box the whole surface fits in. Rays are only marched inside it, and it lets
+    // acceleration structures skip the object
+    pub fn with_bounds(mut self, bounds: Aabb) -> SdfObject {
+        self.bounds = Some(bounds);
+        return self;
+    }
+
+    pub fn distance(&self, point: Vector3D) -> f64 {
+        return (self.sdf)(point);
+    }
+
+    // Gradient of the distance by central differences, which points away from the surface,
+    // or the zero vector where the distance has no slope
+    pub fn normal(&self, point: Vector3D) -> Vector3D {
+        let mut gradient = Vector3D::new(0.0, 0.0, 0.0);
+        for axis in 0..3 {
+            let mut step = Vector3D::new(0.0, 0.0, 0.0);
+            step[axis] = self.epsilon;
+            gradient[axis] = self.distance(point + step) - self.distance(point - step);
+        }
+        return gradient.normalize_or_zero();
+    }
+
+    fn hit_record(&self, ray: &Ray, t: f64) -> ObjectHitRecord {
+        let point = ray.at(t);
+        let mut normal = self.normal(point);
+        if normal.near_zero() {
+            // Face the ray where there is no slope to take a normal from
+            normal = -ray.direction.unit_vector();
+        }
+        let front_face = ray.direction.dot(&normal) < 0.0;
+
+        // There is no parameterization of the surface to take texture coordinates from
+        return ObjectHitRecord {
+            t: t,
+            point: point,
+            normal: if front_face { normal } else { -normal },
+            front_face: front_face,
+            material: self.material.clone(),
+            u: 0.0,
+            v: 0.0,
+            tangent: Vector3D::new(0.0, 0.0, 0.0),
+            bitangent: Vector3D::new(0.0, 0.0, 0.0),
+        };
+    }
+}
+
+impl Object for SdfObject {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<ObjectHitRecord> {
+        let (t_start, t_end) = match self.bounds {
+            Some(bounds) => bounds.hit_range(ray, t_min, t_max)?,
+            None => (t_min, t_max),
+        };
+
+        let ray_length = ray.direction.length();
+        let mut t = t_start;
+
+        for _ in 0..self.max_steps {
+            if t > t_end {
+                return None;
+            }
+
+            // The magnitude is the distance either way, so rays inside the surface march too
+            let distance = self.distance(ray.at(t)).abs();
+            if distance < self.epsilon {
+                return Some(self.hit_record(ray, t));
+            }
+
+            t += distance / ray_length;
+        }
+
+        return None;
+    }
+
+    fn material(&self) -> Option<&Material> {
+        return Some(self.material.as_ref());
+    }
+
+    fn set_material(&mut self, material: Arc<Material>) -> bool {
+        self.material = material;
+        return true;
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        return self.bounds;
+    }
+}
+
+#[test]
+fn test_sdf_sphere_matches_sphere() {
+    let material = Arc::new(Material::Lambertian(Lambertian::new(Srgb::new(
+        0.5, 0.5, 0.5,
+    ))));
+    let center = Vector3D::new(0.5, -0.25, -3.0);
+    let sphere = Sphere::new(center, 1.0, material.clone());
+    let sdf = SdfObject::new(move |point| (point - center).length() - 1.0, material);
+
+    let origin = Vector3D::new(0.0, 0.0, 0.0);
+    for direction in [
+        Vector3D::new(0.0, 0.0, -1.0),
+        Vector3D::new(0.3, -0.2, -1.0),
+        Vector3D::new(0.6, 0.1, -2.0),
+        Vector3D::new(-0.05, -0.3, -1.0),
+    ] {
+        let ray = Ray::new(origin, direction);
+        let expected = sphere.hit(&ray, 0.001, f64::MAX).unwrap();
+        let hit = sdf
+            .hit(&ray, 0.001, f64::MAX)
+            .expect("Ray should hit the SDF sphere");
+
+        assert_approx_eq!(hit.t, expected.t, 1e-4);
+        assert!((hit.point - expected.point).length() < 1e-4);
+        assert!((hit.normal - expected.normal).length() < 1e-3);
+        assert!(hit.front_face);
+    }
+
+    // Missing the sphere runs out of steps or distance
+    let ray = Ray::new(origin, Vector3D::new(0.0, 1.0, 0.0));
+    assert!(sdf.hit(&ray, 0.001, f64::MAX).is_none());
+
+    // From inside, the far side is hit from the back
+    let ray = Ray::new(center, Vector3D::new(0.0, 0.0, 1.0));
+    let hit = sdf.hit(&ray, 0.001, f64::MAX).unwrap();
+    assert_approx_eq!(hit.t, 1.0, 1e-4);
+    assert!(!hit.front_face);
+    assert_approx_eq!(hit.normal.get_z(), -1.0, 1e-3);
+}
+
+#[test]
+fn test_sdf_bounds() {
+    let material = Material::Lambertian(Lambertian::new(Srgb::new(0.5, 0.5, 0.5)));
+    let sdf = SdfObject::new(|point: Vector3D| point.length() - 1.0, material);
+    assert!(sdf.bounding_box().is_none());
+
+    let bounds = Aabb::new(
+        Vector3D::new(-1.0, -1.0, -1.0),
+        Vector3D::new(1.0, 1.0, 1.0),
+    );
+    let sdf = sdf.with_bounds(bounds).with_max_steps(64);
+    assert_eq!(sdf.bounding_box(), Some(bounds));
+
+    let ray = Ray::new(Vector3D::new(0.0, 0.0, 5.0), Vector3D::new(0.0, 0.0, -1.0));
+    assert_approx_eq!(sdf.hit(&ray, 0.001, f64::MAX).unwrap().t, 4.0, 1e-4);
+
+    // Stopping before the surface is a miss
+    assert!(sdf.hit(&ray, 0.001, 3.5).is_none());
+
+    // A scattered ray pushed off the surface by SURFACE_EPSILON does not hit it again
+    let ray = Ray::new(
+        Vector3D::new(0.0, 0.0, 1.0001),
+        Vector3D::new(0.2, 0.0, 1.0),
+    );
+    assert!(sdf.hit(&ray, 0.001, f64::MAX).is_none());
+}
@@ -1,25 +1,97 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::aabb::Aabb;
 use crate::material::Material;
 use crate::object::{Object, ObjectHitRecord};
 use crate::ray::Ray;
 use crate::vector_3d::Vector3D;
 
+#[cfg(feature = "simd")]
+use std::simd::{StdFloat, f64x4};
+
+#[cfg(test)]
+use crate::material::{Lambertian, Metal};
 #[cfg(test)]
 use assert_approx_eq::assert_approx_eq;
+#[cfg(test)]
+use palette::Srgb;
 
+// Spheres sharing a material each get their own copy of it when loaded from a scene file
+#[derive(Clone, Deserialize, Serialize)]
 pub struct Sphere {
     center: Vector3D,
     radius: f64,
-    material: Material,
+    material: Arc<Material>,
 }
 
 impl Sphere {
-    pub fn new(center: Vector3D, radius: f64, material: Material) -> Sphere {
+    pub fn new(center: Vector3D, radius: f64, material: impl Into<Arc<Material>>) -> Sphere {
         return Sphere {
             center: center,
             radius: radius,
-            material: material,
+            material: material.into(),
+        };
+    }
+
+    pub fn center(&self) -> Vector3D {
+        return self.center;
+    }
+
+    pub fn radius(&self) -> f64 {
+        return self.radius;
+    }
+
+    pub fn material(&self) -> &Material {
+        return &self.material;
+    }
+
+    // Move the sphere, e.g. between frames of an animation
+    pub fn set_center(&mut self, center: Vector3D) {
+        self.center = center;
+    }
+
+    // Hit record for a ray known to hit the sphere at t
+    fn hit_record(&self, ray: &Ray, t: f64) -> ObjectHitRecord {
+        let intersect_point = ray.at(t);
+        let normal = (intersect_point - self.center) / self.radius;
+
+        // If the dot product against the normal is negative (90 < x < 270)
+        // This means we are outisde the sphere, and want to keep the normal the same
+        let front_face = ray.direction.dot(&normal) < 0.0;
+        let (u, v) = Sphere::get_sphere_uv(&normal);
+
+        // u runs around the y axis and v from the bottom pole to the top, both vanishing at the poles
+        let around = Vector3D::new(normal.get_z(), 0.0, -normal.get_x());
+        let tangent = around * (2.0 * std::f64::consts::PI * self.radius);
+        let bitangent =
+            normal.cross(&around).normalize_or_zero() * (std::f64::consts::PI * self.radius);
+
+        return ObjectHitRecord {
+            t: t,
+            point: intersect_point,
+            normal: if front_face { normal } else { -normal },
+            front_face: front_face,
+            material: self.material.clone(),
+            u: u,
+            v: v,
+            tangent: tangent,
+            bitangent: bitangent,
         };
     }
+
+    // Map a point on the unit sphere to (u, v) texture coordinates
+    // u is the angle around the y axis from x = -1, v is the angle from y = -1
+    pub(crate) fn get_sphere_uv(point: &Vector3D) -> (f64, f64) {
+        let theta = (-point.get_y()).acos();
+        let phi = (-point.get_z()).atan2(point.get_x()) + std::f64::consts::PI;
+
+        return (
+            phi / (2.0 * std::f64::consts::PI),
+            theta / std::f64::consts::PI,
+        );
+    }
 }
 
 impl Object for Sphere {
@@ -33,26 +105,196 @@ impl Object for Sphere {
 
         if discriminant > 0.0 {
             let root = discriminant.sqrt();
-            let temp_soln = (-half_b - root) / a;
-
-            // Check if its within the range of distance from camera
-            if temp_soln < t_max && temp_soln > t_min {
-                let intersect_point = ray.at(temp_soln);
-                let normal = (intersect_point - self.center) / self.radius;
-
-                // If the dot product against the normal is negative (90 < x < 270)
-                // This means we are outisde the sphere, and want to keep the normal the same
-                let front_face = ray.direction.dot(&normal) < 0.0;
-
-                return Some(ObjectHitRecord {
-                    t: temp_soln,
-                    point: intersect_point,
-                    normal: if front_face { normal } else { -normal },
-                    front_face: front_face,
-                    material: &self.material,
-                });
+
+            // Try the nearest intersection first, then the far one (e.g. a ray starting inside)
+            for temp_soln in [(-half_b - root) / a, (-half_b + root) / a] {
+                // Check if its within the range of distance from camera
+                if temp_soln < t_max && temp_soln > t_min {
+                    return Some(self.hit_record(ray, temp_soln));
+                }
             }
         }
         return None;
     }
+
+    // Same quadratic as hit, solved for four rays in f64x4 lanes
+    #[cfg(feature = "simd")]
+    fn hit4(&self, rays: &[Ray; 4], t_min: f64, t_max: [f64; 4]) -> [Option<ObjectHitRecord>; 4] {
+        let lanes = |component: fn(&Ray) -> f64| {
+            f64x4::from_array([
+                component(&rays[0]),
+                component(&rays[1]),
+                component(&rays[2]),
+                component(&rays[3]),
+            ])
+        };
+
+        let direction_x = lanes(|ray| ray.direction.get_x());
+        let direction_y = lanes(|ray| ray.direction.get_y());
+        let direction_z = lanes(|ray| ray.direction.get_z());
+        let offset_x = lanes(|ray| ray.origin.get_x()) - f64x4::splat(self.center.get_x());
+        let offset_y = lanes(|ray| ray.origin.get_y()) - f64x4::splat(self.center.get_y());
+        let offset_z = lanes(|ray| ray.origin.get_z()) - f64x4::splat(self.center.get_z());
+
+        let a = direction_x * direction_x + direction_y * direction_y + direction_z * direction_z;
+        let half_b = offset_x * direction_x + offset_y * direction_y + offset_z * direction_z;
+        let c = offset_x * offset_x + offset_y * offset_y + offset_z * offset_z
+            - f64x4::splat(self.radius * self.radius);
+        let discriminant = half_b * half_b - a * c;
+
+        // Lanes with a negative discriminant produce NaN roots, but those are never read
+        let root = discriminant.sqrt();
+        let near = ((-half_b - root) / a).to_array();
+        let far = ((-half_b + root) / a).to_array();
+        let discriminant = discriminant.to_array();
+
+        return [0, 1, 2, 3].map(|lane| {
+            if discriminant[lane] <= 0.0 {
+                return None;
+            }
+
+            // Nearest intersection first, then the far one, as in hit
+            for t in [near[lane], far[lane]] {
+                if t < t_max[lane] && t > t_min {
+                    return Some(self.hit_record(&rays[lane], t));
+                }
+            }
+            return None;
+        });
+    }
+
+    fn material(&self) -> Option<&Material> {
+        return Some(self.material.as_ref());
+    }
+
+    fn set_material(&mut self, material: Arc<Material>) -> bool {
+        self.material = material;
+        return true;
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let extent = Vector3D::new(self.radius, self.radius, self.radius);
+        return Some(Aabb::new(self.center - extent, self.center + extent));
+    }
+}
+
+#[test]
+fn test_sphere_uv() {
+    let (u, v) = Sphere::get_sphere_uv(&Vector3D::new(1.0, 0.0, 0.0));
+    assert_approx_eq!(u, 0.5);
+    assert_approx_eq!(v, 0.5);
+
+    let (u, v) = Sphere::get_sphere_uv(&Vector3D::new(0.0, 1.0, 0.0));
+    assert_approx_eq!(u, 0.5);
+    assert_approx_eq!(v, 1.0);
+
+    let (u, v) = Sphere::get_sphere_uv(&Vector3D::new(0.0, 0.0, 1.0));
+    assert_approx_eq!(u, 0.25);
+    assert_approx_eq!(v, 0.5);
+}
+
+#[test]
+fn test_sphere_bounding_box() {
+    let sphere = Sphere::new(
+        Vector3D::new(0.0, 0.0, 0.0),
+        1.0,
+        Material::Lambertian(Lambertian::new(Srgb::new(0.5, 0.5, 0.5))),
+    );
+
+    let aabb = sphere.bounding_box().expect("Spheres are bounded");
+    assert_eq!(aabb.min(), Vector3D::new(-1.0, -1.0, -1.0));
+    assert_eq!(aabb.max(), Vector3D::new(1.0, 1.0, 1.0));
+}
+
+#[test]
+fn test_sphere_getters() {
+    let mut sphere = Sphere::new(
+        Vector3D::new(1.0, 2.0, 3.0),
+        0.5,
+        Material::Metal(Metal::new(Srgb::new(0.8, 0.6, 0.2), 0.3)),
+    );
+
+    assert_eq!(sphere.center(), Vector3D::new(1.0, 2.0, 3.0));
+    assert_eq!(sphere.radius(), 0.5);
+    match sphere.material() {
+        Material::Metal(metal) => assert_eq!(metal.roughness, 0.3),
+        material => panic!("Unexpected material {:?}", material),
+    }
+
+    sphere.set_center(Vector3D::new(-1.0, 0.0, 0.0));
+    assert_eq!(sphere.center(), Vector3D::new(-1.0, 0.0, 0.0));
+
+    // The bounding box follows the sphere
+    let aabb = sphere.bounding_box().unwrap();
+    assert_eq!(aabb.min(), Vector3D::new(-1.5, -0.5, -0.5));
+}
+
+#[cfg(feature = "simd")]
+#[test]
+fn test_sphere_hit4() {
+    let sphere = Sphere::new(
+        Vector3D::new(0.0, 0.0, -1.0),
+        0.5,
+        Material::Lambertian(Lambertian::new(Srgb::new(0.5, 0.5, 0.5))),
+    );
+
+    let origin = Vector3D::new(0.0, 0.0, 0.0);
+    let batches = [
+        [Ray::new(origin, Vector3D::new(0.1, 0.2, -1.0)); 4],
+        [
+            Ray::new(origin, Vector3D::new(0.0, 0.0, -1.0)),
+            Ray::new(origin, Vector3D::new(1.0, 1.0, -1.0)),
+            Ray::new(Vector3D::new(0.0, 0.0, -1.0), Vector3D::new(0.0, 1.0, 0.0)),
+            Ray::new(origin, Vector3D::new(0.0, 0.0, 1.0)),
+        ],
+    ];
+
+    for rays in batches.iter() {
+        let hits = sphere.hit4(rays, 0.001, [f64::MAX; 4]);
+        for (ray, hit) in rays.iter().zip(hits.iter()) {
+            match (hit, sphere.hit(ray, 0.001, f64::MAX)) {
+                (Some(hit), Some(expected)) => {
+                    assert_approx_eq!(hit.t, expected.t);
+                    assert_eq!(hit.point, expected.point);
+                    assert_eq!(hit.normal, expected.normal);
+                    assert_eq!(hit.front_face, expected.front_face);
+                }
+                (None, None) => {}
+                _ => panic!("hit4 and hit disagree for {:?}", ray),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_sphere_shared_material() {
+    let material = Arc::new(Material::Lambertian(Lambertian::new(Srgb::new(
+        0.5, 0.5, 0.5,
+    ))));
+    let left = Sphere::new(Vector3D::new(-1.0, 0.0, -2.0), 0.5, material.clone());
+    let right = Sphere::new(Vector3D::new(1.0, 0.0, -2.0), 0.5, material.clone());
+
+    let origin = Vector3D::new(0.0, 0.0, 0.0);
+    let left_hit = left
+        .hit(
+            &Ray::new(origin, Vector3D::new(-1.0, 0.0, -2.0)),
+            0.001,
+            f64::MAX,
+        )
+        .expect("Ray should hit the left sphere");
+    let right_hit = right
+        .hit(
+            &Ray::new(origin, Vector3D::new(1.0, 0.0, -2.0)),
+            0.001,
+            f64::MAX,
+        )
+        .expect("Ray should hit the right sphere");
+
+    assert!(Arc::ptr_eq(&left_hit.material, &material));
+    assert!(Arc::ptr_eq(&right_hit.material, &material));
+
+    // Hit records keep the material alive on their own
+    drop(left);
+    drop(right);
+    assert_eq!(Arc::strong_count(&material), 3);
 }
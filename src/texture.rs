@@ -0,0 +1,275 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use palette::Srgb;
+
+use crate::error::RayTracerError;
+use crate::perlin::Perlin;
+use crate::vector_3d::Vector3D;
+
+#[cfg(test)]
+use assert_approx_eq::assert_approx_eq;
+#[cfg(test)]
+use image::ColorType;
+#[cfg(test)]
+use image::png::PNGEncoder;
+#[cfg(test)]
+use std::fs::File;
+
+// Textures are shared between materials across render threads, so they must be Send + Sync
+pub trait Texture: std::fmt::Debug + Send + Sync {
+    // Color of the texture at surface coordinates (u, v) and hit point
+    fn value(&self, u: f64, v: f64, point: &Vector3D) -> Srgb;
+}
+
+// How an image texture is sampled between texel centers
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, Serialize)]
+pub enum TextureFilter {
+    #[default]
+    Nearest, // Color of the closest texel, blocky up close
+    Bilinear, // Blend of the four surrounding texels
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    data: Vec<u8>, // Tightly packed RGB8 pixels, row major from the top left
+    #[serde(default)]
+    filter: TextureFilter,
+}
+
+impl ImageTexture {
+    // Build a texture from tightly packed RGB8 pixels, e.g. a previous render
+    pub fn new(width: usize, height: usize, data: Vec<u8>) -> ImageTexture {
+        assert_eq!(
+            data.len(),
+            width * height * 3,
+            "Texture data does not match its dimensions"
+        );
+
+        return ImageTexture {
+            width: width,
+            height: height,
+            data: data,
+            filter: TextureFilter::Nearest,
+        };
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<ImageTexture, RayTracerError> {
+        let image = image::open(path)?.to_rgb();
+
+        return Ok(ImageTexture {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            data: image.into_raw(),
+            filter: TextureFilter::Nearest,
+        });
+    }
+
+    pub fn width(&self) -> usize {
+        return self.width;
+    }
+
+    pub fn height(&self) -> usize {
+        return self.height;
+    }
+
+    pub fn filter(&self) -> TextureFilter {
+        return self.filter;
+    }
+
+    pub fn set_filter(&mut self, filter: TextureFilter) {
+        self.filter = filter;
+    }
+
+    // Color of the texel in column i and row j, counted from the top left
+    fn texel(&self, i: usize, j: usize) -> [f32; 3] {
+        let index = (j * self.width + i) * 3;
+        let scale = 1.0 / 255.0;
+        return [
+            self.data[index] as f32 * scale,
+            self.data[index + 1] as f32 * scale,
+            self.data[index + 2] as f32 * scale,
+        ];
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _point: &Vector3D) -> Srgb {
+        // Solid cyan makes an empty texture obvious in the render
+        if self.data.is_empty() {
+            return Srgb::new(0.0, 1.0, 1.0);
+        }
+
+        // Clamp to the image edges and flip v, since image rows start at the top
+        let u = u.clamp(0.0, 1.0);
+        let v = 1.0 - v.clamp(0.0, 1.0);
+
+        match self.filter {
+            TextureFilter::Nearest => {
+                let i = ((u * self.width as f64) as usize).min(self.width - 1);
+                let j = ((v * self.height as f64) as usize).min(self.height - 1);
+
+                let [red, green, blue] = self.texel(i, j);
+                return Srgb::new(red, green, blue);
+            }
+            TextureFilter::Bilinear => {
+                // Position relative to the texel centers, clamped so the edge texels repeat
+                // outwards just like Nearest clamps at the image edges
+                let x = (u * self.width as f64 - 0.5).clamp(0.0, (self.width - 1) as f64);
+                let y = (v * self.height as f64 - 0.5).clamp(0.0, (self.height - 1) as f64);
+
+                let i0 = x.floor() as usize;
+                let j0 = y.floor() as usize;
+                let i1 = (i0 + 1).min(self.width - 1);
+                let j1 = (j0 + 1).min(self.height - 1);
+                let fx = (x - i0 as f64) as f32;
+                let fy = (y - j0 as f64) as f32;
+
+                let top_left = self.texel(i0, j0);
+                let top_right = self.texel(i1, j0);
+                let bottom_left = self.texel(i0, j1);
+                let bottom_right = self.texel(i1, j1);
+                let blend = |channel: usize| {
+                    let top = top_left[channel] * (1.0 - fx) + top_right[channel] * fx;
+                    let bottom = bottom_left[channel] * (1.0 - fx) + bottom_right[channel] * fx;
+                    top * (1.0 - fy) + bottom * fy
+                };
+
+                return Srgb::new(blend(0), blend(1), blend(2));
+            }
+        }
+    }
+}
+
+// Squares of two alternating colors laid out over the surface coordinates
+#[derive(Debug, Clone, Copy)]
+pub struct CheckerTexture {
+    even: Srgb,
+    odd: Srgb,
+    scale: f64, // Squares per unit of u and v
+}
+
+impl CheckerTexture {
+    pub fn new(even: Srgb, odd: Srgb, scale: f64) -> CheckerTexture {
+        return CheckerTexture {
+            even: even,
+            odd: odd,
+            scale: scale,
+        };
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, u: f64, v: f64, _point: &Vector3D) -> Srgb {
+        let square = (u * self.scale).floor() as i64 + (v * self.scale).floor() as i64;
+        if square.rem_euclid(2) == 0 {
+            return self.even;
+        }
+        return self.odd;
+    }
+}
+
+// Procedural grayscale texture driven by Perlin noise
+#[derive(Debug, Clone)]
+pub struct NoiseTexture {
+    noise: Perlin,
+    scale: f64, // Higher values give finer detail
+}
+
+impl NoiseTexture {
+    pub fn new(seed: u64, scale: f64) -> NoiseTexture {
+        return NoiseTexture {
+            noise: Perlin::new(seed),
+            scale: scale,
+        };
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _u: f64, _v: f64, point: &Vector3D) -> Srgb {
+        // Remap the noise from [-1, 1] into [0, 1]
+        let gray = 0.5 * (1.0 + self.noise.noise(&(point * self.scale))) as f32;
+        return Srgb::new(gray, gray, gray);
+    }
+}
+
+#[test]
+fn test_image_texture() {
+    // 2x2 checkerboard: white and black on the top row, black and white on the bottom row
+    #[rustfmt::skip]
+    let pixels: [u8; 12] = [
+        255, 255, 255,   0,   0,   0,
+          0,   0,   0, 255, 255, 255,
+    ];
+
+    let path = std::env::temp_dir().join("ray_tracer_test_image_texture.png");
+    let encoder = PNGEncoder::new(File::create(&path).unwrap());
+    encoder.encode(&pixels, 2, 2, ColorType::RGB(8)).unwrap();
+
+    let texture = ImageTexture::open(&path).expect("Failed to load texture");
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(texture.width(), 2);
+    assert_eq!(texture.height(), 2);
+
+    let origin = Vector3D::new(0.0, 0.0, 0.0);
+
+    // v = 1 is the top row of the image
+    let top_left = texture.value(0.0, 1.0, &origin);
+    let top_right = texture.value(1.0, 1.0, &origin);
+    let bottom_left = texture.value(0.0, 0.0, &origin);
+    let bottom_right = texture.value(1.0, 0.0, &origin);
+
+    assert_approx_eq!(top_left.red, 1.0);
+    assert_approx_eq!(top_right.red, 0.0);
+    assert_approx_eq!(bottom_left.red, 0.0);
+    assert_approx_eq!(bottom_right.red, 1.0);
+}
+
+#[test]
+fn test_noise_texture() {
+    let texture = NoiseTexture::new(42, 4.0);
+
+    for _ in 0..100 {
+        let point = Vector3D::random(-10.0, 10.0);
+        let color = texture.value(0.0, 0.0, &point);
+        assert!(color.red >= 0.0 && color.red <= 1.0);
+        assert_approx_eq!(color.red, color.green);
+        assert_approx_eq!(color.red, color.blue);
+    }
+}
+
+#[test]
+fn test_image_texture_missing_file() {
+    let path = std::env::temp_dir().join("ray_tracer_test_missing_texture.png");
+    let error = ImageTexture::open(&path).unwrap_err();
+    assert!(matches!(error, RayTracerError::Io(_)), "Got {:?}", error);
+}
+
+#[test]
+fn test_image_texture_bilinear() {
+    // One black and one white texel side by side
+    let mut texture = ImageTexture::new(2, 1, vec![0, 0, 0, 255, 255, 255]);
+    let point = Vector3D::new(0.0, 0.0, 0.0);
+
+    // Halfway between the two texel centers
+    let nearest = texture.value(0.5, 0.5, &point);
+    assert!(nearest.red == 0.0 || nearest.red == 1.0);
+
+    texture.set_filter(TextureFilter::Bilinear);
+    assert_eq!(texture.filter(), TextureFilter::Bilinear);
+    let blended = texture.value(0.5, 0.5, &point);
+    assert_approx_eq!(blended.red, 0.5);
+    assert_approx_eq!(blended.green, 0.5);
+    assert_approx_eq!(blended.blue, 0.5);
+
+    // A quarter of the way from the black center towards the white one
+    assert_approx_eq!(texture.value(0.375, 0.5, &point).red, 0.25);
+
+    // Past the outer texel centers the edge texels are clamped
+    assert_approx_eq!(texture.value(0.0, 0.5, &point).red, 0.0);
+    assert_approx_eq!(texture.value(1.0, 0.5, &point).red, 1.0);
+}
@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+
+use palette::LinSrgb;
+
+#[cfg(test)]
+use assert_approx_eq::assert_approx_eq;
+
+// Maps accumulated HDR radiance into the displayable [0, 1] range
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum ToneMap {
+    Clamp,      // Hard clip anything above 1.0
+    Reinhard,   // x / (1 + x), compresses highlights smoothly
+    AcesFilmic, // Narkowicz's fit of the ACES filmic curve
+}
+
+impl ToneMap {
+    pub fn map_channel(&self, value: f32) -> f32 {
+        let value = value.max(0.0);
+
+        match self {
+            ToneMap::Clamp => value.min(1.0),
+            ToneMap::Reinhard => value / (1.0 + value),
+            ToneMap::AcesFilmic => {
+                let a = 2.51;
+                let b = 0.03;
+                let c = 2.43;
+                let d = 0.59;
+                let e = 0.14;
+                ((value * (a * value + b)) / (value * (c * value + d) + e)).clamp(0.0, 1.0)
+            }
+        }
+    }
+
+    pub fn apply(&self, color: LinSrgb) -> LinSrgb {
+        return LinSrgb::new(
+            self.map_channel(color.red),
+            self.map_channel(color.green),
+            self.map_channel(color.blue),
+        );
+    }
+}
+
+#[test]
+fn test_tone_map_clamp() {
+    assert_approx_eq!(ToneMap::Clamp.map_channel(0.5), 0.5);
+    assert_approx_eq!(ToneMap::Clamp.map_channel(4.0), 1.0);
+    assert_approx_eq!(ToneMap::Clamp.map_channel(-1.0), 0.0);
+}
+
+#[test]
+fn test_tone_map_reinhard() {
+    assert_approx_eq!(ToneMap::Reinhard.map_channel(0.0), 0.0);
+    assert_approx_eq!(ToneMap::Reinhard.map_channel(4.0), 0.8);
+    assert!(ToneMap::Reinhard.map_channel(1000.0) < 1.0);
+}
+
+#[test]
+fn test_tone_map_aces_filmic() {
+    assert_approx_eq!(ToneMap::AcesFilmic.map_channel(0.0), 0.0);
+    assert!(ToneMap::AcesFilmic.map_channel(4.0) <= 1.0);
+    assert!(ToneMap::AcesFilmic.map_channel(0.5) < ToneMap::AcesFilmic.map_channel(1.0));
+}
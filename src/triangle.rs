@@ -0,0 +1,250 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::material::Material;
+use crate::object::{Object, ObjectHitRecord};
+use crate::ray::Ray;
+use crate::vector_3d::Vector3D;
+
+#[cfg(test)]
+use crate::material::Lambertian;
+#[cfg(test)]
+use assert_approx_eq::assert_approx_eq;
+#[cfg(test)]
+use palette::Srgb;
+
+#[derive(Clone)]
+pub struct Triangle {
+    vertices: [Vector3D; 3],
+    normal: Vector3D, // Unit geometric normal, following the counter-clockwise winding
+    vertex_normals: Option<[Vector3D; 3]>, // Unit shading normals for smooth meshes
+    vertex_uvs: Option<[(f64, f64); 3]>, // Texture coordinates at each vertex
+    material: Arc<Material>,
+}
+
+impl Triangle {
+    pub fn new(
+        v0: Vector3D,
+        v1: Vector3D,
+        v2: Vector3D,
+        material: impl Into<Arc<Material>>,
+    ) -> Triangle {
+        return Triangle {
+            vertices: [v0, v1, v2],
+            normal: (v1 - v0).cross(&(v2 - v0)).unit_vector(),
+            vertex_normals: None,
+            vertex_uvs: None,
+            material: material.into(),
+        };
+    }
+
+    // Shade with normals interpolated across the face instead of the flat geometric normal
+    pub fn with_vertex_normals(mut self, normals: [Vector3D; 3]) -> Triangle {
+        self.vertex_normals = Some([
+            normals[0].unit_vector(),
+            normals[1].unit_vector(),
+            normals[2].unit_vector(),
+        ]);
+        return self;
+    }
+
+    // Texture coordinates at each vertex, interpolated across the face. Without them u and v
+    // are the barycentric weights of vertices 1 and 2
+    pub fn with_vertex_uvs(mut self, uvs: [(f64, f64); 3]) -> Triangle {
+        self.vertex_uvs = Some(uvs);
+        return self;
+    }
+}
+
+impl Object for Triangle {
+    // Möller-Trumbore intersection
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<ObjectHitRecord> {
+        let edge_1 = self.vertices[1] - self.vertices[0];
+        let edge_2 = self.vertices[2] - self.vertices[0];
+
+        let p = ray.direction.cross(&edge_2);
+        let determinant = edge_1.dot(&p);
+
+        // A ray (nearly) parallel to the triangle never intersects it
+        if determinant.abs() < 1e-8 {
+            return None;
+        }
+        let inverse_determinant = 1.0 / determinant;
+
+        // Barycentric coordinates of the hit, weighting vertices 1 and 2
+        let s = ray.origin - self.vertices[0];
+        let b1 = s.dot(&p) * inverse_determinant;
+        if !(0.0..=1.0).contains(&b1) {
+            return None;
+        }
+
+        let q = s.cross(&edge_1);
+        let b2 = ray.direction.dot(&q) * inverse_determinant;
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return None;
+        }
+
+        let t = edge_2.dot(&q) * inverse_determinant;
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        let front_face = ray.direction.dot(&self.normal) < 0.0;
+
+        // Opposing vertex normals can cancel out, leaving only the face normal to go by
+        let normal = match self.vertex_normals {
+            Some(normals) => {
+                let interpolated =
+                    (normals[0] * (1.0 - b1 - b2) + normals[1] * b1 + normals[2] * b2)
+                        .normalize_or_zero();
+                if interpolated.near_zero() {
+                    self.normal
+                } else {
+                    interpolated
+                }
+            }
+            None => self.normal,
+        };
+
+        let (u, v, tangent, bitangent) = match self.vertex_uvs {
+            Some(uvs) => {
+                let b0 = 1.0 - b1 - b2;
+
+                // Solve edge = tangent * delta u + bitangent * delta v for both edges,
+                // texture coordinates without any area give no directions
+                let (du_1, dv_1) = (uvs[1].0 - uvs[0].0, uvs[1].1 - uvs[0].1);
+                let (du_2, dv_2) = (uvs[2].0 - uvs[0].0, uvs[2].1 - uvs[0].1);
+                let uv_determinant = du_1 * dv_2 - du_2 * dv_1;
+                let (tangent, bitangent) = if uv_determinant.abs() < 1e-12 {
+                    (Vector3D::new(0.0, 0.0, 0.0), Vector3D::new(0.0, 0.0, 0.0))
+                } else {
+                    (
+                        (edge_1 * dv_2 - edge_2 * dv_1) / uv_determinant,
+                        (edge_2 * du_1 - edge_1 * du_2) / uv_determinant,
+                    )
+                };
+
+                (
+                    uvs[0].0 * b0 + uvs[1].0 * b1 + uvs[2].0 * b2,
+                    uvs[0].1 * b0 + uvs[1].1 * b1 + uvs[2].1 * b2,
+                    tangent,
+                    bitangent,
+                )
+            }
+            None => (b1, b2, edge_1, edge_2),
+        };
+
+        return Some(ObjectHitRecord {
+            t: t,
+            point: ray.at(t),
+            normal: if front_face { normal } else { -normal },
+            front_face: front_face,
+            material: self.material.clone(),
+            u: u,
+            v: v,
+            tangent: tangent,
+            bitangent: bitangent,
+        });
+    }
+
+    fn material(&self) -> Option<&Material> {
+        return Some(self.material.as_ref());
+    }
+
+    fn set_material(&mut self, material: Arc<Material>) -> bool {
+        self.material = material;
+        return true;
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let min = self.vertices[0]
+            .component_min(&self.vertices[1])
+            .component_min(&self.vertices[2]);
+        let max = self.vertices[0]
+            .component_max(&self.vertices[1])
+            .component_max(&self.vertices[2]);
+
+        // Pad the box so axis-aligned triangles do not produce a flat box
+        let padding = Vector3D::new(0.0001, 0.0001, 0.0001);
+        return Some(Aabb::new(min - padding, max + padding));
+    }
+}
+
+#[cfg(test)]
+fn test_triangle() -> Triangle {
+    Triangle::new(
+        Vector3D::new(-1.0, -1.0, -2.0),
+        Vector3D::new(1.0, -1.0, -2.0),
+        Vector3D::new(0.0, 1.0, -2.0),
+        Material::Lambertian(Lambertian::new(Srgb::new(0.5, 0.5, 0.5))),
+    )
+}
+
+#[test]
+fn test_triangle_hit() {
+    let triangle = test_triangle();
+    let ray = Ray::new(Vector3D::new(0.0, 0.0, 0.0), Vector3D::new(0.0, 0.0, -1.0));
+
+    let hit = triangle
+        .hit(&ray, 0.001, f64::MAX)
+        .expect("Ray should hit the triangle");
+
+    assert_approx_eq!(hit.t, 2.0);
+    assert_approx_eq!(hit.normal.get_z(), 1.0);
+    assert!(hit.front_face);
+
+    // Past the slanted edge between vertices 1 and 2
+    let ray = Ray::new(Vector3D::new(0.6, 0.3, 0.0), Vector3D::new(0.0, 0.0, -1.0));
+    assert!(triangle.hit(&ray, 0.001, f64::MAX).is_none());
+}
+
+#[test]
+fn test_triangle_vertex_normals() {
+    let normals = [
+        Vector3D::new(-0.5, 0.0, 1.0),
+        Vector3D::new(0.5, 0.0, 1.0),
+        Vector3D::new(0.0, 0.5, 1.0),
+    ];
+    let triangle = test_triangle().with_vertex_normals(normals);
+
+    // Aim at the centroid, where all three vertex normals are weighted equally
+    let centroid = Vector3D::new(0.0, -1.0 / 3.0, -2.0);
+    let ray = Ray::new(
+        Vector3D::new(0.0, -1.0 / 3.0, 0.0),
+        Vector3D::new(0.0, 0.0, -1.0),
+    );
+    let hit = triangle
+        .hit(&ray, 0.001, f64::MAX)
+        .expect("Ray should hit the triangle");
+    assert_approx_eq!(hit.point.get_y(), centroid.get_y());
+
+    let expected = (normals[0].unit_vector() + normals[1].unit_vector() + normals[2].unit_vector())
+        .unit_vector();
+    assert_approx_eq!(hit.normal.get_x(), expected.get_x());
+    assert_approx_eq!(hit.normal.get_y(), expected.get_y());
+    assert_approx_eq!(hit.normal.get_z(), expected.get_z());
+
+    // The smooth normal differs from the flat geometric normal
+    assert!(hit.normal.get_y() > 0.0);
+}
+
+#[test]
+fn test_triangle_uvs() {
+    // Straight through vertex 1, and halfway along the edge between vertices 0 and 2
+    let at_vertex_1 = Ray::new(Vector3D::new(1.0, -1.0, 0.0), Vector3D::new(0.0, 0.0, -1.0));
+    let on_edge = Ray::new(Vector3D::new(-0.5, 0.0, 0.0), Vector3D::new(0.0, 0.0, -1.0));
+
+    // Falls back to the barycentric coordinates
+    let triangle = test_triangle();
+    let hit = triangle.hit(&at_vertex_1, 0.001, f64::MAX).unwrap();
+    assert_approx_eq!(hit.u, 1.0);
+    assert_approx_eq!(hit.v, 0.0);
+
+    let triangle = test_triangle().with_vertex_uvs([(0.0, 0.0), (1.0, 0.0), (0.5, 1.0)]);
+    let hit = triangle.hit(&at_vertex_1, 0.001, f64::MAX).unwrap();
+    assert_approx_eq!(hit.u, 1.0);
+    assert_approx_eq!(hit.v, 0.0);
+    let hit = triangle.hit(&on_edge, 0.001, f64::MAX).unwrap();
+    assert_approx_eq!(hit.u, 0.25);
+    assert_approx_eq!(hit.v, 0.5);
+}
@@ -1,6 +1,7 @@
 use std::cmp::PartialEq;
 use std::f64;
-use std::ops::{Add, Div, Mul, Neg, Sub};
+use std::fmt;
+use std::ops::{Add, AddAssign, Div, Index, IndexMut, Mul, MulAssign, Neg, Sub, SubAssign};
 
 use rand::Rng;
 
@@ -11,6 +12,8 @@ use std::simd::f64x4;
 
 #[cfg(test)]
 use assert_approx_eq::assert_approx_eq;
+#[cfg(test)]
+use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, PI};
 
 #[cfg(not(feature = "simd"))]
 #[derive(Debug, Clone, Copy, Deserialize, Serialize)]
@@ -85,6 +88,26 @@ impl Vector3D {
         }
     }
 
+    // Vector with all three components equal to value, e.g. a uniform scale or a gray color
+    pub fn splat(value: f64) -> Vector3D {
+        #[cfg(not(feature = "simd"))]
+        {
+            return Vector3D {
+                x: value,
+                y: value,
+                z: value,
+            };
+        }
+
+        // Not f64x4::splat, the unused fourth lane stays 0 like in new
+        #[cfg(feature = "simd")]
+        {
+            return Vector3D {
+                data: f64x4::from_array([value, value, value, 0.0]),
+            };
+        }
+    }
+
     pub fn random(min: f64, max: f64) -> Vector3D {
         let mut rng = rand::thread_rng();
         Vector3D::new(
@@ -103,6 +126,26 @@ impl Vector3D {
         }
     }
 
+    // Uniformly distributed point on the surface of the unit sphere
+    pub fn random_unit_vector() -> Vector3D {
+        return Vector3D::random_in_unit_sphere().unit_vector();
+    }
+
+    // Random direction in the +z hemisphere with probability density cos(theta) / pi,
+    // where theta is the angle to the z axis
+    pub fn random_cosine_direction() -> Vector3D {
+        let mut rng = rand::thread_rng();
+        let r1: f64 = rng.r#gen();
+        let r2: f64 = rng.r#gen();
+
+        let phi = 2.0 * std::f64::consts::PI * r1;
+        let x = phi.cos() * r2.sqrt();
+        let y = phi.sin() * r2.sqrt();
+        let z = (1.0 - r2).sqrt();
+
+        return Vector3D::new(x, y, z);
+    }
+
     pub fn get_x(&self) -> f64 {
         #[cfg(not(feature = "simd"))]
         {
@@ -214,6 +257,16 @@ impl Vector3D {
         return *self / length;
     }
 
+    // Like unit_vector, but a (nearly) zero vector gives the zero vector instead of NaNs,
+    // for directions that can degenerate, e.g. the sum of two opposite vectors
+    pub fn normalize_or_zero(&self) -> Vector3D {
+        let length = self.length();
+        if length < f64::EPSILON {
+            return Vector3D::new(0.0, 0.0, 0.0);
+        }
+        return *self / length;
+    }
+
     pub fn dot(&self, other: &Vector3D) -> f64 {
         #[cfg(not(feature = "simd"))]
         {
@@ -280,6 +333,211 @@ impl Vector3D {
                 && self.data[2].abs() < f64::EPSILON
         }
     }
+
+    // Linear interpolation, returns self at t = 0 and other at t = 1
+    pub fn lerp(&self, other: &Vector3D, t: f64) -> Vector3D {
+        #[cfg(not(feature = "simd"))]
+        {
+            return Vector3D::new(
+                self.x + (other.get_x() - self.x) * t,
+                self.y + (other.get_y() - self.y) * t,
+                self.z + (other.get_z() - self.z) * t,
+            );
+        }
+
+        #[cfg(feature = "simd")]
+        {
+            return Vector3D {
+                data: self.data + (other.data - self.data) * f64x4::splat(t),
+            };
+        }
+    }
+
+    // Clamp every component into [min, max]
+    pub fn clamp(&self, min: f64, max: f64) -> Vector3D {
+        #[cfg(not(feature = "simd"))]
+        {
+            return Vector3D::new(
+                self.x.clamp(min, max),
+                self.y.clamp(min, max),
+                self.z.clamp(min, max),
+            );
+        }
+
+        #[cfg(feature = "simd")]
+        {
+            let result = f64x4::from_array([
+                self.data[0].clamp(min, max),
+                self.data[1].clamp(min, max),
+                self.data[2].clamp(min, max),
+                0.0,
+            ]);
+
+            return Vector3D { data: result };
+        }
+    }
+
+    pub fn component_sqrt(&self) -> Vector3D {
+        #[cfg(not(feature = "simd"))]
+        {
+            return Vector3D::new(self.x.sqrt(), self.y.sqrt(), self.z.sqrt());
+        }
+
+        #[cfg(feature = "simd")]
+        {
+            let result = f64x4::from_array([
+                self.data[0].sqrt(),
+                self.data[1].sqrt(),
+                self.data[2].sqrt(),
+                0.0,
+            ]);
+
+            return Vector3D { data: result };
+        }
+    }
+
+    pub fn abs(&self) -> Vector3D {
+        #[cfg(not(feature = "simd"))]
+        {
+            return Vector3D::new(self.x.abs(), self.y.abs(), self.z.abs());
+        }
+
+        #[cfg(feature = "simd")]
+        {
+            let result = f64x4::from_array([
+                self.data[0].abs(),
+                self.data[1].abs(),
+                self.data[2].abs(),
+                0.0,
+            ]);
+
+            return Vector3D { data: result };
+        }
+    }
+
+    // Sign of each component as -1, 0 or 1. Unlike f64::signum, zero components stay 0
+    pub fn signum(&self) -> Vector3D {
+        let sign = |value: f64| {
+            if value == 0.0 || value.is_nan() {
+                value
+            } else {
+                value.signum()
+            }
+        };
+
+        #[cfg(not(feature = "simd"))]
+        {
+            return Vector3D::new(sign(self.x), sign(self.y), sign(self.z));
+        }
+
+        #[cfg(feature = "simd")]
+        {
+            let result = f64x4::from_array([
+                sign(self.data[0]),
+                sign(self.data[1]),
+                sign(self.data[2]),
+                0.0,
+            ]);
+
+            return Vector3D { data: result };
+        }
+    }
+
+    pub fn component_min(&self, other: &Vector3D) -> Vector3D {
+        #[cfg(not(feature = "simd"))]
+        {
+            return Vector3D::new(
+                self.x.min(other.get_x()),
+                self.y.min(other.get_y()),
+                self.z.min(other.get_z()),
+            );
+        }
+
+        #[cfg(feature = "simd")]
+        {
+            let result = f64x4::from_array([
+                self.data[0].min(other.data[0]),
+                self.data[1].min(other.data[1]),
+                self.data[2].min(other.data[2]),
+                0.0,
+            ]);
+
+            return Vector3D { data: result };
+        }
+    }
+
+    pub fn component_max(&self, other: &Vector3D) -> Vector3D {
+        #[cfg(not(feature = "simd"))]
+        {
+            return Vector3D::new(
+                self.x.max(other.get_x()),
+                self.y.max(other.get_y()),
+                self.z.max(other.get_z()),
+            );
+        }
+
+        #[cfg(feature = "simd")]
+        {
+            let result = f64x4::from_array([
+                self.data[0].max(other.data[0]),
+                self.data[1].max(other.data[1]),
+                self.data[2].max(other.data[2]),
+                0.0,
+            ]);
+
+            return Vector3D { data: result };
+        }
+    }
+
+    // Equality within a caller chosen tolerance on every component, unlike ==
+    // which only allows f64::EPSILON
+    pub fn approx_eq(&self, other: &Vector3D, epsilon: f64) -> bool {
+        #[cfg(not(feature = "simd"))]
+        {
+            return (self.x - other.x).abs() <= epsilon
+                && (self.y - other.y).abs() <= epsilon
+                && (self.z - other.z).abs() <= epsilon;
+        }
+
+        #[cfg(feature = "simd")]
+        {
+            let difference = self.data - other.data;
+            return difference[0].abs() <= epsilon
+                && difference[1].abs() <= epsilon
+                && difference[2].abs() <= epsilon;
+        }
+    }
+
+    // Component of self parallel to other
+    pub fn project_onto(&self, other: &Vector3D) -> Vector3D {
+        return other * (self.dot(other) / other.length_squared());
+    }
+
+    // Component of self perpendicular to other, so project_onto + reject_from == self
+    pub fn reject_from(&self, other: &Vector3D) -> Vector3D {
+        return self - &self.project_onto(other);
+    }
+
+    // Angle in radians between the two directions, in [0, pi]
+    pub fn angle_between(&self, other: &Vector3D) -> f64 {
+        // Rounding can push the cosine of (anti)parallel vectors just past +-1, where acos is NaN
+        let cos_angle = self.dot(other) / (self.length() * other.length());
+        return cos_angle.clamp(-1.0, 1.0).acos();
+    }
+
+    // Rotate counter-clockwise by angle_rad when looking down the axis towards the origin
+    // (Rodrigues' rotation formula). The axis does not need to be unit length, a zero axis
+    // leaves the vector unchanged
+    pub fn rotate_around_axis(&self, axis: &Vector3D, angle_rad: f64) -> Vector3D {
+        let length = axis.length();
+        if length == 0.0 {
+            return *self;
+        }
+
+        let k = *axis / length;
+        let (sin, cos) = angle_rad.sin_cos();
+        return self * cos + k.cross(self) * sin + k * (k.dot(self) * (1.0 - cos));
+    }
 }
 
 impl Add for Vector3D {
@@ -326,6 +584,38 @@ impl Sub for Vector3D {
     }
 }
 
+impl AddAssign for Vector3D {
+    fn add_assign(&mut self, other: Vector3D) {
+        #[cfg(not(feature = "simd"))]
+        {
+            self.x += other.get_x();
+            self.y += other.get_y();
+            self.z += other.get_z();
+        }
+
+        #[cfg(feature = "simd")]
+        {
+            self.data += other.data;
+        }
+    }
+}
+
+impl SubAssign for Vector3D {
+    fn sub_assign(&mut self, other: Vector3D) {
+        #[cfg(not(feature = "simd"))]
+        {
+            self.x -= other.get_x();
+            self.y -= other.get_y();
+            self.z -= other.get_z();
+        }
+
+        #[cfg(feature = "simd")]
+        {
+            self.data -= other.data;
+        }
+    }
+}
+
 impl Mul<Vector3D> for Vector3D {
     type Output = Vector3D;
 
@@ -370,6 +660,22 @@ impl Mul<f64> for Vector3D {
     }
 }
 
+impl MulAssign<f64> for Vector3D {
+    fn mul_assign(&mut self, scale: f64) {
+        #[cfg(not(feature = "simd"))]
+        {
+            self.x *= scale;
+            self.y *= scale;
+            self.z *= scale;
+        }
+
+        #[cfg(feature = "simd")]
+        {
+            self.data *= f64x4::splat(scale);
+        }
+    }
+}
+
 impl Div for Vector3D {
     type Output = Vector3D;
 
@@ -414,6 +720,131 @@ impl Div<f64> for Vector3D {
     }
 }
 
+// Adding or subtracting a scalar broadcasts it to all three components
+impl Add<f64> for Vector3D {
+    type Output = Vector3D;
+
+    fn add(self, offset: f64) -> Vector3D {
+        #[cfg(not(feature = "simd"))]
+        {
+            return Vector3D {
+                x: self.x + offset,
+                y: self.y + offset,
+                z: self.z + offset,
+            };
+        }
+
+        // Through Vector3D::splat so the unused fourth lane stays 0
+        #[cfg(feature = "simd")]
+        {
+            return Vector3D {
+                data: self.data + Vector3D::splat(offset).data,
+            };
+        }
+    }
+}
+
+impl Sub<f64> for Vector3D {
+    type Output = Vector3D;
+
+    fn sub(self, offset: f64) -> Vector3D {
+        #[cfg(not(feature = "simd"))]
+        {
+            return Vector3D {
+                x: self.x - offset,
+                y: self.y - offset,
+                z: self.z - offset,
+            };
+        }
+
+        #[cfg(feature = "simd")]
+        {
+            return Vector3D {
+                data: self.data - Vector3D::splat(offset).data,
+            };
+        }
+    }
+}
+
+impl From<[f64; 3]> for Vector3D {
+    fn from(array: [f64; 3]) -> Vector3D {
+        return Vector3D::new(array[0], array[1], array[2]);
+    }
+}
+
+impl From<Vector3D> for [f64; 3] {
+    fn from(vec: Vector3D) -> [f64; 3] {
+        #[cfg(not(feature = "simd"))]
+        {
+            return [vec.x, vec.y, vec.z];
+        }
+
+        #[cfg(feature = "simd")]
+        {
+            return [vec.data[0], vec.data[1], vec.data[2]];
+        }
+    }
+}
+
+// Component access by axis, 0 = x, 1 = y, 2 = z
+impl Index<usize> for Vector3D {
+    type Output = f64;
+
+    fn index(&self, index: usize) -> &f64 {
+        #[cfg(not(feature = "simd"))]
+        {
+            match index {
+                0 => &self.x,
+                1 => &self.y,
+                2 => &self.z,
+                _ => panic!(
+                    "Vector3D index out of range: {} (expected 0, 1 or 2)",
+                    index
+                ),
+            }
+        }
+
+        #[cfg(feature = "simd")]
+        {
+            if index > 2 {
+                panic!(
+                    "Vector3D index out of range: {} (expected 0, 1 or 2)",
+                    index
+                );
+            }
+            &self.data[index]
+        }
+    }
+}
+
+impl IndexMut<usize> for Vector3D {
+    fn index_mut(&mut self, index: usize) -> &mut f64 {
+        #[cfg(not(feature = "simd"))]
+        {
+            match index {
+                0 => &mut self.x,
+                1 => &mut self.y,
+                2 => &mut self.z,
+                _ => panic!(
+                    "Vector3D index out of range: {} (expected 0, 1 or 2)",
+                    index
+                ),
+            }
+        }
+
+        #[cfg(feature = "simd")]
+        {
+            if index > 2 {
+                panic!(
+                    "Vector3D index out of range: {} (expected 0, 1 or 2)",
+                    index
+                );
+            }
+            &mut self.data[index]
+        }
+    }
+}
+
 impl PartialEq for Vector3D {
     fn eq(&self, other: &Vector3D) -> bool {
         #[cfg(not(feature = "simd"))]
@@ -432,6 +863,20 @@ impl PartialEq for Vector3D {
     }
 }
 
+// Prints (x, y, z) on both backends, passing precision and width through to each component
+// e.g. format!("{:.2}", vector) gives (1.00, 2.00, 3.00)
+impl fmt::Display for Vector3D {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "(")?;
+        fmt::Display::fmt(&self.get_x(), f)?;
+        write!(f, ", ")?;
+        fmt::Display::fmt(&self.get_y(), f)?;
+        write!(f, ", ")?;
+        fmt::Display::fmt(&self.get_z(), f)?;
+        write!(f, ")")
+    }
+}
+
 impl Neg for Vector3D {
     type Output = Vector3D;
 
@@ -452,6 +897,48 @@ impl Neg for Vector3D {
     }
 }
 
+// Reference variants of the operators, so borrowed vectors can be combined without
+// dereferencing them first, e.g. &a + &b
+impl Add for &Vector3D {
+    type Output = Vector3D;
+
+    fn add(self, other: &Vector3D) -> Vector3D {
+        return *self + *other;
+    }
+}
+
+impl Sub for &Vector3D {
+    type Output = Vector3D;
+
+    fn sub(self, other: &Vector3D) -> Vector3D {
+        return *self - *other;
+    }
+}
+
+impl Mul<&Vector3D> for &Vector3D {
+    type Output = Vector3D;
+
+    fn mul(self, other: &Vector3D) -> Vector3D {
+        return *self * *other;
+    }
+}
+
+impl Mul<f64> for &Vector3D {
+    type Output = Vector3D;
+
+    fn mul(self, scale: f64) -> Vector3D {
+        return *self * scale;
+    }
+}
+
+impl Neg for &Vector3D {
+    type Output = Vector3D;
+
+    fn neg(self) -> Vector3D {
+        return -*self;
+    }
+}
+
 #[test]
 fn test_gen() {
     #[cfg(feature = "simd")]
@@ -496,6 +983,18 @@ fn test_sub() {
     assert_approx_eq!(result.get_z(), -0.1);
 }
 
+#[test]
+fn test_add_sub_scalar() {
+    let vec = Vector3D::new(1.0, 2.0, 3.0);
+    assert_eq!(vec + 1.0, Vector3D::new(2.0, 3.0, 4.0));
+    assert_eq!(vec - 1.0, Vector3D::new(0.0, 1.0, 2.0));
+    assert_eq!(vec + 0.5 - 0.5, vec);
+
+    // The broadcast never touches the padding lane, so lengths are unaffected
+    let offset = Vector3D::new(0.0, 0.0, 0.0) + 2.0;
+    assert_approx_eq!(offset.length_squared(), 12.0);
+}
+
 #[test]
 fn test_mul() {
     let vec_1 = Vector3D::new(0.1, 0.25, 1.0);
@@ -545,3 +1044,270 @@ fn test_random() {
     assert!(vec_1.get_y() >= -1.0 && vec_1.get_y() <= 1.0);
     assert!(vec_1.get_z() >= -1.0 && vec_1.get_z() <= 1.0);
 }
+
+#[test]
+fn test_lerp() {
+    let vec_1 = Vector3D::new(0.0, 1.0, 2.0);
+    let vec_2 = Vector3D::new(2.0, 3.0, 6.0);
+
+    let start = vec_1.lerp(&vec_2, 0.0);
+    assert_approx_eq!(start.get_x(), 0.0);
+    assert_approx_eq!(start.get_y(), 1.0);
+    assert_approx_eq!(start.get_z(), 2.0);
+
+    let end = vec_1.lerp(&vec_2, 1.0);
+    assert_approx_eq!(end.get_x(), 2.0);
+    assert_approx_eq!(end.get_y(), 3.0);
+    assert_approx_eq!(end.get_z(), 6.0);
+
+    let middle = vec_1.lerp(&vec_2, 0.5);
+    assert_approx_eq!(middle.get_x(), 1.0);
+    assert_approx_eq!(middle.get_y(), 2.0);
+    assert_approx_eq!(middle.get_z(), 4.0);
+}
+
+#[test]
+fn test_clamp() {
+    let vec_1 = Vector3D::new(0.5, 1.5, -0.25);
+    let result = vec_1.clamp(0.0, 1.0);
+    assert_approx_eq!(result.get_x(), 0.5);
+    assert_approx_eq!(result.get_y(), 1.0);
+    assert_approx_eq!(result.get_z(), 0.0);
+}
+
+#[test]
+fn test_normalize_or_zero() {
+    let zero = Vector3D::new(0.0, 0.0, 0.0).normalize_or_zero();
+    assert_eq!(zero, Vector3D::new(0.0, 0.0, 0.0));
+    assert!(!zero.get_x().is_nan() && !zero.get_y().is_nan() && !zero.get_z().is_nan());
+    assert!(
+        Vector3D::new(1e-20, 0.0, -1e-20)
+            .normalize_or_zero()
+            .near_zero()
+    );
+
+    let vec = Vector3D::new(3.0, 0.0, -4.0);
+    assert_eq!(vec.normalize_or_zero(), vec.unit_vector());
+    assert_eq!(vec.normalize_or_zero(), Vector3D::new(0.6, 0.0, -0.8));
+}
+
+#[test]
+fn test_random_unit_vector() {
+    for _ in 0..1000 {
+        let vec_1 = Vector3D::random_unit_vector();
+        assert_approx_eq!(vec_1.length(), 1.0);
+    }
+}
+
+#[test]
+fn test_add_assign() {
+    let mut vec_1 = Vector3D::new(0.15, 0.2, 0.3);
+    vec_1 += Vector3D::new(0.25, 3.0, 0.4);
+    assert_approx_eq!(vec_1.get_x(), 0.4);
+    assert_approx_eq!(vec_1.get_y(), 3.2);
+    assert_approx_eq!(vec_1.get_z(), 0.7);
+}
+
+#[test]
+fn test_sub_assign() {
+    let mut vec_1 = Vector3D::new(0.1, 0.2, 0.3);
+    vec_1 -= Vector3D::new(0.2, 0.3, 0.4);
+    assert_approx_eq!(vec_1.get_x(), -0.1);
+    assert_approx_eq!(vec_1.get_y(), -0.1);
+    assert_approx_eq!(vec_1.get_z(), -0.1);
+}
+
+#[test]
+fn test_mul_assign() {
+    let mut vec_1 = Vector3D::new(0.1, 0.25, 1.0);
+    vec_1 *= 2.0;
+    assert_approx_eq!(vec_1.get_x(), 0.2);
+    assert_approx_eq!(vec_1.get_y(), 0.5);
+    assert_approx_eq!(vec_1.get_z(), 2.0);
+}
+
+#[test]
+fn test_index() {
+    let mut vec_1 = Vector3D::new(0.1, 0.2, 0.3);
+    assert_approx_eq!(vec_1[0], 0.1);
+    assert_approx_eq!(vec_1[1], 0.2);
+    assert_approx_eq!(vec_1[2], 0.3);
+
+    vec_1[0] = 1.0;
+    vec_1[1] = 2.0;
+    vec_1[2] = 3.0;
+    assert_approx_eq!(vec_1.get_x(), 1.0);
+    assert_approx_eq!(vec_1.get_y(), 2.0);
+    assert_approx_eq!(vec_1.get_z(), 3.0);
+}
+
+#[test]
+#[should_panic(expected = "Vector3D index out of range")]
+fn test_index_out_of_range() {
+    let vec_1 = Vector3D::new(0.1, 0.2, 0.3);
+    let _ = vec_1[3];
+}
+
+#[test]
+fn test_component_sqrt() {
+    let vec_1 = Vector3D::new(4.0, 9.0, 16.0);
+    let result = vec_1.component_sqrt();
+    assert_approx_eq!(result.get_x(), 2.0);
+    assert_approx_eq!(result.get_y(), 3.0);
+    assert_approx_eq!(result.get_z(), 4.0);
+}
+
+#[test]
+fn test_component_min_max() {
+    let vec_1 = Vector3D::new(1.0, 5.0, -2.0);
+    let vec_2 = Vector3D::new(3.0, -1.0, -2.5);
+
+    let min = vec_1.component_min(&vec_2);
+    assert_approx_eq!(min.get_x(), 1.0);
+    assert_approx_eq!(min.get_y(), -1.0);
+    assert_approx_eq!(min.get_z(), -2.5);
+
+    let max = vec_1.component_max(&vec_2);
+    assert_approx_eq!(max.get_x(), 3.0);
+    assert_approx_eq!(max.get_y(), 5.0);
+    assert_approx_eq!(max.get_z(), -2.0);
+}
+
+#[test]
+fn test_array_conversion() {
+    let vec_1 = Vector3D::from([1.0, 2.0, 3.0]);
+    assert_approx_eq!(vec_1.get_x(), 1.0);
+    assert_approx_eq!(vec_1.get_y(), 2.0);
+    assert_approx_eq!(vec_1.get_z(), 3.0);
+
+    let array: [f64; 3] = vec_1.into();
+    assert_eq!(array, [1.0, 2.0, 3.0]);
+}
+
+#[test]
+fn test_project_reject() {
+    let vec_1 = Vector3D::new(1.0, 1.0, 0.0);
+    let x_axis = Vector3D::new(1.0, 0.0, 0.0);
+
+    let projection = vec_1.project_onto(&x_axis);
+    let rejection = vec_1.reject_from(&x_axis);
+    assert_eq!(projection, Vector3D::new(1.0, 0.0, 0.0));
+    assert_eq!(rejection, Vector3D::new(0.0, 1.0, 0.0));
+    assert_eq!(projection + rejection, vec_1);
+
+    // The length of other does not matter
+    let projection = vec_1.project_onto(&(x_axis * 3.0));
+    assert_approx_eq!(projection.get_x(), 1.0);
+    assert_approx_eq!(projection.get_y(), 0.0);
+}
+
+#[test]
+fn test_random_cosine_direction() {
+    let samples = 10000;
+    let mut mean_cos_theta = 0.0;
+    for _ in 0..samples {
+        let direction = Vector3D::random_cosine_direction();
+        assert_approx_eq!(direction.length(), 1.0);
+        assert!(direction.get_z() >= 0.0);
+        mean_cos_theta += direction.get_z() / samples as f64;
+    }
+
+    // E[cos(theta)] is 2/3 for a cosine distribution, versus 1/2 for a uniform hemisphere
+    assert_approx_eq!(mean_cos_theta, 2.0 / 3.0, 0.02);
+}
+
+#[test]
+fn test_approx_eq() {
+    let vec_1 = Vector3D::new(1.0, 2.0, 3.0);
+    let vec_2 = Vector3D::new(1.0, 2.0 + 1e-10, 3.0);
+
+    assert!(vec_1 != vec_2);
+    assert!(vec_1.approx_eq(&vec_2, 1e-6));
+    assert!(!vec_1.approx_eq(&Vector3D::new(1.0, 2.0, 3.1), 1e-6));
+}
+
+#[test]
+fn test_angle_between() {
+    let x_axis = Vector3D::new(1.0, 0.0, 0.0);
+
+    assert_approx_eq!(
+        x_axis.angle_between(&Vector3D::new(0.0, 2.0, 0.0)),
+        FRAC_PI_2
+    );
+    assert_approx_eq!(x_axis.angle_between(&Vector3D::new(3.0, 0.0, 0.0)), 0.0);
+    assert_approx_eq!(x_axis.angle_between(&Vector3D::new(-0.5, 0.0, 0.0)), PI);
+    assert_approx_eq!(
+        x_axis.angle_between(&Vector3D::new(1.0, 1.0, 0.0)),
+        FRAC_PI_4
+    );
+
+    // The cosine of many (anti)parallel pairs rounds to just past +-1, which must not give NaN
+    for i in 1..100 {
+        let direction = Vector3D::new(0.1 * i as f64, 0.2, 0.7);
+        let scaled = direction * 3.0;
+        assert_approx_eq!(direction.angle_between(&scaled), 0.0, 1e-7);
+        assert_approx_eq!(direction.angle_between(&-scaled), PI, 1e-7);
+    }
+}
+
+#[test]
+fn test_rotate_around_axis() {
+    let x_axis = Vector3D::new(1.0, 0.0, 0.0);
+    let z_axis = Vector3D::new(0.0, 0.0, 1.0);
+
+    let rotated = x_axis.rotate_around_axis(&z_axis, FRAC_PI_2);
+    assert!(rotated.approx_eq(&Vector3D::new(0.0, 1.0, 0.0), 1e-12));
+
+    // The axis is normalized, and rotating half a turn about a tilted axis keeps the length
+    let rotated = x_axis.rotate_around_axis(&(z_axis * 5.0), PI);
+    assert!(rotated.approx_eq(&Vector3D::new(-1.0, 0.0, 0.0), 1e-12));
+    let v = Vector3D::new(0.3, -1.2, 2.0);
+    let rotated = v.rotate_around_axis(&Vector3D::new(1.0, 1.0, 1.0), 1.0);
+    assert_approx_eq!(rotated.length(), v.length());
+
+    // A zero angle, or an axis along the vector itself, leaves it unchanged
+    assert!(v.rotate_around_axis(&z_axis, 0.0).approx_eq(&v, 1e-12));
+    assert!(v.rotate_around_axis(&(v * 2.0), 1.3).approx_eq(&v, 1e-12));
+}
+
+#[test]
+fn test_splat() {
+    assert_eq!(Vector3D::splat(2.0), Vector3D::new(2.0, 2.0, 2.0));
+    assert_approx_eq!(Vector3D::splat(1.0).length_squared(), 3.0);
+}
+
+#[test]
+fn test_abs_signum() {
+    assert_eq!(
+        Vector3D::new(-1.0, 2.0, -3.0).abs(),
+        Vector3D::new(1.0, 2.0, 3.0)
+    );
+    assert_eq!(
+        Vector3D::new(-5.0, 0.0, 3.0).signum(),
+        Vector3D::new(-1.0, 0.0, 1.0)
+    );
+}
+
+#[test]
+fn test_display() {
+    let vector = Vector3D::new(1.0, 2.0, 3.0);
+    assert_eq!(vector.to_string(), "(1, 2, 3)");
+    assert_eq!(format!("{:.2}", vector), "(1.00, 2.00, 3.00)");
+    assert_eq!(
+        Vector3D::new(-0.5, 0.25, 1e3).to_string(),
+        "(-0.5, 0.25, 1000)"
+    );
+}
+
+#[test]
+fn test_reference_operators() {
+    let a = Vector3D::new(1.0, -2.0, 3.5);
+    let b = Vector3D::new(-0.5, 4.0, 2.0);
+    let (a_ref, b_ref) = (&a, &b);
+
+    assert_eq!(a_ref + b_ref, a + b);
+    assert_eq!(a_ref - b_ref, a - b);
+    assert_eq!(a_ref * b_ref, a * b);
+    assert_eq!(a_ref * 2.5, a * 2.5);
+    assert_eq!(-a_ref, -a);
+}
@@ -1,19 +1,237 @@
+use std::sync::Arc;
+
+use palette::Srgb;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::aabb::Aabb;
+use crate::bvh::BvhNode;
+use crate::grid::UniformGrid;
+use crate::light::PointLight;
+use crate::material::{Lambertian, Material, Metal};
 use crate::object::{Object, ObjectHitRecord};
 use crate::ray::Ray;
+use crate::sphere::Sphere;
+use crate::vector_3d::Vector3D;
+
+#[cfg(test)]
+use crate::material::DiffuseLight;
+#[cfg(test)]
+use crate::plane::Plane;
+#[cfg(test)]
+use assert_approx_eq::assert_approx_eq;
 
+// Structure World::accelerate gathers objects into
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Acceleration {
+    Bvh,         // Bounding volume hierarchy, adapts to any distribution of objects
+    UniformGrid, // Equally sized cells, quick to build and fast for evenly spread objects
+}
+
+#[derive(Clone)]
 pub struct World {
     objects: Vec<Box<dyn Object>>,
+    emissive: Vec<usize>, // Indices into objects of every light source
+    lights: Vec<PointLight>,
 }
 
 impl World {
     pub fn new() -> Self {
         World {
             objects: Vec::new(),
+            emissive: Vec::new(),
+            lights: Vec::new(),
+        }
+    }
+
+    // Three metal spheres in a row above a large gray ground sphere, seen by a camera at the
+    // origin looking down -z
+    pub fn demo_scene() -> World {
+        let mut world = World::new();
+
+        world.add(Sphere::new(
+            Vector3D::new(0.0, 0.0, -1.0),
+            0.1,
+            Material::Metal(Metal::new(Srgb::new(0.05, 0.0, 0.05), 0.0)),
+        ));
+        world.add(Sphere::new(
+            Vector3D::new(-0.5, 0.0, -1.0),
+            0.25,
+            Material::Metal(Metal::new(Srgb::new(1.0, 0.5, 1.0), 0.15)),
+        ));
+        world.add(Sphere::new(
+            Vector3D::new(0.5, 0.0, -1.0),
+            0.25,
+            Material::Metal(Metal::new(Srgb::new(0.5, 1.0, 0.5), 0.15)),
+        ));
+        world.add(Sphere::new(
+            Vector3D::new(0.0, -100.5, -1.0),
+            100.0,
+            Material::Lambertian(Lambertian::new(Srgb::new(0.5, 0.5, 0.5))),
+        ));
+
+        return world;
+    }
+
+    // The classic cover scene: count small diffuse and metal spheres scattered over a huge
+    // ground sphere, within 11 units of the origin. The same seed always gives the same scene
+    pub fn random_spheres(count: usize, seed: u64) -> World {
+        let mut world = World::new();
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        world.add(Sphere::new(
+            Vector3D::new(0.0, -1000.0, 0.0),
+            1000.0,
+            Material::Lambertian(Lambertian::new(Srgb::new(0.5, 0.5, 0.5))),
+        ));
+
+        for _ in 0..count {
+            let center = Vector3D::new(rng.gen_range(-11.0..11.0), 0.2, rng.gen_range(-11.0..11.0));
+            let color = Srgb::new(rng.r#gen::<f32>(), rng.r#gen::<f32>(), rng.r#gen::<f32>());
+
+            let material = if rng.r#gen::<f64>() < 0.8 {
+                Material::Lambertian(Lambertian::new(color))
+            } else {
+                Material::Metal(Metal::new(color, rng.gen_range(0.0..0.5)))
+            };
+            world.add(Sphere::new(center, 0.2, material));
         }
+
+        return world;
     }
 
     pub fn add<T: Object + 'static>(&mut self, object: T) {
-        self.objects.push(Box::new(object));
+        self.add_boxed(Box::new(object));
+    }
+
+    // Add an object that is already a trait object, e.g. one of many types loaded from a file
+    pub fn add_boxed(&mut self, object: Box<dyn Object>) {
+        if object
+            .material()
+            .is_some_and(|material| material.is_emissive())
+        {
+            self.emissive.push(self.objects.len());
+        }
+        self.objects.push(object);
+    }
+
+    // Gather every bounded object that is not a light into one acceleration structure, so rays
+    // skip most of them instead of testing each in turn. Lights and unbounded objects (e.g.
+    // planes) stay listed on their own, which keeps lights available for sampling
+    // Object indices change, the kept objects come first in their original order
+    pub fn accelerate(&mut self, acceleration: Acceleration) {
+        let (accelerated, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut self.objects)
+            .into_iter()
+            .partition(|object| {
+                object.bounding_box().is_some()
+                    && !object
+                        .material()
+                        .is_some_and(|material| material.is_emissive())
+            });
+
+        self.emissive.clear();
+        for object in kept {
+            self.add_boxed(object);
+        }
+        if accelerated.is_empty() {
+            return;
+        }
+
+        // Neither can fail, since there is at least one object and all of them are bounded
+        let structure: Box<dyn Object> = match acceleration {
+            Acceleration::Bvh => Box::new(BvhNode::new_parallel(accelerated).unwrap()),
+            Acceleration::UniformGrid => Box::new(UniformGrid::new(accelerated).unwrap()),
+        };
+        self.objects.push(structure);
+    }
+
+    // Take the object at index out of the world, shifting later objects down by one
+    pub fn remove(&mut self, index: usize) -> Option<Box<dyn Object>> {
+        if index >= self.objects.len() {
+            return None;
+        }
+
+        self.emissive.retain(|&emissive| emissive != index);
+        for emissive in self.emissive.iter_mut() {
+            if *emissive > index {
+                *emissive -= 1;
+            }
+        }
+        return Some(self.objects.remove(index));
+    }
+
+    // Swap the material of the object at index, returns false if there is no such object
+    // or it has no single material to replace. Passing an Arc shares the material
+    pub fn replace_material(&mut self, index: usize, material: impl Into<Arc<Material>>) -> bool {
+        let material = material.into();
+        let is_emissive = material.is_emissive();
+        let object = match self.objects.get_mut(index) {
+            Some(object) => object,
+            None => return false,
+        };
+        if !object.set_material(material) {
+            return false;
+        }
+
+        // The object may have turned into a light or stopped being one
+        self.emissive.retain(|&emissive| emissive != index);
+        if is_emissive {
+            let position = self.emissive.partition_point(|&emissive| emissive < index);
+            self.emissive.insert(position, index);
+        }
+        return true;
+    }
+
+    // Remove every object while keeping the allocation for reuse
+    pub fn clear(&mut self) {
+        self.objects.clear();
+        self.emissive.clear();
+        self.lights.clear();
+    }
+
+    pub fn add_light(&mut self, light: PointLight) {
+        self.lights.push(light);
+    }
+
+    pub fn lights(&self) -> &[PointLight] {
+        return &self.lights;
+    }
+
+    // Indices of the objects with an emissive material, for sampling lights directly
+    pub fn emissive_objects(&self) -> &[usize] {
+        return &self.emissive;
+    }
+
+    // Read access to the objects in the order they were added
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Object> {
+        return self.objects.iter().map(|object| object.as_ref());
+    }
+
+    pub fn get(&self, index: usize) -> Option<&dyn Object> {
+        return self.objects.get(index).map(|object| object.as_ref());
+    }
+
+    // Box around every object, e.g. to frame the whole scene with the camera
+    // None if the world is empty or any object is unbounded
+    pub fn bounding_box(&self) -> Option<Aabb> {
+        let mut bounds: Option<Aabb> = None;
+        for object in &self.objects {
+            let object_bounds = object.bounding_box()?;
+            bounds = match bounds {
+                Some(bounds) => Some(bounds.surrounding(&object_bounds)),
+                None => Some(object_bounds),
+            };
+        }
+        return bounds;
+    }
+
+    pub fn len(&self) -> usize {
+        return self.objects.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.objects.is_empty();
     }
 
     pub fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<ObjectHitRecord> {
@@ -27,4 +245,291 @@ impl World {
         }
         return hit_record;
     }
+
+    // Closest hit for each of four rays, e.g. a batch of camera samples
+    pub fn hit4(&self, rays: &[Ray; 4], t_min: f64, t_max: f64) -> [Option<ObjectHitRecord>; 4] {
+        let mut closest_so_far = [t_max; 4];
+        let mut hit_records = [None, None, None, None];
+        for object in &self.objects {
+            let hits = object.hit4(rays, t_min, closest_so_far);
+            for (lane, hit) in hits.into_iter().enumerate() {
+                if let Some(hit) = hit {
+                    closest_so_far[lane] = hit.t;
+                    hit_records[lane] = Some(hit);
+                }
+            }
+        }
+        return hit_records;
+    }
+
+    // Occlusion query for shadow rays, stops at the first hit instead of finding the closest
+    pub fn hit_any(&self, ray: &Ray, t_min: f64, t_max: f64) -> bool {
+        return self
+            .objects
+            .iter()
+            .any(|object| object.hit(ray, t_min, t_max).is_some());
+    }
+}
+
+#[cfg(test)]
+fn test_sphere(center: Vector3D) -> Sphere {
+    Sphere::new(
+        center,
+        0.5,
+        Material::Lambertian(Lambertian::new(Srgb::new(0.5, 0.5, 0.5))),
+    )
+}
+
+#[test]
+fn test_world_len_and_clear() {
+    let mut world = World::new();
+    assert!(world.is_empty());
+
+    world.add(test_sphere(Vector3D::new(0.0, 0.0, -1.0)));
+    world.add(test_sphere(Vector3D::new(1.0, 0.0, -1.0)));
+    assert_eq!(world.len(), 2);
+    assert!(!world.is_empty());
+
+    world.clear();
+    assert_eq!(world.len(), 0);
+    assert!(world.is_empty());
+}
+
+#[test]
+fn test_world_hit_any() {
+    let mut world = World::new();
+    world.add(test_sphere(Vector3D::new(0.0, 0.0, -1.0)));
+    world.add(test_sphere(Vector3D::new(0.0, 0.0, -3.0)));
+
+    let origin = Vector3D::new(0.0, 0.0, 0.0);
+    let rays = [
+        Ray::new(origin, Vector3D::new(0.0, 0.0, -1.0)),
+        Ray::new(origin, Vector3D::new(0.0, 0.0, 1.0)),
+        Ray::new(origin, Vector3D::new(0.3, 0.0, -1.0)),
+        Ray::new(origin, Vector3D::new(1.0, 1.0, -1.0)),
+        Ray::new(Vector3D::new(0.0, 0.0, -2.0), Vector3D::new(0.0, 0.0, -1.0)),
+    ];
+
+    for ray in rays.iter() {
+        assert_eq!(
+            world.hit_any(ray, 0.001, f64::MAX),
+            world.hit(ray, 0.001, f64::MAX).is_some()
+        );
+    }
+
+    // The range limits which hits count
+    assert!(!world.hit_any(&rays[0], 0.001, 0.4));
+}
+
+#[test]
+fn test_world_emissive_objects() {
+    let mut world = World::new();
+    world.add(test_sphere(Vector3D::new(0.0, 0.0, -1.0)));
+    world.add(Sphere::new(
+        Vector3D::new(0.0, 2.0, -1.0),
+        0.5,
+        Material::DiffuseLight(DiffuseLight::new(Srgb::new(4.0, 4.0, 4.0))),
+    ));
+    world.add(test_sphere(Vector3D::new(1.0, 0.0, -1.0)));
+
+    assert_eq!(world.emissive_objects(), &[1]);
+
+    world.clear();
+    assert!(world.emissive_objects().is_empty());
+}
+
+#[test]
+fn test_world_iter_and_get() {
+    let mut world = World::new();
+    world.add(test_sphere(Vector3D::new(0.0, 0.0, -1.0)));
+    world.add(test_sphere(Vector3D::new(1.0, 0.0, -1.0)));
+    world.add(test_sphere(Vector3D::new(2.0, 0.0, -1.0)));
+
+    assert_eq!(world.iter().count(), 3);
+    assert!(world.iter().all(|object| object.bounding_box().is_some()));
+
+    // Objects keep the order they were added in
+    let third = world.get(2).expect("World should have a third object");
+    let bounds = third.bounding_box().unwrap();
+    assert_approx_eq!(bounds.min().get_x(), 1.5);
+    assert!(world.get(3).is_none());
+}
+
+#[test]
+fn test_world_remove() {
+    let mut world = World::new();
+    world.add(test_sphere(Vector3D::new(0.0, 0.0, -1.0)));
+    world.add(Sphere::new(
+        Vector3D::new(1.0, 0.0, -1.0),
+        0.5,
+        Material::DiffuseLight(DiffuseLight::new(Srgb::new(4.0, 4.0, 4.0))),
+    ));
+
+    let removed = world.remove(0).expect("World has a first object");
+    assert_approx_eq!(removed.bounding_box().unwrap().min().get_x(), -0.5);
+    assert_eq!(world.len(), 1);
+    assert!(world.remove(1).is_none());
+
+    // The remaining object was at index 1, and is still known to be a light
+    assert_approx_eq!(
+        world.get(0).unwrap().bounding_box().unwrap().min().get_x(),
+        0.5
+    );
+    assert_eq!(world.emissive_objects(), &[0]);
+}
+
+#[test]
+fn test_world_replace_material() {
+    let mut world = World::new();
+    world.add(test_sphere(Vector3D::new(0.0, 0.0, -1.0)));
+    world.add(test_sphere(Vector3D::new(1.0, 0.0, -1.0)));
+
+    // Both objects end up sharing the one light material
+    let light = Arc::new(Material::DiffuseLight(DiffuseLight::new(Srgb::new(
+        4.0, 4.0, 4.0,
+    ))));
+    assert!(world.replace_material(1, light.clone()));
+    assert!(world.replace_material(0, light.clone()));
+    assert_eq!(world.emissive_objects(), &[0, 1]);
+    for index in [0, 1] {
+        let material = world.get(index).unwrap().material().unwrap();
+        assert!(std::ptr::eq(material, light.as_ref()));
+    }
+
+    let metal = Material::Metal(Metal::new(Srgb::new(0.8, 0.8, 0.8), 0.0));
+    assert!(world.replace_material(0, metal));
+    assert!(matches!(
+        world.get(0).unwrap().material(),
+        Some(Material::Metal(_))
+    ));
+    assert_eq!(world.emissive_objects(), &[1]);
+
+    assert!(!world.replace_material(
+        2,
+        Material::Lambertian(Lambertian::new(Srgb::new(0.5, 0.5, 0.5)))
+    ));
+}
+
+#[test]
+fn test_world_bounding_box() {
+    let mut world = World::new();
+    assert!(world.bounding_box().is_none());
+
+    world.add(test_sphere(Vector3D::new(-2.0, 0.0, -1.0)));
+    world.add(test_sphere(Vector3D::new(1.0, 3.0, -4.0)));
+
+    let bounds = world.bounding_box().expect("Spheres are bounded");
+    assert_eq!(bounds.min(), Vector3D::new(-2.5, -0.5, -4.5));
+    assert_eq!(bounds.max(), Vector3D::new(1.5, 3.5, -0.5));
+    assert_eq!(bounds.center(), Vector3D::new(-0.5, 1.5, -2.5));
+
+    // A single infinite plane makes the whole scene unbounded
+    world.add(Plane::new(
+        Vector3D::new(0.0, -1.0, 0.0),
+        Vector3D::new(0.0, 1.0, 0.0),
+        Material::Lambertian(Lambertian::new(Srgb::new(0.5, 0.5, 0.5))),
+    ));
+    assert!(world.bounding_box().is_none());
+}
+
+#[test]
+fn test_world_add_boxed() {
+    let mut world = World::new();
+    let sphere: Box<dyn Object> = Box::new(Sphere::new(
+        Vector3D::new(0.0, 0.0, -2.0),
+        0.5,
+        Material::DiffuseLight(DiffuseLight::new(Srgb::new(1.0, 1.0, 1.0))),
+    ));
+    world.add_boxed(sphere);
+
+    let ray = Ray::new(Vector3D::new(0.0, 0.0, 0.0), Vector3D::new(0.0, 0.0, -1.0));
+    let hit = world
+        .hit(&ray, 0.001, f64::MAX)
+        .expect("Ray should hit the boxed sphere");
+    assert_approx_eq!(hit.t, 1.5);
+    assert_eq!(world.emissive_objects(), &[0]);
+}
+
+#[test]
+fn test_world_random_spheres_deterministic() {
+    let first = World::random_spheres(20, 42);
+    let second = World::random_spheres(20, 42);
+    assert_eq!(first.len(), 21);
+    assert_eq!(second.len(), first.len());
+
+    let boxes = |world: &World| -> Vec<Option<Aabb>> {
+        world.iter().map(|object| object.bounding_box()).collect()
+    };
+    assert_eq!(boxes(&first), boxes(&second));
+    assert_ne!(boxes(&first), boxes(&World::random_spheres(20, 43)));
+
+    assert_eq!(World::demo_scene().len(), 4);
+}
+
+#[test]
+fn test_world_clone() {
+    let mut world = World::new();
+    world.add(test_sphere(Vector3D::new(0.0, 0.0, -1.0)));
+    world.add(test_sphere(Vector3D::new(1.0, 0.0, -3.0)));
+    let snapshot = world.clone();
+
+    // Changing the original leaves the clone as it was
+    world.clear();
+    assert_eq!(snapshot.len(), 2);
+
+    let copy = snapshot.clone();
+    let origin = Vector3D::new(0.0, 0.0, 0.0);
+    for direction in [
+        Vector3D::new(0.0, 0.0, -1.0),
+        Vector3D::new(1.0, 0.0, -3.0),
+        Vector3D::new(0.0, 1.0, 0.0),
+    ] {
+        let ray = Ray::new(origin, direction);
+        let expected = snapshot.hit(&ray, 0.001, f64::MAX).map(|hit| hit.t);
+        let actual = copy.hit(&ray, 0.001, f64::MAX).map(|hit| hit.t);
+        assert_eq!(actual, expected);
+    }
+    assert!(
+        copy.hit(
+            &Ray::new(origin, Vector3D::new(0.0, 0.0, -1.0)),
+            0.001,
+            f64::MAX
+        )
+        .is_some()
+    );
+}
+
+#[test]
+fn test_world_accelerate() {
+    let mut world = World::random_spheres(200, 9);
+    world.add(Plane::new(
+        Vector3D::new(0.0, -0.5, 0.0),
+        Vector3D::new(0.0, 1.0, 0.0),
+        Material::Lambertian(Lambertian::new(Srgb::new(0.5, 0.5, 0.5))),
+    ));
+    world.add(Sphere::new(
+        Vector3D::new(0.0, 5.0, 0.0),
+        1.0,
+        Material::DiffuseLight(DiffuseLight::new(Srgb::new(4.0, 4.0, 4.0))),
+    ));
+
+    let rays: Vec<Ray> = (0..500)
+        .map(|_| Ray::new(Vector3D::new(0.0, 2.0, 14.0), Vector3D::random(-1.0, 1.0)))
+        .collect();
+    let hits = |world: &World| -> Vec<Option<f64>> {
+        rays.iter()
+            .map(|ray| world.hit(ray, 0.001, f64::MAX).map(|hit| hit.t))
+            .collect()
+    };
+    let expected = hits(&world);
+
+    for acceleration in [Acceleration::Bvh, Acceleration::UniformGrid] {
+        let mut accelerated = world.clone();
+        accelerated.accelerate(acceleration);
+
+        // The plane and the light stay on their own, everything else is one object
+        assert_eq!(accelerated.len(), 3);
+        assert_eq!(accelerated.emissive_objects(), &[1]);
+        assert_eq!(hits(&accelerated), expected);
+    }
 }